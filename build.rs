@@ -1,39 +1,154 @@
 extern crate dunce;
 use std::env;
-//use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// the canonical directory given by the first set environment variable of `vars`
+fn dir_from_env(vars: &[&str]) -> Option<PathBuf> {
+    vars.iter().for_each(|var| println!("cargo:rerun-if-env-changed={}", var));
+    vars.iter().find_map(|var| {
+        let dir = env::var(var).ok().filter(|dir| !dir.is_empty())?;
+        match dunce::canonicalize(&dir) {
+            Ok(path) => Some(path),
+            Err(err) => panic!("Error:: {}={} is not an accessible directory: {}", var, dir, err),
+        }
+    })
+}
+
+fn has_library(dir: &Path, name: &str) -> bool {
+    ["so", "dylib", "a", "lib"].iter().any(|ext| {
+        let file = if *ext == "lib" {format!("{}.lib", name)} else {format!("lib{}.{}", name, ext)};
+        dir.join(file).exists()
+    })
+}
+
+/// `dir` or its `lib`/`lib64` subdirectory holding the library `name`
+fn library_dir(dir: &Path, name: &str) -> Option<PathBuf> {
+    [dir.to_path_buf(), dir.join("lib"), dir.join("lib64")].into_iter()
+        .find(|path| has_library(path, name))
+}
+
+fn link_search(dir: &Path) {
+    println!("cargo:rustc-link-search=native={}", dir.display());
+}
+
+/// Find libcint, in the order of `LIBCINT_DIR` (or the legacy `REST_CINT_DIR`), pkg-config and
+/// the system library directories, and link it.
+#[cfg(all(feature = "libcint", not(feature = "vendored")))]
+fn link_libcint() {
+    if let Some(dir) = dir_from_env(&["LIBCINT_DIR", "REST_CINT_DIR"]) {
+        match library_dir(&dir, "cint") {
+            Some(lib_dir) => link_search(&lib_dir),
+            None => panic!("Error:: libcint is not found in {} (nor in its lib/ or lib64/)", dir.display()),
+        }
+        println!("cargo:rustc-link-lib=cint");
+        return
+    }
+    // pkg-config emits the search path and the library itself
+    if pkg_config::Config::new().probe("libcint").is_ok() {
+        return
+    }
+    let system_dirs = ["/usr/local/lib", "/usr/local/lib64", "/usr/lib", "/usr/lib64",
+        "/usr/lib/x86_64-linux-gnu", "/usr/lib/aarch64-linux-gnu", "/opt/homebrew/lib", "/opt/local/lib"];
+    let ld_dirs: Vec<PathBuf> = ["LD_LIBRARY_PATH", "DYLD_LIBRARY_PATH", "LIBRARY_PATH"].iter()
+        .filter_map(env::var_os)
+        .flat_map(|paths| env::split_paths(&paths).collect::<Vec<_>>())
+        .collect();
+    match ld_dirs.into_iter().chain(system_dirs.iter().map(PathBuf::from)).find(|dir| has_library(dir, "cint")) {
+        Some(dir) => {
+            link_search(&dir);
+            println!("cargo:rustc-link-lib=cint");
+        },
+        None => panic!("Error:: libcint cannot be found. Install libcint (https://github.com/sunqm/libcint) and \
+                        either set LIBCINT_DIR to its installation (or library) directory, make `pkg-config libcint` \
+                        resolvable via PKG_CONFIG_PATH, or install it to a system library directory."),
+    }
+}
+
+/// the release of libcint that the submodule `vendor/libcint` is pinned to
+#[cfg(feature = "vendored")]
+const LIBCINT_VERSION: &str = "6.1.2";
+
+/// the version `major.minor.patch` declared by the `CMakeLists.txt` of a libcint source tree
+#[cfg(feature = "vendored")]
+fn libcint_source_version(src_dir: &Path) -> Option<String> {
+    let cmake_lists = std::fs::read_to_string(src_dir.join("CMakeLists.txt")).ok()?;
+    let part = |name: &str| {
+        let key = format!("set(cint_VERSION_{} ", name);
+        let start = cmake_lists.find(&key)? + key.len();
+        let value = cmake_lists[start..].split(')').next()?;
+        Some(value.trim().trim_matches('"').to_string())
+    };
+    Some(format!("{}.{}.{}", part("MAJOR")?, part("MINOR")?, part("PATCH")?))
+}
+
+/// Build the bundled libcint with cmake and link it statically (feature `vendored`).
+///
+/// The source tree is the git submodule `vendor/libcint`, pinned to the release `LIBCINT_VERSION`
+/// and shipped in the package; `LIBCINT_SRC_DIR` overrides it with another tree. The features
+/// `with-range-coulomb`, `with-f12`, `with-gtg`, `with-4c1e` and `pypzpx` switch on the cmake
+/// options of the same names.
+#[cfg(feature = "vendored")]
+fn build_vendored_libcint() {
+    println!("cargo:rerun-if-env-changed=LIBCINT_SRC_DIR");
+    let (src_dir, overridden) = match env::var("LIBCINT_SRC_DIR") {
+        Ok(dir) if !dir.is_empty() => (PathBuf::from(dir), true),
+        _ => (PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join("vendor").join("libcint"), false),
+    };
+    if !src_dir.join("CMakeLists.txt").exists() {
+        if overridden {
+            panic!("Error:: LIBCINT_SRC_DIR={} is not a libcint source tree (no CMakeLists.txt found)", src_dir.display())
+        }
+        panic!("Error:: the bundled libcint is missing in {}; run `git submodule update --init vendor/libcint` \
+                in a git checkout of this crate, or set LIBCINT_SRC_DIR to a libcint source tree.", src_dir.display())
+    }
+    match libcint_source_version(&src_dir) {
+        Some(version) if version == LIBCINT_VERSION => {},
+        Some(version) if !overridden => panic!("Error:: vendor/libcint is libcint {}, but this crate is pinned to {}; \
+            run `git submodule update vendor/libcint`", version, LIBCINT_VERSION),
+        Some(version) => println!("cargo:warning=LIBCINT_SRC_DIR is libcint {}, not the pinned {}", version, LIBCINT_VERSION),
+        None => println!("cargo:warning=the version of libcint in {} cannot be read from its CMakeLists.txt", src_dir.display()),
+    }
+    println!("cargo:rerun-if-changed={}", src_dir.join("CMakeLists.txt").display());
+    println!("cargo:rerun-if-changed={}", src_dir.join("src").display());
+    let on_off = |feature: &str| if env::var_os(feature).is_some() {"ON"} else {"OFF"};
+    let dst = cmake::Config::new(&src_dir)
+        .define("BUILD_SHARED_LIBS", "OFF")
+        .define("ENABLE_EXAMPLE", "OFF")
+        .define("ENABLE_TEST", "OFF")
+        .define("WITH_RANGE_COULOMB", on_off("CARGO_FEATURE_WITH_RANGE_COULOMB"))
+        .define("WITH_F12", on_off("CARGO_FEATURE_WITH_F12"))
+        .define("WITH_GTG", on_off("CARGO_FEATURE_WITH_GTG"))
+        .define("WITH_4C1E", on_off("CARGO_FEATURE_WITH_4C1E"))
+        .define("PYPZPX", on_off("CARGO_FEATURE_PYPZPX"))
+        .define("CMAKE_POSITION_INDEPENDENT_CODE", "ON")
+        .build();
+    ["lib", "lib64"].iter().map(|dir| dst.join(dir)).filter(|dir| dir.exists()).for_each(|dir| link_search(&dir));
+    println!("cargo:rustc-link-lib=static=cint");
+    // the math library used by the static libcint
+    if env::var("CARGO_CFG_TARGET_OS").is_ok_and(|os| os != "windows" && os != "macos") {
+        println!("cargo:rustc-link-lib=m");
+    }
+}
 
 fn main() {
-    //let root = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap());
-
-    let external_dir = if let Ok(external_dir) = env::var("REST_EXT_DIR") {
-        external_dir
-    } else {"".to_string()};
-    let blas_dir = if let Ok(blas_dir) = env::var("REST_BLAS_DIR") {
-        blas_dir
-    } else {"".to_string()};
-    let cint_dir = if let Ok(cint_dir) = env::var("REST_CINT_DIR") {
-        cint_dir
-    } else {"".to_string()};
-    let xc_dir = if let Ok(xc_dir) = env::var("REST_XC_DIR") {
-        xc_dir
-    } else {"".to_string()};
-    let hdf5_dir = if let Ok(hdf5_dir) = env::var("REST_HDF5_DIR") {
-        hdf5_dir
-    } else {"".to_string()};
-
-    let library_names = ["restmatr","openblas","xc","hdf5","rest2fch"];
-    library_names.iter().for_each(|name| {
-        println!("cargo:rustc-link-lib={}",*name);
-    });
-    let library_path = [
-        //dunce::canonicalize("/home/igor/Documents/Package-Pool/rest_workspace/rest/src/dft/libxc").unwrap(),
-        dunce::canonicalize(external_dir).unwrap(),
-        dunce::canonicalize(blas_dir).unwrap(),
-        dunce::canonicalize(cint_dir).unwrap(),
-        dunce::canonicalize(hdf5_dir).unwrap(),
-        dunce::canonicalize(xc_dir).unwrap(),
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "vendored")]
+    build_vendored_libcint();
+    #[cfg(all(feature = "libcint", not(feature = "vendored")))]
+    link_libcint();
+
+    // the libraries of the REST workspace, opt-in by the features of the same names
+    let optional_libraries = [
+        ("restmatr", "CARGO_FEATURE_RESTMATR", vec!["REST_EXT_DIR"]),
+        ("openblas", "CARGO_FEATURE_OPENBLAS", vec!["REST_BLAS_DIR"]),
+        ("xc", "CARGO_FEATURE_XC", vec!["REST_XC_DIR"]),
+        ("hdf5", "CARGO_FEATURE_HDF5", vec!["HDF5_DIR", "REST_HDF5_DIR"]),
+        ("rest2fch", "CARGO_FEATURE_REST2FCH", vec!["REST_EXT_DIR"]),
     ];
-    library_path.iter().for_each(|path| {
-        println!("cargo:rustc-link-search=native={}",env::join_paths(&[path]).unwrap().to_str().unwrap())
+    optional_libraries.iter().filter(|(_, feature, _)| env::var_os(feature).is_some()).for_each(|(name, _, vars)| {
+        if let Some(dir) = dir_from_env(vars) {
+            link_search(&library_dir(&dir, name).unwrap_or(dir));
+        }
+        println!("cargo:rustc-link-lib={}", name);
     });
-}
\ No newline at end of file
+}
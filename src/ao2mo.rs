@@ -0,0 +1,317 @@
+//! Four-index transformation of the ERIs from AOs to MOs.
+//!
+//! For four sets of orbitals `C1`, `C2`, `C3`, `C4` (each `column-major` `[nao, n_x]`),
+//!
+//! (pq|rs) = \sum_{ijkl} C1_{ip} C2_{jq} (ij|kl) C3_{kr} C4_{ls}
+//!
+//! is computed in two halves, so that arbitrary orbital sets, e.g. (ij|ab), (ia|jb) or (pq|rs), share
+//! one engine:
+//! 1. the AO integrals ("int2e" of any `IntegralEngine`) are evaluated for batches of `kl` shell pairs and all
+//!    `ij` shell pairs (`i>=j`, `k>=l`), and transformed immediately to the half-transformed `(pq|kl)`;
+//! 2. the half-transformed integrals are read back in blocks of `pq` and transformed to `(pq|rs)`.
+//!
+//! The batches are sized by `Ao2moOptions::max_memory`. If the half-transformed integrals exceed half of
+//! it, they are spilled to a temporary file, written batch by batch and read block by block.
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::PathBuf;
+
+use crate::{CINTR2CDATA, linalg};
+use crate::engine::IntegralEngine;
+use crate::screening::schwarz_screening;
+
+#[derive(Clone,Debug)]
+pub struct Ao2moOptions {
+    /// the memory for the AO batches and the half-transformed integrals, in MB
+    pub max_memory: usize,
+    /// the directory of the temporary file, `std::env::temp_dir()` if `None`
+    pub tmp_dir: Option<PathBuf>,
+    /// the Schwarz threshold of the AO shell quartets
+    pub schwarz_threshold: f64,
+}
+
+impl Default for Ao2moOptions {
+    fn default() -> Self {
+        Ao2moOptions {max_memory: 4000, tmp_dir: None, schwarz_threshold: 1.0e-14}
+    }
+}
+
+#[derive(Clone,Debug,Default,PartialEq)]
+pub struct Ao2moStats {
+    /// whether the half-transformed integrals were spilled to disk
+    pub spilled: bool,
+    pub n_kl_batches: usize,
+    pub n_pq_blocks: usize,
+}
+
+/// The half-transformed integrals `(pq|kl)`, stored by batches of `[nkl_batch, n12]` with `kl` the fastest.
+enum HalfStore {
+    Memory(Vec<Vec<f64>>),
+    Disk {path: PathBuf, file: File, offsets: Vec<u64>},
+}
+
+impl HalfStore {
+    fn new(spill: bool, tmp_dir: &Option<PathBuf>) -> HalfStore {
+        if !spill {return HalfStore::Memory(vec![])}
+        let dir = tmp_dir.clone().unwrap_or_else(std::env::temp_dir);
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+        let path = dir.join(format!("rest_libcint_ao2mo_{}_{}.tmp", std::process::id(), nanos));
+        let file = OpenOptions::new().read(true).write(true).create_new(true).open(&path)
+            .unwrap_or_else(|e| panic!("Error:: cannot create the temporary file {:?} for ao2mo: {}", path, e));
+        HalfStore::Disk {path, file, offsets: vec![]}
+    }
+
+    fn push(&mut self, batch: Vec<f64>) {
+        match self {
+            HalfStore::Memory(batches) => batches.push(batch),
+            HalfStore::Disk {path, file, offsets} => {
+                let offset = file.seek(SeekFrom::End(0)).unwrap();
+                let bytes: Vec<u8> = batch.iter().flat_map(|x| x.to_le_bytes()).collect();
+                file.write_all(&bytes).unwrap_or_else(|e| panic!("Error:: cannot write the temporary file {:?}: {}", path, e));
+                offsets.push(offset);
+            },
+        }
+    }
+
+    /// the elements `range` of the batch `ibatch`
+    fn read(&mut self, ibatch: usize, range: Range<usize>) -> Vec<f64> {
+        match self {
+            HalfStore::Memory(batches) => batches[ibatch][range].to_vec(),
+            HalfStore::Disk {path, file, offsets} => {
+                let mut bytes = vec![0u8; 8*range.len()];
+                file.seek(SeekFrom::Start(offsets[ibatch] + 8*range.start as u64)).unwrap();
+                file.read_exact(&mut bytes).unwrap_or_else(|e| panic!("Error:: cannot read the temporary file {:?}: {}", path, e));
+                bytes.chunks_exact(8).map(|b| f64::from_le_bytes(b.try_into().unwrap())).collect()
+            },
+        }
+    }
+}
+
+impl Drop for HalfStore {
+    fn drop(&mut self) {
+        if let HalfStore::Disk {path, ..} = self {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// `C_a^T M C_b` for the symmetric or general `[nao, nao]` matrix `M`
+fn transform_square(square: &[f64], ca: &[f64], na: usize, cb: &[f64], nb: usize, nao: usize) -> Vec<f64> {
+    linalg::matmul_tn(ca, &linalg::matmul(square, cb, nao, nao, nb), na, nao, nb)
+}
+
+impl CINTR2CDATA {
+    /// Transform the ERIs to the orbitals `orbs` and pass the `(pq|rs)` by blocks of `pq` to `f(pq_range, block)`,
+    /// with `pq = p + n1*q`, `rs = r + n3*s` and `block` of `[pq_range.len(), n3*n4]`.
+    ///
+    /// The 2e optimizer is (re-)initialized by this driver.
+    pub fn ao2mo_for_each_block<F>(&mut self, orbs: [&[f64];4], options: &Ao2moOptions, f: F) -> Ao2moStats
+    where F: FnMut(Range<usize>, &[f64]) {
+        ao2mo_for_each_block(self, orbs, options, f)
+    }
+
+    /// `(pq|rs)` of `[n1*n2, n3*n4]` for the orbitals `orbs` (see `ao2mo_for_each_block`).
+    pub fn ao2mo_general(&mut self, orbs: [&[f64];4], options: &Ao2moOptions) -> Vec<f64> {
+        ao2mo_general(self, orbs, options)
+    }
+
+    /// `(pq|rs)` of `[nmo*nmo, nmo*nmo]` for one set of orbitals `mo_coeff` of `[nao, nmo]`.
+    pub fn ao2mo_full(&mut self, mo_coeff: &[f64], options: &Ao2moOptions) -> Vec<f64> {
+        ao2mo_full(self, mo_coeff, options)
+    }
+}
+
+/// `CINTR2CDATA::ao2mo_for_each_block` for any engine: the `(pq|rs)` of the orbitals `orbs` by blocks of `pq`.
+///
+/// Only the Schwarz screening applies. "int2e" is prepared by this driver.
+pub fn ao2mo_for_each_block<E, F>(engine: &mut E, orbs: [&[f64];4], options: &Ao2moOptions, mut f: F) -> Ao2moStats
+where E: IntegralEngine, F: FnMut(Range<usize>, &[f64]) {
+    let ao_loc = engine.ao_loc();
+    let nbas = engine.nbas();
+    let nao = ao_loc[nbas];
+    let npair_ao = nao*(nao+1)/2;
+    let nmo: Vec<usize> = orbs.iter().map(|c| {
+        if c.len() % nao.max(1) != 0 {panic!("Error:: the orbital coefficients of {} elements do not match {} AOs", c.len(), nao)};
+        c.len()/nao.max(1)
+    }).collect();
+    let (n12, n34) = (nmo[0]*nmo[1], nmo[2]*nmo[3]);
+    let max_words = options.max_memory*1_000_000/8;
+
+    // the AO pairs (k,l) with k>=l in the order of the shell pairs
+    let shell_pairs: Vec<(usize,usize)> = (0..nbas).flat_map(|k| (0..=k).map(move |l| (k,l))).collect();
+    let pair_columns = |(k,l): (usize,usize)| -> Vec<(usize,usize)> {
+        let mut cols = vec![];
+        for ll in ao_loc[l]..ao_loc[l+1] {
+            for kk in ao_loc[k]..ao_loc[k+1] {if kk >= ll {cols.push((kk,ll))}};
+        }
+        cols
+    };
+    let nkl = npair_ao;
+    let spill = n12*nkl > max_words/2;
+    let mut store = HalfStore::new(spill, &options.tmp_dir);
+    let mut stats = Ao2moStats {spilled: spill, ..Ao2moStats::default()};
+
+    let screening = schwarz_screening(engine, options.schwarz_threshold);
+    engine.prepare("int2e");
+    let mut buf = vec![0.0; engine.max_block_size("int2e")];
+    let mut workspace = engine.workspace("int2e");
+
+    // 1. (ij|kl) -> (pq|kl) by batches of kl shell pairs
+    let mut kl_ao: Vec<(usize,usize)> = vec![];
+    let mut batch_cols: Vec<(usize,usize)> = vec![];
+    let mut batch_start = 0;
+    let mut ipair = 0;
+    while ipair < shell_pairs.len() {
+        let mut batch = vec![];
+        let mut ncol = 0;
+        while ipair < shell_pairs.len() {
+            let cols = pair_columns(shell_pairs[ipair]);
+            if !batch.is_empty() && (ncol + cols.len())*(npair_ao + n12) > max_words/2 {break};
+            ncol += cols.len();
+            batch.push(shell_pairs[ipair]);
+            kl_ao.extend(cols);
+            ipair += 1;
+        }
+        // the AO integrals of [npair_ao, ncol]
+        let mut ao_block = vec![0.0; npair_ao*ncol];
+        let mut col0 = 0;
+        for (k,l) in batch.iter() {
+            let (k0, dk) = (ao_loc[*k], ao_loc[k+1]-ao_loc[*k]);
+            let l0 = ao_loc[*l];
+            let cols = pair_columns((*k,*l));
+            for i in 0..nbas {
+                for j in 0..=i {
+                    if screening.skip(i as i32, j as i32, *k as i32, *l as i32) {continue};
+                    engine.eval_4c2e(i, j, *k, *l, &mut buf, &mut workspace);
+                    let (i0, di) = (ao_loc[i], ao_loc[i+1]-ao_loc[i]);
+                    let (j0, dj) = (ao_loc[j], ao_loc[j+1]-ao_loc[j]);
+                    for (icol, (kk, ll)) in cols.iter().enumerate() {
+                        let (kk, ll) = (kk-k0, ll-l0);
+                        for jj in 0..dj {
+                            for ii in 0..di {
+                                if i0+ii < j0+jj {continue};
+                                let pq = (i0+ii)*(i0+ii+1)/2 + j0+jj;
+                                ao_block[pq + npair_ao*(col0+icol)] = buf[ii + di*(jj + dj*(kk + dk*ll))];
+                            }
+                        }
+                    }
+                }
+            }
+            col0 += cols.len();
+        }
+        // (pq|kl) of [ncol, n12]
+        let mut half = vec![0.0; ncol*n12];
+        let mut square = vec![0.0; nao*nao];
+        for c in 0..ncol {
+            let col = &ao_block[npair_ao*c..npair_ao*(c+1)];
+            for j in 0..nao {
+                for i in j..nao {
+                    let v = col[i*(i+1)/2 + j];
+                    square[i + nao*j] = v;
+                    square[j + nao*i] = v;
+                }
+            }
+            let t = transform_square(&square, orbs[0], nmo[0], orbs[1], nmo[1], nao);
+            t.iter().enumerate().for_each(|(pq, v)| half[c + ncol*pq] = *v);
+        }
+        store.push(half);
+        batch_cols.push((batch_start, ncol));
+        batch_start += ncol;
+        stats.n_kl_batches += 1;
+    }
+
+    // 2. (pq|kl) -> (pq|rs) by blocks of pq
+    let nrow_max = (max_words/2/(nkl + nao*nao + n34).max(1)).max(1);
+    let mut pq0 = 0;
+    while pq0 < n12 {
+        let pq1 = (pq0 + nrow_max).min(n12);
+        let nrows = pq1 - pq0;
+        // (pq|kl) of [nkl, nrows]
+        let mut rows = vec![0.0; nkl*nrows];
+        for (ibatch, (start, ncol)) in batch_cols.iter().enumerate() {
+            let data = store.read(ibatch, ncol*pq0..ncol*pq1);
+            for r in 0..nrows {
+                rows[nkl*r + start..nkl*r + start + ncol].copy_from_slice(&data[ncol*r..ncol*(r+1)]);
+            }
+        }
+        let mut block = vec![0.0; nrows*n34];
+        let mut square = vec![0.0; nao*nao];
+        for r in 0..nrows {
+            kl_ao.iter().zip(rows[nkl*r..nkl*(r+1)].iter()).for_each(|((k,l), v)| {
+                square[k + nao*l] = *v;
+                square[l + nao*k] = *v;
+            });
+            let t = transform_square(&square, orbs[2], nmo[2], orbs[3], nmo[3], nao);
+            t.iter().enumerate().for_each(|(rs, v)| block[r + nrows*rs] = *v);
+        }
+        f(pq0..pq1, &block);
+        stats.n_pq_blocks += 1;
+        pq0 = pq1;
+    }
+    stats
+}
+
+/// `CINTR2CDATA::ao2mo_general` for any engine: `(pq|rs)` of `[n1*n2, n3*n4]` for the orbitals `orbs`.
+pub fn ao2mo_general<E: IntegralEngine>(engine: &mut E, orbs: [&[f64];4], options: &Ao2moOptions) -> Vec<f64> {
+    let nao = engine.nao().max(1);
+    let n12 = orbs[0].len()/nao*(orbs[1].len()/nao);
+    let n34 = orbs[2].len()/nao*(orbs[3].len()/nao);
+    let mut eri = vec![0.0; n12*n34];
+    ao2mo_for_each_block(engine, orbs, options, |pq_range, block| {
+        let nrows = pq_range.len();
+        for rs in 0..n34 {
+            eri[pq_range.start + n12*rs..pq_range.end + n12*rs].copy_from_slice(&block[nrows*rs..nrows*(rs+1)]);
+        }
+    });
+    eri
+}
+
+/// `CINTR2CDATA::ao2mo_full` for any engine: `(pq|rs)` of `[nmo*nmo, nmo*nmo]` for one set of orbitals `mo_coeff`.
+pub fn ao2mo_full<E: IntegralEngine>(engine: &mut E, mo_coeff: &[f64], options: &Ao2moOptions) -> Vec<f64> {
+    ao2mo_general(engine, [mo_coeff; 4], options)
+}
+
+#[test]
+pub fn test_ao2mo() {
+    let mut cint_data = crate::test_h2_data();
+    let ao_loc = cint_data.ao_loc();
+    let nbas = cint_data.get_nbas() as usize;
+    let nao = ao_loc[nbas];
+    let eri = crate::full_eri(&mut cint_data);
+    // (ia|jb)-like orbital sets of different sizes
+    let nmo = [2, 3, 1, 4];
+    let orbs: Vec<Vec<f64>> = nmo.iter().enumerate().map(|(x, n)| (0..nao*n).map(|i| 0.3*((i*(x+2)) as f64).sin()).collect()).collect();
+    let reference = |p: usize, q: usize, r: usize, s: usize| {
+        let mut v = 0.0;
+        for a in 0..nao {for b in 0..nao {for c in 0..nao {for d in 0..nao {
+            v += orbs[0][a + nao*p]*orbs[1][b + nao*q]*orbs[2][c + nao*r]*orbs[3][d + nao*s]*eri[a + nao*(b + nao*(c + nao*d))];
+        }}}}
+        v
+    };
+    let orb_refs = [orbs[0].as_slice(), orbs[1].as_slice(), orbs[2].as_slice(), orbs[3].as_slice()];
+    let in_memory = cint_data.ao2mo_general(orb_refs, &Ao2moOptions::default());
+    // a tiny memory forces the spilling to disk and one shell pair per batch
+    let tiny = Ao2moOptions {max_memory: 0, ..Ao2moOptions::default()};
+    let mut spilled = vec![0.0; in_memory.len()];
+    let stats = cint_data.ao2mo_for_each_block(orb_refs, &tiny, |pq_range, block| {
+        let nrows = pq_range.len();
+        for rs in 0..4 {
+            spilled[pq_range.start + 6*rs..pq_range.end + 6*rs].copy_from_slice(&block[nrows*rs..nrows*(rs+1)]);
+        }
+    });
+    assert!(stats.spilled);
+    assert_eq!(stats.n_kl_batches, nbas*(nbas+1)/2);
+    assert_eq!(stats.n_pq_blocks, 6);
+    for s in 0..4 {for r in 0..1 {for q in 0..3 {for p in 0..2 {
+        let (pq, rs) = (p + 2*q, r + s);
+        assert!((in_memory[pq + 6*rs] - reference(p,q,r,s)).abs() < 1.0e-10);
+        assert!((spilled[pq + 6*rs] - in_memory[pq + 6*rs]).abs() < 1.0e-12);
+    }}}}
+    // the generic driver on the reference engine
+    let mut reference = crate::reference::ReferenceEngine::from_cint_data(&cint_data);
+    let eri_ref = ao2mo_general(&mut reference, orb_refs, &Ao2moOptions::default());
+    assert_eq!(eri_ref.len(), in_memory.len());
+    eri_ref.iter().zip(in_memory.iter()).for_each(|(x,y)| assert!((x-y).abs() < 1.0e-10));
+    cint_data.final_c2r();
+}
@@ -0,0 +1,194 @@
+//! Conversion of the AO ordering and normalization of `libcint` to those of other programs.
+//!
+//! The components of a shell are ordered in `libcint` as
+//! - spheric: `m = -l..l`, except for the p shells which keep (x, y, z);
+//! - Cartesian: lexicographic, i.e. xx, xy, xz, yy, yz, zz, ...
+//!
+//! The Cartesian GTOs of `libcint` with `l >= 2` share the radial normalization of the spheric
+//! ones, such that no Cartesian component is normalized. The other programs normalize the `x^l`
+//! component and use its normalization for all components of the shell, so that the MO coefficients
+//! differ by `sqrt(4π/(2l+1))`. The spheric GTOs are normalized in all conventions.
+//!
+//! A conversion is given as `AoReorder`: the AO `k` in the target convention is the AO `perm[k]`
+//! of `libcint`, and the coefficient of an orbital on it is multiplied by `scale[k]`.
+use std::f64::consts::PI;
+
+use crate::{CINTR2CDATA, CintType};
+use crate::cart2sph::cart_index;
+
+// slots of bas
+const ANG_OF: usize = 1;
+const NCTR_OF: usize = 3;
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum AoConvention {
+    Libcint,
+    /// Gaussian (including the formatted checkpoint files)
+    Gaussian,
+    Molden,
+    Orca,
+    QChem,
+}
+
+/// The permutation and scaling from the AOs of `libcint` to those of another convention.
+#[derive(Clone,Debug,PartialEq)]
+pub struct AoReorder {
+    pub perm: Vec<usize>,
+    pub scale: Vec<f64>,
+}
+
+fn cart_by_strings(comps: &[&str]) -> Vec<usize> {
+    comps.iter().map(|s| {
+        let count = |c: char| s.chars().filter(|x| *x == c).count();
+        cart_index(count('x'), count('y'), count('z'))
+    }).collect()
+}
+
+impl AoConvention {
+    /// The order of the components of a shell `l` in this convention, as indices of the `libcint` components.
+    pub fn component_order(&self, l: usize, cint_type: CintType) -> Vec<usize> {
+        let nsph = 2*l+1;
+        let ncart = (l+1)*(l+2)/2;
+        match cint_type {
+            CintType::Spheric => match (self, l) {
+                (_, 0) => vec![0],
+                (AoConvention::Orca, 1) => vec![2, 0, 1],
+                (_, 1) => vec![0, 1, 2],
+                (AoConvention::Libcint, _) | (AoConvention::QChem, _) => (0..nsph).collect(),
+                // m = 0, +1, -1, +2, -2, ...
+                _ => (0..nsph).map(|k| if k == 0 {l} else if k % 2 == 1 {l + k.div_ceil(2)} else {l - k/2}).collect(),
+            },
+            CintType::Cartesian => match (self, l) {
+                (AoConvention::Libcint, _) | (_, 0) | (_, 1) => (0..ncart).collect(),
+                (AoConvention::QChem, _) => {
+                    // by the power of z, then by the power of y: xx, xy, yy, xz, yz, zz
+                    let mut order = vec![];
+                    for lz in 0..=l {
+                        for ly in 0..=l-lz {order.push(cart_index(l-lz-ly, ly, lz))};
+                    }
+                    order
+                },
+                (_, 2) => cart_by_strings(&["xx","yy","zz","xy","xz","yz"]),
+                (_, 3) => cart_by_strings(&["xxx","yyy","zzz","xyy","xxy","xxz","xzz","yzz","yyz","xyz"]),
+                (AoConvention::Gaussian, _) => (0..ncart).rev().collect(),
+                (_, 4) => cart_by_strings(&["xxxx","yyyy","zzzz","xxxy","xxxz","yyyx","yyyz","zzzx","zzzy",
+                                            "xxyy","xxzz","yyzz","xxyz","yyxz","zzxy"]),
+                _ => (0..ncart).collect(),
+            },
+        }
+    }
+
+    /// The factors of the MO coefficients of the components of a shell `l`, in the order of this convention.
+    pub fn component_scale(&self, l: usize, cint_type: CintType) -> Vec<f64> {
+        let order = self.component_order(l, cint_type);
+        match cint_type {
+            CintType::Spheric => order.iter().map(|k| {
+                // ORCA flips the sign of the solid harmonics with |m| >= 3
+                let m = *k as i64 - l as i64;
+                if *self == AoConvention::Orca && l >= 3 && m.abs() >= 3 {-1.0} else {1.0}
+            }).collect(),
+            CintType::Cartesian => {
+                let factor = if l <= 1 || *self == AoConvention::Libcint {1.0} else {(4.0*PI/(2*l+1) as f64).sqrt()};
+                vec![factor; order.len()]
+            },
+        }
+    }
+}
+
+impl AoReorder {
+    pub fn nao(&self) -> usize {
+        self.perm.len()
+    }
+
+    /// the conversion back to `libcint`
+    pub fn inverse(&self) -> AoReorder {
+        let mut perm = vec![0; self.nao()];
+        let mut scale = vec![0.0; self.nao()];
+        self.perm.iter().zip(self.scale.iter()).enumerate().for_each(|(k, (p, s))| {
+            perm[*p] = k;
+            scale[*p] = 1.0/s;
+        });
+        AoReorder {perm, scale}
+    }
+
+    /// the MO coefficients `mo_coeff` of `[nao, nmo]` in the target convention
+    pub fn transform_mo_coeff(&self, mo_coeff: &[f64], nmo: usize) -> Vec<f64> {
+        let nao = self.nao();
+        let mut out = vec![0.0; nao*nmo];
+        for m in 0..nmo {
+            for k in 0..nao {out[k + nao*m] = self.scale[k]*mo_coeff[self.perm[k] + nao*m]};
+        }
+        out
+    }
+
+    /// A density-like matrix of `[nao, nao]`, whose elements are transformed as the products of MO coefficients.
+    pub fn transform_density(&self, dm: &[f64]) -> Vec<f64> {
+        let nao = self.nao();
+        let mut out = vec![0.0; nao*nao];
+        for l in 0..nao {
+            for k in 0..nao {
+                out[k + nao*l] = self.scale[k]*self.scale[l]*dm[self.perm[k] + nao*self.perm[l]];
+            }
+        }
+        out
+    }
+
+    /// An integral-like matrix of `[nao, nao]`, e.g. the overlap or Fock matrix.
+    pub fn transform_ao_matrix(&self, mat: &[f64]) -> Vec<f64> {
+        let nao = self.nao();
+        let mut out = vec![0.0; nao*nao];
+        for l in 0..nao {
+            for k in 0..nao {
+                out[k + nao*l] = mat[self.perm[k] + nao*self.perm[l]]/(self.scale[k]*self.scale[l]);
+            }
+        }
+        out
+    }
+}
+
+impl CINTR2CDATA {
+    /// The conversion of all AOs of the current `CintType` from `libcint` to the convention `conv`.
+    pub fn ao_reorder(&self, conv: AoConvention) -> AoReorder {
+        let mut perm = vec![];
+        let mut scale = vec![];
+        let mut off = 0;
+        for ibas in self.get_bas() {
+            let l = ibas[ANG_OF] as usize;
+            let order = conv.component_order(l, self.cint_type);
+            let factors = conv.component_scale(l, self.cint_type);
+            for _ in 0..ibas[NCTR_OF] {
+                perm.extend(order.iter().map(|k| off + k));
+                scale.extend(factors.iter());
+                off += order.len();
+            }
+        }
+        AoReorder {perm, scale}
+    }
+}
+
+#[test]
+pub fn test_ao_reorder() {
+    let conventions = [AoConvention::Libcint, AoConvention::Gaussian, AoConvention::Molden, AoConvention::Orca, AoConvention::QChem];
+    for conv in conventions.iter() {
+        for l in 0..6 {
+            for cint_type in [CintType::Spheric, CintType::Cartesian] {
+                let mut order = conv.component_order(l, cint_type);
+                order.sort();
+                let n = match cint_type {CintType::Spheric => 2*l+1, CintType::Cartesian => (l+1)*(l+2)/2};
+                assert_eq!(order, (0..n).collect::<Vec<usize>>());
+            }
+        }
+    }
+    assert_eq!(AoConvention::Molden.component_order(2, CintType::Spheric), vec![2,3,1,4,0]);
+    assert_eq!(AoConvention::Gaussian.component_order(2, CintType::Cartesian), vec![0,3,5,1,2,4]);
+    assert_eq!(AoConvention::QChem.component_order(2, CintType::Cartesian), vec![0,1,3,2,4,5]);
+    assert_eq!(AoConvention::Orca.component_scale(3, CintType::Spheric), vec![1.0,1.0,1.0,1.0,1.0,-1.0,-1.0]);
+
+    let mut cint_data = crate::test_h2_data();
+    let reorder = cint_data.ao_reorder(AoConvention::Orca);
+    assert_eq!(reorder.perm, vec![0,3,1,2,4,7,5,6]);
+    let mo: Vec<f64> = (0..16).map(|i| i as f64).collect();
+    let back = reorder.inverse().transform_mo_coeff(&reorder.transform_mo_coeff(&mo, 2), 2);
+    assert_eq!(back, mo);
+    cint_data.final_c2r();
+}
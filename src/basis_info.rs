@@ -0,0 +1,155 @@
+//! Metadata of the basis functions: the atom, shell, angular momentum and component
+//! each AO index belongs to.
+//!
+//! The AOs of a shell are ordered as in `libcint` (`CINTcgto_spheric`/`CINTcgto_cart`): the
+//! components run faster than the contractions. The spheric components are `m = -l..l` except for
+//! the p shells, which keep the Cartesian order (x, y, z); the Cartesian components are in the
+//! lexicographic order xx, xy, xz, yy, yz, zz, ...
+use crate::{CINTR2CDATA, CintType};
+
+// slots of atm and bas
+const CHARGE_OF: usize = 0;
+const ATOM_OF: usize = 0;
+const ANG_OF: usize = 1;
+const NCTR_OF: usize = 3;
+
+/// Element symbols indexed by the nuclear charge, with "X" for ghost atoms.
+pub const ELEMENTS: [&str; 119] = [
+    "X",
+    "H", "He",
+    "Li", "Be", "B", "C", "N", "O", "F", "Ne",
+    "Na", "Mg", "Al", "Si", "P", "S", "Cl", "Ar",
+    "K", "Ca", "Sc", "Ti", "V", "Cr", "Mn", "Fe", "Co", "Ni", "Cu", "Zn", "Ga", "Ge", "As", "Se", "Br", "Kr",
+    "Rb", "Sr", "Y", "Zr", "Nb", "Mo", "Tc", "Ru", "Rh", "Pd", "Ag", "Cd", "In", "Sn", "Sb", "Te", "I", "Xe",
+    "Cs", "Ba", "La", "Ce", "Pr", "Nd", "Pm", "Sm", "Eu", "Gd", "Tb", "Dy", "Ho", "Er", "Tm", "Yb", "Lu",
+    "Hf", "Ta", "W", "Re", "Os", "Ir", "Pt", "Au", "Hg", "Tl", "Pb", "Bi", "Po", "At", "Rn",
+    "Fr", "Ra", "Ac", "Th", "Pa", "U", "Np", "Pu", "Am", "Cm", "Bk", "Cf", "Es", "Fm", "Md", "No", "Lr",
+    "Rf", "Db", "Sg", "Bh", "Hs", "Mt", "Ds", "Rg", "Cn", "Nh", "Fl", "Mc", "Lv", "Ts", "Og",
+];
+
+const ANG_LABELS: [char; 8] = ['s', 'p', 'd', 'f', 'g', 'h', 'i', 'k'];
+
+/// The labels of the components of a shell with the angular momentum `l`, in the `libcint` order.
+pub fn component_labels(l: usize, cint_type: CintType) -> Vec<String> {
+    match cint_type {
+        CintType::Spheric => match l {
+            0 => vec![String::new()],
+            1 => vec!["x".to_string(), "y".to_string(), "z".to_string()],
+            2 => ["xy", "yz", "z^2", "xz", "x2-y2"].iter().map(|x| x.to_string()).collect(),
+            _ => (-(l as i32)..=l as i32).map(|m| format!("{:+}", m)).collect(),
+        },
+        CintType::Cartesian => {
+            let mut labels = vec![];
+            for lx in (0..=l).rev() {
+                for ly in (0..=l-lx).rev() {
+                    let lz = l - lx - ly;
+                    labels.push(format!("{}{}{}", "x".repeat(lx), "y".repeat(ly), "z".repeat(lz)));
+                }
+            }
+            labels
+        },
+    }
+}
+
+impl CINTR2CDATA {
+    /// the atom each shell belongs to
+    pub fn shell_atoms(&self) -> Vec<usize> {
+        self.get_bas().iter().map(|ibas| ibas[ATOM_OF] as usize).collect()
+    }
+
+    /// the atom each AO belongs to
+    pub fn ao_atoms(&self) -> Vec<usize> {
+        let ao_loc = self.ao_loc();
+        self.shell_atoms().iter().enumerate()
+            .flat_map(|(ish, atm_id)| vec![*atm_id; ao_loc[ish+1]-ao_loc[ish]]).collect()
+    }
+
+    /// the shell each AO belongs to
+    pub fn ao_shells(&self) -> Vec<usize> {
+        let ao_loc = self.ao_loc();
+        (0..self.c_nbas as usize).flat_map(|ish| vec![ish; ao_loc[ish+1]-ao_loc[ish]]).collect()
+    }
+
+    /// The shell and AO ranges of each atom, `[shell_start, shell_end, ao_start, ao_end]`,
+    /// for the shells grouped by atoms as in `bas`.
+    pub fn aoslice_by_atom(&self) -> Vec<[usize;4]> {
+        let ao_loc = self.ao_loc();
+        let shell_atoms = self.shell_atoms();
+        let mut aoslice = vec![];
+        let mut ish = 0;
+        for atm_id in 0..self.c_natm as usize {
+            let sh0 = ish;
+            while ish < shell_atoms.len() && shell_atoms[ish] == atm_id {ish += 1};
+            aoslice.push([sh0, ish, ao_loc[sh0], ao_loc[ish]]);
+        }
+        if ish != shell_atoms.len() {
+            panic!("Error:: the shells in bas are not grouped by atoms in ascending order")
+        }
+        aoslice
+    }
+
+    /// the number of AOs of each angular momentum, indexed by `l`
+    pub fn ao_count_by_l(&self) -> Vec<usize> {
+        let ao_loc = self.ao_loc();
+        let mut count = vec![];
+        self.get_bas().iter().enumerate().for_each(|(ish, ibas)| {
+            let l = ibas[ANG_OF] as usize;
+            if count.len() <= l {count.resize(l+1, 0)};
+            count[l] += ao_loc[ish+1] - ao_loc[ish];
+        });
+        count
+    }
+
+    /// the element symbol of the atom `atm_id`
+    pub fn atom_symbol(&self, atm_id: usize) -> &'static str {
+        let charge = self.get_atm()[atm_id][CHARGE_OF] as usize;
+        ELEMENTS.get(charge).unwrap_or(&"X")
+    }
+
+    /// The labels of all AOs, e.g. "0 O 2p_x", with the atom index, the element symbol, the shell
+    /// counted from `l+1` for each angular momentum of the atom, and the component.
+    pub fn ao_labels(&self) -> Vec<String> {
+        let bas = self.get_bas();
+        let mut labels = vec![];
+        let mut shell_count: Vec<Vec<usize>> = vec![vec![]; self.c_natm as usize];
+        for ibas in bas.iter() {
+            let atm_id = ibas[ATOM_OF] as usize;
+            let l = ibas[ANG_OF] as usize;
+            let symbol = self.atom_symbol(atm_id);
+            let count = &mut shell_count[atm_id];
+            if count.len() <= l {count.resize(l+1, 0)};
+            let ang = ANG_LABELS.get(l).map(|c| c.to_string()).unwrap_or(format!("l{}", l));
+            for _ in 0..ibas[NCTR_OF] {
+                let n = l + 1 + count[l];
+                count[l] += 1;
+                for comp in component_labels(l, self.cint_type) {
+                    if comp.is_empty() {
+                        labels.push(format!("{} {} {}{}", atm_id, symbol, n, ang));
+                    } else {
+                        labels.push(format!("{} {} {}{}_{}", atm_id, symbol, n, ang, comp));
+                    }
+                }
+            }
+        }
+        labels
+    }
+}
+
+#[test]
+pub fn test_ao_labels() {
+    let mut cint_data = crate::test_h2_data();
+    assert_eq!(cint_data.ao_loc(), vec![0,1,4,5,8]);
+    assert_eq!(cint_data.aoslice_by_atom(), vec![[0,2,0,4],[2,4,4,8]]);
+    assert_eq!(cint_data.ao_atoms(), vec![0,0,0,0,1,1,1,1]);
+    assert_eq!(cint_data.ao_count_by_l(), vec![2,6]);
+    let labels = cint_data.ao_labels();
+    assert_eq!(labels[0], "0 H 1s");
+    assert_eq!(labels[3], "0 H 2p_z");
+    assert_eq!(labels[5], "1 H 2p_x");
+
+    cint_data.set_cint_type(&CintType::Cartesian);
+    assert_eq!(component_labels(2, CintType::Cartesian), vec!["xx","xy","xz","yy","yz","zz"]);
+    assert_eq!(component_labels(3, CintType::Spheric)[0], "-3");
+    assert_eq!(cint_data.ao_labels().len(), 8);
+    cint_data.final_c2r();
+}
@@ -0,0 +1,210 @@
+//! Transformations between Cartesian and spheric GTOs.
+//!
+//! The spheric GTOs of `libcint` are the real solid harmonics `r^l Y_lm` (without the Condon-Shortley
+//! phase) with `m = -l..l`, expressed by the Cartesian components of the same shell in the lexicographic
+//! order xx, xy, xz, yy, yz, zz, ... For the s and p shells the angular normalization is absorbed in
+//! the integrals of both kinds (`CINTcommon_fac_sp`), so that the transformation is the identity and the
+//! p functions keep the order (x, y, z).
+//!
+//! All matrices are `column-major`; `cart2sph_coeff(l)` is of `[ncart, nsph]` such that
+//! `S_sph = C^T S_cart C` for any one-electron matrix of the shell.
+use std::f64::consts::PI;
+
+use crate::{CINTR2CDATA, linalg};
+
+// slots of bas
+const ANG_OF: usize = 1;
+const NCTR_OF: usize = 3;
+
+fn factorial(n: usize) -> f64 {
+    (1..=n).fold(1.0, |acc, i| acc*i as f64)
+}
+
+fn binomial(n: usize, k: usize) -> f64 {
+    if k > n {0.0} else {factorial(n)/(factorial(k)*factorial(n-k))}
+}
+
+/// the number of Cartesian components of the angular momentum `l`
+pub fn ncart(l: usize) -> usize {
+    (l+1)*(l+2)/2
+}
+
+/// the index of the component `x^lx y^ly z^lz` in the lexicographic order of `libcint`
+pub fn cart_index(lx: usize, ly: usize, lz: usize) -> usize {
+    let l = lx + ly + lz;
+    let ryz = l - lx;
+    // the components with a larger power of x come first
+    ryz*(ryz+1)/2 + lz
+}
+
+/// The coefficients of the real solid harmonics `S_lm` of Helgaker, Jorgensen and Olsen
+/// (Molecular Electronic-Structure Theory, eq. 6.4.47) on the Cartesian monomials, `[ncart, 2l+1]`.
+fn solid_harmonics(l: usize) -> Vec<f64> {
+    let nc = ncart(l);
+    let mut coeff = vec![0.0; nc*(2*l+1)];
+    for (im, m) in (-(l as i32)..=l as i32).enumerate() {
+        let ma = m.unsigned_abs() as usize;
+        let norm = 1.0/(2.0_f64.powi(ma as i32)*factorial(l))
+            *(2.0*factorial(l+ma)*factorial(l-ma)/if m == 0 {2.0} else {1.0}).sqrt();
+        // 2*v_m
+        let vm2 = if m < 0 {1} else {0};
+        for t in 0..=(l-ma)/2 {
+            for u in 0..=t {
+                let mut v2 = vm2;
+                while v2 <= ma {
+                    let sign = if (t + (v2-vm2)/2) % 2 == 0 {1.0} else {-1.0};
+                    let c = sign*0.25_f64.powi(t as i32)*binomial(l,t)*binomial(l-t,ma+t)*binomial(t,u)*binomial(ma,v2);
+                    let lx = 2*t + ma - 2*u - v2;
+                    let ly = 2*u + v2;
+                    let lz = l - 2*t - ma;
+                    coeff[cart_index(lx,ly,lz) + nc*im] += norm*c;
+                    v2 += 2;
+                }
+            }
+        }
+    }
+    coeff
+}
+
+/// The Cartesian-to-spheric transformation of the angular momentum `l`, `[ncart, 2l+1]`.
+pub fn cart2sph_coeff(l: usize) -> Vec<f64> {
+    let nc = ncart(l);
+    let ns = 2*l+1;
+    if l <= 1 {
+        let mut identity = vec![0.0; nc*ns];
+        (0..nc).for_each(|i| identity[i + nc*i] = 1.0);
+        return identity
+    }
+    let factor = ((2*l+1) as f64/(4.0*PI)).sqrt();
+    solid_harmonics(l).iter().map(|c| factor*c).collect()
+}
+
+/// The overlap of the angular parts `\int x^{a+a'} y^{b+b'} z^{c+c'} dΩ` of the Cartesian components, `[ncart, ncart]`.
+fn cart_angular_overlap(l: usize) -> Vec<f64> {
+    let nc = ncart(l);
+    let comps: Vec<(usize,usize,usize)> = (0..=l).rev()
+        .flat_map(|lx| (0..=l-lx).rev().map(move |ly| (lx, ly, l-lx-ly))).collect();
+    let double_factorial = |n: i64| {let mut r = 1.0; let mut k = n; while k > 1 {r *= k as f64; k -= 2}; r};
+    let mut ovlp = vec![0.0; nc*nc];
+    for (j, (jx,jy,jz)) in comps.iter().enumerate() {
+        for (i, (ix,iy,iz)) in comps.iter().enumerate() {
+            let (px, py, pz) = (ix+jx, iy+jy, iz+jz);
+            if px % 2 == 1 || py % 2 == 1 || pz % 2 == 1 {continue};
+            ovlp[i + nc*j] = 4.0*PI*double_factorial(px as i64-1)*double_factorial(py as i64-1)*double_factorial(pz as i64-1)
+                /double_factorial((px+py+pz) as i64+1);
+        }
+    }
+    ovlp
+}
+
+/// The projection of Cartesian onto spheric functions of the angular momentum `l`, `[2l+1, ncart]`,
+/// i.e. the least-squares solution `P = (C^T A C)^{-1} C^T A` in the metric `A` of the Cartesian
+/// functions of the same shell, such that `P C = 1`.
+pub fn sph_projection_coeff(l: usize) -> Vec<f64> {
+    let nc = ncart(l);
+    let ns = 2*l+1;
+    let c = cart2sph_coeff(l);
+    if l <= 1 {return c};
+    let a = cart_angular_overlap(l);
+    // C^T A, whose rows are orthogonal to each other in the metric of C
+    let cta = linalg::matmul_tn(&c, &a, ns, nc, nc);
+    let ctac = linalg::matmul(&cta, &c, ns, nc, ns);
+    let mut p = cta;
+    for j in 0..nc {
+        for m in 0..ns {p[m + ns*j] /= ctac[m + ns*m]};
+    }
+    p
+}
+
+impl CINTR2CDATA {
+    /// the number of Cartesian and spheric basis functions, independent of the current `CintType`
+    pub fn nao_cart_sph(&self) -> (usize, usize) {
+        self.get_bas().iter().fold((0,0), |(nc,ns), ibas| {
+            let (l, nctr) = (ibas[ANG_OF] as usize, ibas[NCTR_OF] as usize);
+            (nc + ncart(l)*nctr, ns + (2*l+1)*nctr)
+        })
+    }
+
+    fn block_diagonal<F: Fn(usize) -> Vec<f64>>(&self, block: F, transpose: bool) -> Vec<f64> {
+        let (nao_cart, nao_sph) = self.nao_cart_sph();
+        let (nrow, ncol) = if transpose {(nao_sph, nao_cart)} else {(nao_cart, nao_sph)};
+        let mut mat = vec![0.0; nrow*ncol];
+        let (mut ic, mut is) = (0, 0);
+        for ibas in self.get_bas() {
+            let l = ibas[ANG_OF] as usize;
+            let (nc, ns) = (ncart(l), 2*l+1);
+            let b = block(l);
+            for _ in 0..ibas[NCTR_OF] {
+                let (r0, c0, br, bc) = if transpose {(is, ic, ns, nc)} else {(ic, is, nc, ns)};
+                for j in 0..bc {
+                    mat[r0 + nrow*(c0+j)..r0 + br + nrow*(c0+j)].copy_from_slice(&b[br*j..br*(j+1)]);
+                }
+                ic += nc;
+                is += ns;
+            }
+        }
+        mat
+    }
+
+    /// The block-diagonal Cartesian-to-spheric transformation of the whole basis, `[nao_cart, nao_sph]`.
+    pub fn cart2sph_matrix(&self) -> Vec<f64> {
+        self.block_diagonal(cart2sph_coeff, false)
+    }
+
+    /// The block-diagonal projection of Cartesian onto spheric functions, `[nao_sph, nao_cart]`.
+    pub fn sph_projection_matrix(&self) -> Vec<f64> {
+        self.block_diagonal(sph_projection_coeff, true)
+    }
+
+    /// `C^T M C` for a matrix `M` of `[nao_cart, nao_cart]` computed with Cartesian GTOs.
+    pub fn cart2sph_ao_matrix(&self, mat_cart: &[f64]) -> Vec<f64> {
+        let (nao_cart, nao_sph) = self.nao_cart_sph();
+        let c = self.cart2sph_matrix();
+        let mc = linalg::matmul(mat_cart, &c, nao_cart, nao_cart, nao_sph);
+        linalg::matmul_tn(&c, &mc, nao_sph, nao_cart, nao_sph)
+    }
+
+    /// The spheric MO coefficients, `[nao_sph, nmo]`, of the Cartesian ones `mo_cart` of `[nao_cart, nmo]`.
+    ///
+    /// The spurious lower-angular-momentum components (e.g. the s-type `x^2+y^2+z^2` in a d shell) are projected out.
+    pub fn mo_coeff_cart2sph(&self, mo_cart: &[f64], nmo: usize) -> Vec<f64> {
+        let (nao_cart, nao_sph) = self.nao_cart_sph();
+        linalg::matmul(&self.sph_projection_matrix(), mo_cart, nao_sph, nao_cart, nmo)
+    }
+
+    /// The Cartesian MO coefficients, `[nao_cart, nmo]`, of the spheric ones `mo_sph` of `[nao_sph, nmo]`.
+    pub fn mo_coeff_sph2cart(&self, mo_sph: &[f64], nmo: usize) -> Vec<f64> {
+        let (nao_cart, nao_sph) = self.nao_cart_sph();
+        linalg::matmul(&self.cart2sph_matrix(), mo_sph, nao_cart, nao_sph, nmo)
+    }
+}
+
+#[test]
+pub fn test_cart2sph() {
+    // reference values from the tables of libcint (cart2sph.c)
+    let c = cart2sph_coeff(2);
+    assert!((c[1] - 1.0925484305920792).abs() < 1.0e-14);  // dxy: xy
+    assert!((c[6*2] + 0.31539156525252).abs() < 1.0e-14);  // dz2: xx
+    assert!((c[5 + 6*2] - 0.63078313050504).abs() < 1.0e-14);  // dz2: zz
+    assert!((c[6*4] - 0.5462742152960396).abs() < 1.0e-14);  // dx2-y2: xx
+    let c = cart2sph_coeff(3);
+    assert!((c[1] - 1.7701307697799304).abs() < 1.0e-14);  // fyx2: xxy
+    assert!((c[6] + 0.5900435899266435).abs() < 1.0e-14);  // fyx2: yyy
+    assert!((c[10*4] + 0.4570457994644657).abs() < 1.0e-14);  // fxz2: xxx
+    assert!((c[5 + 10*4] - 1.8281831978578629).abs() < 1.0e-14);  // fxz2: xzz
+    // the projection inverts the transformation
+    for l in 0..6 {
+        let (nc, ns) = (ncart(l), 2*l+1);
+        let pc = linalg::matmul(&sph_projection_coeff(l), &cart2sph_coeff(l), ns, nc, ns);
+        for j in 0..ns {
+            for i in 0..ns {
+                let one = if i == j {1.0} else {0.0};
+                assert!((pc[i + ns*j] - one).abs() < 1.0e-12);
+            }
+        }
+    }
+    let mut cint_data = crate::test_h2_data();
+    assert_eq!(cint_data.nao_cart_sph(), (8,8));
+    assert_eq!(cint_data.cart2sph_matrix().len(), 64);
+    cint_data.final_c2r();
+}
@@ -0,0 +1,185 @@
+//! Pivoted Cholesky decomposition of the two-electron repulsive integrals.
+//!
+//! The ERI matrix `(ij|kl)`, with the packed pair indices `ij` and `kl`, is decomposed as
+//!
+//! (ij|kl) ≈ \sum_P L^P_{ij} L^P_{kl}
+//!
+//! on the fly from the "int2e" blocks of any `IntegralEngine`: only the diagonal `(ij|ij)` and the columns `(kl|pq)` of
+//! the selected shell pairs `pq` are computed. The Cholesky vectors are returned in the same
+//! packed-ij layout as the density-fitting factors (see `density_fitting::RIFactors`), so that
+//! they can be used wherever the RI factors are expected.
+use crate::CINTR2CDATA;
+use crate::density_fitting::{RIFactors, pair_index};
+use crate::engine::IntegralEngine;
+
+impl CINTR2CDATA {
+    /// The diagonal `(ij|ij)` of the ERI matrix in packed-ij order.
+    ///
+    /// The 2e optimizer is (re-)initialized by this driver.
+    pub fn int2e_diagonal(&mut self) -> Vec<f64> {
+        int2e_diagonal(self)
+    }
+
+    /// The pivoted Cholesky vectors of the ERI matrix, with the decomposition stopped once the
+    /// largest remaining diagonal element is below `threshold`.
+    ///
+    /// The number of Cholesky vectors is given by `naux` of the returned factors.
+    pub fn eri_cholesky(&mut self, threshold: f64) -> RIFactors {
+        eri_cholesky(self, threshold)
+    }
+}
+
+/// `CINTR2CDATA::int2e_diagonal` for any engine: the diagonal `(ij|ij)` in packed-ij order.
+///
+/// "int2e" is prepared by this driver.
+pub fn int2e_diagonal<E: IntegralEngine>(engine: &mut E) -> Vec<f64> {
+    let ao_loc = engine.ao_loc();
+    let nbas = engine.nbas();
+    let nao = ao_loc[nbas];
+    let mut diag = vec![0.0; nao*(nao+1)/2];
+    engine.prepare("int2e");
+    let mut buf = vec![0.0; engine.max_block_size("int2e")];
+    let mut workspace = engine.workspace("int2e");
+    for i in 0..nbas {
+        let i0 = ao_loc[i];
+        let di = ao_loc[i+1] - i0;
+        for j in 0..=i {
+            let j0 = ao_loc[j];
+            let dj = ao_loc[j+1] - j0;
+            engine.eval_4c2e(i, j, i, j, &mut buf, &mut workspace);
+            let dij = di*dj;
+            for jj in 0..dj {
+                for ii in 0..di {
+                    if i0+ii < j0+jj {continue};
+                    let ij = ii + di*jj;
+                    diag[pair_index(i0+ii,j0+jj)] = buf[ij + dij*ij];
+                }
+            }
+        }
+    }
+    diag
+}
+
+/// `CINTR2CDATA::eri_cholesky` for any engine: the pivoted Cholesky vectors of the ERI matrix
+/// down to the remaining diagonal `threshold`.
+///
+/// "int2e" is prepared by this driver.
+pub fn eri_cholesky<E: IntegralEngine>(engine: &mut E, threshold: f64) -> RIFactors {
+    let ao_loc = engine.ao_loc();
+    let nbas = engine.nbas();
+    let nao = ao_loc[nbas];
+    let npair = nao*(nao+1)/2;
+    let mut ao_to_shell = vec![0usize; nao];
+    (0..nbas).for_each(|ish| ao_to_shell[ao_loc[ish]..ao_loc[ish+1]].iter_mut().for_each(|x| {*x = ish}));
+
+    let mut diag = int2e_diagonal(engine);
+    let mut buf = vec![0.0; engine.max_block_size("int2e")];
+    let mut workspace = engine.workspace("int2e");
+    let mut vectors: Vec<f64> = vec![];
+    let mut nvec = 0;
+    loop {
+        let (pq_max, d_max) = diag.iter().enumerate()
+            .fold((0, 0.0_f64), |acc, (pq,d)| if *d > acc.1 {(pq,*d)} else {acc});
+        if d_max < threshold {break};
+        // the shell pair hosting the largest diagonal element
+        let p = ((((8*pq_max+1) as f64).sqrt() - 1.0)/2.0) as usize;
+        let p = if p*(p+1)/2 > pq_max {p-1} else if (p+1)*(p+2)/2 <= pq_max {p+1} else {p};
+        let q = pq_max - p*(p+1)/2;
+        let (psh, qsh) = (ao_to_shell[p], ao_to_shell[q]);
+        let columns = eri_shell_pair_columns(engine, &ao_loc, psh, qsh, &mut buf, &mut workspace);
+
+        let mut candidates: Vec<(usize, Vec<f64>)> = columns.into_iter()
+            .filter(|(pq,_)| diag[*pq] >= threshold).collect();
+        candidates.sort_by(|a,b| diag[b.0].partial_cmp(&diag[a.0]).unwrap());
+        for (pq, mut col) in candidates {
+            let d_pq = diag[pq];
+            if d_pq < threshold {continue};
+            for m in 0..nvec {
+                let l_m = &vectors[npair*m..npair*(m+1)];
+                let l_m_pq = l_m[pq];
+                if l_m_pq == 0.0 {continue};
+                col.iter_mut().zip(l_m.iter()).for_each(|(c,l)| {*c -= l*l_m_pq});
+            }
+            let inv = 1.0/d_pq.sqrt();
+            col.iter_mut().for_each(|c| {*c *= inv});
+            diag.iter_mut().zip(col.iter()).for_each(|(d,l)| {*d = (*d - l*l).max(0.0)});
+            diag[pq] = 0.0;
+            vectors.extend(col);
+            nvec += 1;
+        }
+    }
+    RIFactors {nao, naux: nvec, ri3fn: vectors}
+}
+
+/// The columns `(kl|pq)` for all pairs `p>=q` of the shell pair `(psh,qsh)`,
+/// each tagged with the packed index of `pq`.
+fn eri_shell_pair_columns<E: IntegralEngine>(engine: &E, ao_loc: &[usize], psh: usize, qsh: usize, buf: &mut [f64], workspace: &mut E::Workspace) -> Vec<(usize, Vec<f64>)> {
+    let nbas = engine.nbas();
+    let nao = ao_loc[nbas];
+    let npair = nao*(nao+1)/2;
+    let (p0, dp) = (ao_loc[psh], ao_loc[psh+1]-ao_loc[psh]);
+    let (q0, dq) = (ao_loc[qsh], ao_loc[qsh+1]-ao_loc[qsh]);
+    let mut columns: Vec<(usize, Vec<f64>)> = vec![];
+    let mut pq_local: Vec<(usize,usize)> = vec![];
+    for qq in 0..dq {
+        for pp in 0..dp {
+            if p0+pp < q0+qq {continue};
+            columns.push((pair_index(p0+pp,q0+qq), vec![0.0; npair]));
+            pq_local.push((pp,qq));
+        }
+    }
+    for k in 0..nbas {
+        let k0 = ao_loc[k];
+        let dk = ao_loc[k+1] - k0;
+        for l in 0..=k {
+            let l0 = ao_loc[l];
+            let dl = ao_loc[l+1] - l0;
+            engine.eval_4c2e(k, l, psh, qsh, buf, workspace);
+            let dkl = dk*dl;
+            columns.iter_mut().zip(pq_local.iter()).for_each(|((_,col),(pp,qq))| {
+                let buf_pq = &buf[dkl*(pp + dp*qq)..dkl*(pp + dp*qq + 1)];
+                for ll in 0..dl {
+                    for kk in 0..dk {
+                        if k0+kk < l0+ll {continue};
+                        col[pair_index(k0+kk,l0+ll)] = buf_pq[kk + dk*ll];
+                    }
+                }
+            });
+        }
+    }
+    columns
+}
+
+#[test]
+pub fn test_eri_cholesky() {
+    let mut cint_data = crate::test_h2_data();
+    let chol = cint_data.eri_cholesky(1.0e-12);
+    // the generic driver on the reference engine
+    let mut reference = crate::reference::ReferenceEngine::from_cint_data(&cint_data);
+    let chol_ref = eri_cholesky(&mut reference, 1.0e-12);
+    let diag_ref = int2e_diagonal(&mut reference);
+    assert_eq!(diag_ref.len(), chol.npair());
+    diag_ref.iter().zip(cint_data.int2e_diagonal().iter()).for_each(|(x,y)| assert!((x-y).abs() < 1.0e-10));
+    let nao = chol.nao;
+    let npair = chol.npair();
+    assert!(chol.naux <= npair);
+    // compare with the exact ERIs
+    let ao_loc = cint_data.ao_loc();
+    let nbas = cint_data.get_nbas() as usize;
+    cint_data.cint2e_optimizer_rust();
+    for (i,j,k,l) in [(0,0,0,0),(0,1,2,3),(1,3,2,2),(3,3,1,0)] {
+        let buf = cint_data.cint_ijkl_by_shell(i as i32,j as i32,k as i32,l as i32);
+        let d = |s: usize| ao_loc[s+1]-ao_loc[s];
+        let (di,dj,dk) = (d(i),d(j),d(k));
+        for ll in 0..d(l) {for kk in 0..dk {for jj in 0..dj {for ii in 0..di {
+            let ij = pair_index(ao_loc[i]+ii, ao_loc[j]+jj);
+            let kl = pair_index(ao_loc[k]+kk, ao_loc[l]+ll);
+            let approx: f64 = (0..chol.naux).map(|p| chol.column(p)[ij]*chol.column(p)[kl]).sum();
+            assert!((approx - buf[ii+di*(jj+dj*(kk+dk*ll))]).abs() < 1.0e-6);
+            let approx_ref: f64 = (0..chol_ref.naux).map(|p| chol_ref.column(p)[ij]*chol_ref.column(p)[kl]).sum();
+            assert!((approx_ref - approx).abs() < 1.0e-6);
+        }}}}
+    }
+    assert!(nbas == 4 && nao == 8);
+    cint_data.final_c2r();
+}
@@ -1,468 +1,327 @@
-/* automatically generated by rust-bindgen 0.59.1 */
+/* bindings of the libcint (>= 5.0) C API, following include/cint_funcs.h,
+   or their stand-ins by the reference backend without the `libcint` feature.
+
+   The catalog covers the spheric and Cartesian integrals of the autocode (1e, 2e, 2c2e, 3c2e and
+   3c1e) and, with the features of the vendored build, the F12 integrals of the STG and Yukawa
+   geminals (`with-f12`, with the exponent in env[PTR_F12_ZETA = 9]) and int4c1e (`with-4c1e`).
+   Not bound are the spinor integrals, the grids integrals (int1e_grids*, whose output has a
+   leading grid dimension given through env[NGRIDS]/env[PTR_GRIDS]) and the GTG integrals, for
+   which `with-gtg` only turns on the cmake option. */
+use std::os::raw::c_int;
+
+/// The integral optimizer of libcint, only handled by pointer.
 #[repr(C)]
-#[derive(Debug, Copy, Clone)]
-pub struct PairData {
-    pub rij: [f64; 3usize],
-    pub eij: f64,
-    pub cceij: f64,
-}
-#[repr(C)]
-#[derive(Debug, Copy, Clone)]
 pub struct CINTOpt {
-    pub index_xyz_array: *mut *mut ::std::os::raw::c_int,
-    pub non0ctr: *mut *mut ::std::os::raw::c_int,
-    pub sortedidx: *mut *mut ::std::os::raw::c_int,
-    pub nbas: ::std::os::raw::c_int,
-    pub log_max_coeff: *mut *mut f64,
-    pub pairdata: *mut *mut PairData,
+    _private: [u8; 0],
 }
-#[link(name="cint")]
+
+/// `int*_sph`/`int*_cart(out, dims, shls, atm, natm, bas, nbas, env, opt, cache)`
+///
+/// `dims` (or NULL for the shell sizes) gives the leading dimensions of `out`, `cache` (or NULL
+/// for an internal allocation) the scratch memory. With `out` NULL, the size of the cache
+/// (in doubles) is returned instead of evaluating the integrals.
+pub type CintIntegralFn = unsafe extern "C" fn(
+    out: *mut f64,
+    dims: *const c_int,
+    shls: *const c_int,
+    atm: *const c_int,
+    natm: c_int,
+    bas: *const c_int,
+    nbas: c_int,
+    env: *const f64,
+    opt: *const CINTOpt,
+    cache: *mut f64,
+) -> c_int;
+
+/// `int*_optimizer(opt, atm, natm, bas, nbas, env)`
+pub type CintOptimizerFn = unsafe extern "C" fn(
+    opt: *mut *mut CINTOpt,
+    atm: *const c_int,
+    natm: c_int,
+    bas: *const c_int,
+    nbas: c_int,
+    env: *const f64,
+);
+
+#[cfg(feature = "libcint")]
 extern "C" {
-    pub fn CINTgto_norm(n: ::std::os::raw::c_int, a: f64) -> f64;
-    pub fn CINTcgto_cart(
-        bas_id: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-    ) -> ::std::os::raw::c_int;
-    pub fn CINTcgto_spheric(
-        bas_id: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-    ) -> ::std::os::raw::c_int;
-    pub fn cint2e_cart(
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    pub fn cint2e_cart_optimizer(
-        opt: *mut *mut CINTOpt,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-    );
-    pub fn cint2e_sph(
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    pub fn cint2e_sph_optimizer(
-        opt: *mut *mut CINTOpt,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-    );
-    pub fn cint3c2e_optimizer(
-        opt: *mut *mut CINTOpt,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-    );
-    pub fn cint3c2e_cart(
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    pub fn cint3c2e_sph(
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    pub fn int3c2e_ip1_optimizer(
-        opt: *mut *mut CINTOpt,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-    );
-    pub fn int3c2e_ip1_cart(
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    pub fn int3c2e_ip1_sph(
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    // IGOR DEBUG
-    pub fn cint3c2e_ip1_sph(
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    pub fn int3c2e_ip2_optimizer(
-        opt: *mut *mut CINTOpt,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-    );
-    pub fn int3c2e_ip2_cart(
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    pub fn int3c2e_ip2_sph(
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    pub fn cint2c2e_optimizer(
-        opt: *mut *mut CINTOpt,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-    );
-    pub fn cint2c2e_cart(
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    pub fn cint2c2e_sph(
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    pub fn cint2c2e_ip1_optimizer(
-        opt: *mut *mut CINTOpt,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-    );
-    pub fn cint2c2e_ip1_cart(
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-        pub fn cint2c2e_ip1_sph(
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    pub fn cint2e(
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    pub fn cint2e_optimizer(
-        opt: *mut *mut CINTOpt,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-    );
-    pub fn cint1e_ovlp_sph(
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    pub fn cint1e_ovlp_cart(
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    pub fn cint1e_ovlp_optimizer(
-        opt: *mut *mut CINTOpt,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-    );
-    pub fn cint1e_nuc_sph(
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    pub fn cint1e_nuc_cart(
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    pub fn cint1e_nuc_optimizer(
-        opt: *mut *mut CINTOpt,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-    );
-    pub fn cint1e_kin_sph(
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    pub fn cint1e_kin_cart(
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    pub fn int1e_kin_optimizer(
-        opt: *mut *mut CINTOpt,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-    );
-    pub fn int1e_ipovlp_optimizer(
-        //CINTOpt **opt, FINT *atm, FINT natm, FINT *bas, FINT nbas, double *env
-        opt: *mut *mut CINTOpt,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-    );
-    pub fn cint1e_ipovlp_sph(
-        //double *out, FINT *dims, FINT *shls,FINT *atm, FINT natm, FINT *bas, FINT nbas, double *env, CINTOpt *opt, double *cache
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    pub fn cint1e_ipovlp_cart(
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;    
-    pub fn int1e_ipkin_optimizer(
-        //CINTOpt **opt, FINT *atm, FINT natm, FINT *bas, FINT nbas, double *env
-        opt: *mut *mut CINTOpt,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-    );
-    pub fn cint1e_ipkin_sph(
-        //double *out, FINT *dims, FINT *shls,FINT *atm, FINT natm, FINT *bas, FINT nbas, double *env, CINTOpt *opt, double *cache
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    pub fn cint1e_ipkin_cart(
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    pub fn int1e_ipnuc_optimizer(
-        //CINTOpt **opt, FINT *atm, FINT natm, FINT *bas, FINT nbas, double *env
-        opt: *mut *mut CINTOpt,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-    );
-    //// IGOR DEBUG
-    //pub fn cint1e_ipnuc_optimizer(
-    //    //CINTOpt **opt, FINT *atm, FINT natm, FINT *bas, FINT nbas, double *env
-    //    opt: *mut *mut CINTOpt,
-    //    atm: *const ::std::os::raw::c_int,
-    //    natm: ::std::os::raw::c_int,
-    //    bas: *const ::std::os::raw::c_int,
-    //    nbas: ::std::os::raw::c_int,
-    //    env: *const f64,
-    //);
-    pub fn cint1e_ipnuc_sph(
-        //double *out, FINT *dims, FINT *shls,FINT *atm, FINT natm, FINT *bas, FINT nbas, double *env, CINTOpt *opt, double *cache
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    pub fn cint1e_ipnuc_cart(
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    pub fn int1e_iprinv_optimizer(
-        //CINTOpt **opt, FINT *atm, FINT natm, FINT *bas, FINT nbas, double *env
-        opt: *mut *mut CINTOpt,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-    );
-    pub fn cint1e_iprinv_sph(
-        //double *out, FINT *dims, FINT *shls,FINT *atm, FINT natm, FINT *bas, FINT nbas, double *env, CINTOpt *opt, double *cache
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    pub fn cint1e_iprinv_cart(
-        opijkl: *mut f64,
-        shls: *const ::std::os::raw::c_int,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
-        env: *const f64,
-        opt: *const CINTOpt,
-    ) -> ::std::os::raw::c_int;
-    
+    pub fn CINTgto_norm(n: c_int, a: f64) -> f64;
+    pub fn CINTcgto_cart(bas_id: c_int, bas: *const c_int) -> c_int;
+    pub fn CINTcgto_spheric(bas_id: c_int, bas: *const c_int) -> c_int;
+    pub fn CINTtot_cgto_cart(bas: *const c_int, nbas: c_int) -> c_int;
+    pub fn CINTtot_cgto_spheric(bas: *const c_int, nbas: c_int) -> c_int;
     pub fn CINTinit_2e_optimizer(
         opt: *mut *mut CINTOpt,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
+        atm: *const c_int,
+        natm: c_int,
+        bas: *const c_int,
+        nbas: c_int,
         env: *const f64,
     );
     pub fn CINTinit_optimizer(
         opt: *mut *mut CINTOpt,
-        atm: *const ::std::os::raw::c_int,
-        natm: ::std::os::raw::c_int,
-        bas: *const ::std::os::raw::c_int,
-        nbas: ::std::os::raw::c_int,
+        atm: *const c_int,
+        natm: c_int,
+        bas: *const c_int,
+        nbas: c_int,
         env: *const f64,
     );
     pub fn CINTdel_2e_optimizer(opt: *mut *mut CINTOpt);
     pub fn CINTdel_optimizer(opt: *mut *mut CINTOpt);
-}
\ No newline at end of file
+}
+
+// Without the `libcint` feature, the helpers and the integrals of the catalog are stand-ins in Rust:
+// the integrals provided by `reference::ReferenceEngine` are evaluated by it, the others panic in
+// `CINTR2CDATA` before the call (a panic inside the `extern "C"` stand-ins would abort).
+#[cfg(not(feature = "libcint"))]
+pub use self::stand_in::*;
+
+#[cfg(not(feature = "libcint"))]
+#[allow(non_snake_case, clippy::missing_safety_doc)]
+mod stand_in {
+    use std::os::raw::c_int;
+    use super::CINTOpt;
+
+    // slots of bas
+    const ANG_OF: usize = 1;
+    const NCTR_OF: usize = 3;
+    const BAS_SLOTS: usize = 8;
+
+    pub unsafe extern "C" fn CINTgto_norm(n: c_int, a: f64) -> f64 {
+        crate::reference::gto_norm(n as usize, a)
+    }
+    pub unsafe extern "C" fn CINTcgto_cart(bas_id: c_int, bas: *const c_int) -> c_int {
+        let l = *bas.add(BAS_SLOTS*bas_id as usize + ANG_OF);
+        (l+1)*(l+2)/2*(*bas.add(BAS_SLOTS*bas_id as usize + NCTR_OF))
+    }
+    pub unsafe extern "C" fn CINTcgto_spheric(bas_id: c_int, bas: *const c_int) -> c_int {
+        let l = *bas.add(BAS_SLOTS*bas_id as usize + ANG_OF);
+        (2*l+1)*(*bas.add(BAS_SLOTS*bas_id as usize + NCTR_OF))
+    }
+    pub unsafe extern "C" fn CINTtot_cgto_cart(bas: *const c_int, nbas: c_int) -> c_int {
+        (0..nbas).map(|i| CINTcgto_cart(i, bas)).sum()
+    }
+    pub unsafe extern "C" fn CINTtot_cgto_spheric(bas: *const c_int, nbas: c_int) -> c_int {
+        (0..nbas).map(|i| CINTcgto_spheric(i, bas)).sum()
+    }
+    /// no optimizer is used by the stand-ins
+    pub unsafe extern "C" fn CINTinit_2e_optimizer(opt: *mut *mut CINTOpt, _atm: *const c_int, _natm: c_int,
+                                                   _bas: *const c_int, _nbas: c_int, _env: *const f64) {
+        *opt = std::ptr::null_mut();
+    }
+    pub unsafe extern "C" fn CINTinit_optimizer(opt: *mut *mut CINTOpt, _atm: *const c_int, _natm: c_int,
+                                                _bas: *const c_int, _nbas: c_int, _env: *const f64) {
+        *opt = std::ptr::null_mut();
+    }
+    pub unsafe extern "C" fn CINTdel_2e_optimizer(opt: *mut *mut CINTOpt) {
+        *opt = std::ptr::null_mut();
+    }
+    pub unsafe extern "C" fn CINTdel_optimizer(opt: *mut *mut CINTOpt) {
+        *opt = std::ptr::null_mut();
+    }
+}
+
+/// An integral of the autocode catalog of libcint.
+pub struct CintIntegral {
+    /// the name without the `_sph`/`_cart` suffix, e.g. "int2e_ip1"
+    pub name: &'static str,
+    /// the number of shells in `shls`
+    pub ncenter: usize,
+    /// the number of components, e.g. 3 for the nuclear gradients
+    pub ncomp: usize,
+    pub sph: CintIntegralFn,
+    pub cart: CintIntegralFn,
+    pub optimizer: CintOptimizerFn,
+}
+
+#[cfg(feature = "libcint")]
+macro_rules! cint_functions {
+    ($($(#[$meta:meta])* $name:literal => $sph:ident, $cart:ident, $optimizer:ident;)*) => {
+        extern "C" {
+            $(
+            $(#[$meta])*
+            pub fn $sph(out: *mut f64, dims: *const c_int, shls: *const c_int,
+                        atm: *const c_int, natm: c_int, bas: *const c_int, nbas: c_int,
+                        env: *const f64, opt: *const CINTOpt, cache: *mut f64) -> c_int;
+            $(#[$meta])*
+            pub fn $cart(out: *mut f64, dims: *const c_int, shls: *const c_int,
+                         atm: *const c_int, natm: c_int, bas: *const c_int, nbas: c_int,
+                         env: *const f64, opt: *const CINTOpt, cache: *mut f64) -> c_int;
+            $(#[$meta])*
+            pub fn $optimizer(opt: *mut *mut CINTOpt, atm: *const c_int, natm: c_int,
+                              bas: *const c_int, nbas: c_int, env: *const f64);
+            )*
+        }
+    };
+}
+
+#[cfg(not(feature = "libcint"))]
+macro_rules! cint_functions {
+    ($($(#[$meta:meta])* $name:literal => $sph:ident, $cart:ident, $optimizer:ident;)*) => {
+        $(
+        /// # Safety
+        /// the arguments of the libcint C API
+        $(#[$meta])*
+        #[allow(clippy::too_many_arguments)]
+        pub unsafe extern "C" fn $sph(out: *mut f64, dims: *const c_int, shls: *const c_int,
+                                      atm: *const c_int, natm: c_int, bas: *const c_int, nbas: c_int,
+                                      env: *const f64, _opt: *const CINTOpt, _cache: *mut f64) -> c_int {
+            crate::reference::cint_eval_raw($name, crate::CintType::Spheric, out, dims, shls, atm, natm, bas, nbas, env)
+        }
+        /// # Safety
+        /// the arguments of the libcint C API
+        $(#[$meta])*
+        #[allow(clippy::too_many_arguments)]
+        pub unsafe extern "C" fn $cart(out: *mut f64, dims: *const c_int, shls: *const c_int,
+                                       atm: *const c_int, natm: c_int, bas: *const c_int, nbas: c_int,
+                                       env: *const f64, _opt: *const CINTOpt, _cache: *mut f64) -> c_int {
+            crate::reference::cint_eval_raw($name, crate::CintType::Cartesian, out, dims, shls, atm, natm, bas, nbas, env)
+        }
+        /// # Safety
+        /// `opt` is a valid pointer; no optimizer is used by the stand-ins
+        $(#[$meta])*
+        pub unsafe extern "C" fn $optimizer(opt: *mut *mut CINTOpt, _atm: *const c_int, _natm: c_int,
+                                            _bas: *const c_int, _nbas: c_int, _env: *const f64) {
+            *opt = std::ptr::null_mut();
+        }
+        )*
+    };
+}
+
+macro_rules! cint_catalog {
+    ($($(#[$meta:meta])* $name:literal, $ncenter:literal, $ncomp:literal => $sph:ident, $cart:ident, $optimizer:ident;)*) => {
+        cint_functions! {$($(#[$meta])* $name => $sph, $cart, $optimizer;)*}
+        /// the real (spheric and Cartesian) integrals of libcint
+        pub static CINT_CATALOG: &[CintIntegral] = &[
+            $($(#[$meta])* CintIntegral {name: $name, ncenter: $ncenter, ncomp: $ncomp, sph: $sph, cart: $cart, optimizer: $optimizer},)*
+        ];
+    };
+}
+
+cint_catalog! {
+    // one-electron integrals
+    "int1e_ovlp",       2, 1  => int1e_ovlp_sph, int1e_ovlp_cart, int1e_ovlp_optimizer;
+    "int1e_kin",        2, 1  => int1e_kin_sph, int1e_kin_cart, int1e_kin_optimizer;
+    "int1e_nuc",        2, 1  => int1e_nuc_sph, int1e_nuc_cart, int1e_nuc_optimizer;
+    "int1e_rinv",       2, 1  => int1e_rinv_sph, int1e_rinv_cart, int1e_rinv_optimizer;
+    "int1e_drinv",      2, 3  => int1e_drinv_sph, int1e_drinv_cart, int1e_drinv_optimizer;
+    "int1e_z",          2, 1  => int1e_z_sph, int1e_z_cart, int1e_z_optimizer;
+    "int1e_zz",         2, 1  => int1e_zz_sph, int1e_zz_cart, int1e_zz_optimizer;
+    "int1e_r",          2, 3  => int1e_r_sph, int1e_r_cart, int1e_r_optimizer;
+    "int1e_r2",         2, 1  => int1e_r2_sph, int1e_r2_cart, int1e_r2_optimizer;
+    "int1e_r4",         2, 1  => int1e_r4_sph, int1e_r4_cart, int1e_r4_optimizer;
+    "int1e_rr",         2, 9  => int1e_rr_sph, int1e_rr_cart, int1e_rr_optimizer;
+    "int1e_rrr",        2, 27 => int1e_rrr_sph, int1e_rrr_cart, int1e_rrr_optimizer;
+    "int1e_rrrr",       2, 81 => int1e_rrrr_sph, int1e_rrrr_cart, int1e_rrrr_optimizer;
+    "int1e_z_origj",    2, 1  => int1e_z_origj_sph, int1e_z_origj_cart, int1e_z_origj_optimizer;
+    "int1e_zz_origj",   2, 1  => int1e_zz_origj_sph, int1e_zz_origj_cart, int1e_zz_origj_optimizer;
+    "int1e_r_origj",    2, 3  => int1e_r_origj_sph, int1e_r_origj_cart, int1e_r_origj_optimizer;
+    "int1e_rr_origj",   2, 9  => int1e_rr_origj_sph, int1e_rr_origj_cart, int1e_rr_origj_optimizer;
+    "int1e_r2_origj",   2, 1  => int1e_r2_origj_sph, int1e_r2_origj_cart, int1e_r2_origj_optimizer;
+    "int1e_r4_origj",   2, 1  => int1e_r4_origj_sph, int1e_r4_origj_cart, int1e_r4_origj_optimizer;
+    "int1e_p4",         2, 1  => int1e_p4_sph, int1e_p4_cart, int1e_p4_optimizer;
+    "int1e_pnucp",      2, 1  => int1e_pnucp_sph, int1e_pnucp_cart, int1e_pnucp_optimizer;
+    "int1e_prinvp",     2, 1  => int1e_prinvp_sph, int1e_prinvp_cart, int1e_prinvp_optimizer;
+    "int1e_pnucxp",     2, 3  => int1e_pnucxp_sph, int1e_pnucxp_cart, int1e_pnucxp_optimizer;
+    "int1e_prinvxp",    2, 3  => int1e_prinvxp_sph, int1e_prinvxp_cart, int1e_prinvxp_optimizer;
+    "int1e_irp",        2, 9  => int1e_irp_sph, int1e_irp_cart, int1e_irp_optimizer;
+    "int1e_ircp",       2, 9  => int1e_ircp_sph, int1e_ircp_cart, int1e_ircp_optimizer;
+    "int1e_irrp",       2, 27 => int1e_irrp_sph, int1e_irrp_cart, int1e_irrp_optimizer;
+    "int1e_irpr",       2, 27 => int1e_irpr_sph, int1e_irpr_cart, int1e_irpr_optimizer;
+    "int1e_igovlp",     2, 3  => int1e_igovlp_sph, int1e_igovlp_cart, int1e_igovlp_optimizer;
+    "int1e_igkin",      2, 3  => int1e_igkin_sph, int1e_igkin_cart, int1e_igkin_optimizer;
+    "int1e_ignuc",      2, 3  => int1e_ignuc_sph, int1e_ignuc_cart, int1e_ignuc_optimizer;
+    "int1e_ggovlp",     2, 9  => int1e_ggovlp_sph, int1e_ggovlp_cart, int1e_ggovlp_optimizer;
+    "int1e_ggkin",      2, 9  => int1e_ggkin_sph, int1e_ggkin_cart, int1e_ggkin_optimizer;
+    "int1e_ggnuc",      2, 9  => int1e_ggnuc_sph, int1e_ggnuc_cart, int1e_ggnuc_optimizer;
+    "int1e_grjxp",      2, 9  => int1e_grjxp_sph, int1e_grjxp_cart, int1e_grjxp_optimizer;
+    "int1e_ia01p",      2, 3  => int1e_ia01p_sph, int1e_ia01p_cart, int1e_ia01p_optimizer;
+    "int1e_giao_irjxp", 2, 3  => int1e_giao_irjxp_sph, int1e_giao_irjxp_cart, int1e_giao_irjxp_optimizer;
+    "int1e_cg_irxp",    2, 3  => int1e_cg_irxp_sph, int1e_cg_irxp_cart, int1e_cg_irxp_optimizer;
+    "int1e_giao_a11part", 2, 9 => int1e_giao_a11part_sph, int1e_giao_a11part_cart, int1e_giao_a11part_optimizer;
+    "int1e_cg_a11part", 2, 9  => int1e_cg_a11part_sph, int1e_cg_a11part_cart, int1e_cg_a11part_optimizer;
+    "int1e_a01gp",      2, 9  => int1e_a01gp_sph, int1e_a01gp_cart, int1e_a01gp_optimizer;
+    // one-electron nuclear derivatives
+    "int1e_ipovlp",     2, 3  => int1e_ipovlp_sph, int1e_ipovlp_cart, int1e_ipovlp_optimizer;
+    "int1e_ovlpip",     2, 3  => int1e_ovlpip_sph, int1e_ovlpip_cart, int1e_ovlpip_optimizer;
+    "int1e_ipkin",      2, 3  => int1e_ipkin_sph, int1e_ipkin_cart, int1e_ipkin_optimizer;
+    "int1e_kinip",      2, 3  => int1e_kinip_sph, int1e_kinip_cart, int1e_kinip_optimizer;
+    "int1e_ipnuc",      2, 3  => int1e_ipnuc_sph, int1e_ipnuc_cart, int1e_ipnuc_optimizer;
+    "int1e_iprinv",     2, 3  => int1e_iprinv_sph, int1e_iprinv_cart, int1e_iprinv_optimizer;
+    "int1e_ippnucp",    2, 3  => int1e_ippnucp_sph, int1e_ippnucp_cart, int1e_ippnucp_optimizer;
+    "int1e_ipprinvp",   2, 3  => int1e_ipprinvp_sph, int1e_ipprinvp_cart, int1e_ipprinvp_optimizer;
+    "int1e_ipipovlp",   2, 9  => int1e_ipipovlp_sph, int1e_ipipovlp_cart, int1e_ipipovlp_optimizer;
+    "int1e_ipovlpip",   2, 9  => int1e_ipovlpip_sph, int1e_ipovlpip_cart, int1e_ipovlpip_optimizer;
+    "int1e_ipipkin",    2, 9  => int1e_ipipkin_sph, int1e_ipipkin_cart, int1e_ipipkin_optimizer;
+    "int1e_ipkinip",    2, 9  => int1e_ipkinip_sph, int1e_ipkinip_cart, int1e_ipkinip_optimizer;
+    "int1e_ipipnuc",    2, 9  => int1e_ipipnuc_sph, int1e_ipipnuc_cart, int1e_ipipnuc_optimizer;
+    "int1e_ipnucip",    2, 9  => int1e_ipnucip_sph, int1e_ipnucip_cart, int1e_ipnucip_optimizer;
+    "int1e_ipiprinv",   2, 9  => int1e_ipiprinv_sph, int1e_ipiprinv_cart, int1e_ipiprinv_optimizer;
+    "int1e_iprinvip",   2, 9  => int1e_iprinvip_sph, int1e_iprinvip_cart, int1e_iprinvip_optimizer;
+    // two-electron (4-center) integrals
+    "int2e",            4, 1  => int2e_sph, int2e_cart, int2e_optimizer;
+    "int2e_ig1",        4, 3  => int2e_ig1_sph, int2e_ig1_cart, int2e_ig1_optimizer;
+    "int2e_gg1",        4, 9  => int2e_gg1_sph, int2e_gg1_cart, int2e_gg1_optimizer;
+    "int2e_g1g2",       4, 9  => int2e_g1g2_sph, int2e_g1g2_cart, int2e_g1g2_optimizer;
+    "int2e_p1vxp1",     4, 3  => int2e_p1vxp1_sph, int2e_p1vxp1_cart, int2e_p1vxp1_optimizer;
+    "int2e_ip1",        4, 3  => int2e_ip1_sph, int2e_ip1_cart, int2e_ip1_optimizer;
+    "int2e_ip2",        4, 3  => int2e_ip2_sph, int2e_ip2_cart, int2e_ip2_optimizer;
+    "int2e_ipip1",      4, 9  => int2e_ipip1_sph, int2e_ipip1_cart, int2e_ipip1_optimizer;
+    "int2e_ipvip1",     4, 9  => int2e_ipvip1_sph, int2e_ipvip1_cart, int2e_ipvip1_optimizer;
+    "int2e_ip1ip2",     4, 9  => int2e_ip1ip2_sph, int2e_ip1ip2_cart, int2e_ip1ip2_optimizer;
+    // two-center two-electron integrals
+    "int2c2e",          2, 1  => int2c2e_sph, int2c2e_cart, int2c2e_optimizer;
+    "int2c2e_ip1",      2, 3  => int2c2e_ip1_sph, int2c2e_ip1_cart, int2c2e_ip1_optimizer;
+    "int2c2e_ip2",      2, 3  => int2c2e_ip2_sph, int2c2e_ip2_cart, int2c2e_ip2_optimizer;
+    "int2c2e_ipip1",    2, 9  => int2c2e_ipip1_sph, int2c2e_ipip1_cart, int2c2e_ipip1_optimizer;
+    "int2c2e_ip1ip2",   2, 9  => int2c2e_ip1ip2_sph, int2c2e_ip1ip2_cart, int2c2e_ip1ip2_optimizer;
+    // three-center two-electron integrals
+    "int3c2e",          3, 1  => int3c2e_sph, int3c2e_cart, int3c2e_optimizer;
+    "int3c2e_ig1",      3, 3  => int3c2e_ig1_sph, int3c2e_ig1_cart, int3c2e_ig1_optimizer;
+    "int3c2e_pvp1",     3, 1  => int3c2e_pvp1_sph, int3c2e_pvp1_cart, int3c2e_pvp1_optimizer;
+    "int3c2e_pvxp1",    3, 3  => int3c2e_pvxp1_sph, int3c2e_pvxp1_cart, int3c2e_pvxp1_optimizer;
+    "int3c2e_ip1",      3, 3  => int3c2e_ip1_sph, int3c2e_ip1_cart, int3c2e_ip1_optimizer;
+    "int3c2e_ip2",      3, 3  => int3c2e_ip2_sph, int3c2e_ip2_cart, int3c2e_ip2_optimizer;
+    "int3c2e_ipip1",    3, 9  => int3c2e_ipip1_sph, int3c2e_ipip1_cart, int3c2e_ipip1_optimizer;
+    "int3c2e_ipip2",    3, 9  => int3c2e_ipip2_sph, int3c2e_ipip2_cart, int3c2e_ipip2_optimizer;
+    "int3c2e_ipvip1",   3, 9  => int3c2e_ipvip1_sph, int3c2e_ipvip1_cart, int3c2e_ipvip1_optimizer;
+    "int3c2e_ip1ip2",   3, 9  => int3c2e_ip1ip2_sph, int3c2e_ip1ip2_cart, int3c2e_ip1ip2_optimizer;
+    // three-center one-electron integrals
+    "int3c1e",          3, 1  => int3c1e_sph, int3c1e_cart, int3c1e_optimizer;
+    "int3c1e_p2",       3, 1  => int3c1e_p2_sph, int3c1e_p2_cart, int3c1e_p2_optimizer;
+    "int3c1e_rinv",     3, 1  => int3c1e_rinv_sph, int3c1e_rinv_cart, int3c1e_rinv_optimizer;
+    "int3c1e_ip1",      3, 3  => int3c1e_ip1_sph, int3c1e_ip1_cart, int3c1e_ip1_optimizer;
+    "int3c1e_iprinv",   3, 3  => int3c1e_iprinv_sph, int3c1e_iprinv_cart, int3c1e_iprinv_optimizer;
+    "int3c1e_r2_origk", 3, 1  => int3c1e_r2_origk_sph, int3c1e_r2_origk_cart, int3c1e_r2_origk_optimizer;
+    "int3c1e_r4_origk", 3, 1  => int3c1e_r4_origk_sph, int3c1e_r4_origk_cart, int3c1e_r4_origk_optimizer;
+    "int3c1e_r6_origk", 3, 1  => int3c1e_r6_origk_sph, int3c1e_r6_origk_cart, int3c1e_r6_origk_optimizer;
+    // F12 integrals of the STG and Yukawa geminals, with the exponent in env[PTR_F12_ZETA]
+    #[cfg(feature = "with-f12")]
+    "int2e_stg",        4, 1  => int2e_stg_sph, int2e_stg_cart, int2e_stg_optimizer;
+    #[cfg(feature = "with-f12")]
+    "int2e_yp",         4, 1  => int2e_yp_sph, int2e_yp_cart, int2e_yp_optimizer;
+    #[cfg(feature = "with-f12")]
+    "int2e_stg_ip1",    4, 3  => int2e_stg_ip1_sph, int2e_stg_ip1_cart, int2e_stg_ip1_optimizer;
+    #[cfg(feature = "with-f12")]
+    "int2e_yp_ip1",     4, 3  => int2e_yp_ip1_sph, int2e_yp_ip1_cart, int2e_yp_ip1_optimizer;
+    #[cfg(feature = "with-f12")]
+    "int2e_stg_ipip1",  4, 9  => int2e_stg_ipip1_sph, int2e_stg_ipip1_cart, int2e_stg_ipip1_optimizer;
+    #[cfg(feature = "with-f12")]
+    "int2e_yp_ipip1",   4, 9  => int2e_yp_ipip1_sph, int2e_yp_ipip1_cart, int2e_yp_ipip1_optimizer;
+    #[cfg(feature = "with-f12")]
+    "int2e_stg_ipvip1", 4, 9  => int2e_stg_ipvip1_sph, int2e_stg_ipvip1_cart, int2e_stg_ipvip1_optimizer;
+    #[cfg(feature = "with-f12")]
+    "int2e_yp_ipvip1",  4, 9  => int2e_yp_ipvip1_sph, int2e_yp_ipvip1_cart, int2e_yp_ipvip1_optimizer;
+    #[cfg(feature = "with-f12")]
+    "int2e_stg_ip1ip2", 4, 9  => int2e_stg_ip1ip2_sph, int2e_stg_ip1ip2_cart, int2e_stg_ip1ip2_optimizer;
+    #[cfg(feature = "with-f12")]
+    "int2e_yp_ip1ip2",  4, 9  => int2e_yp_ip1ip2_sph, int2e_yp_ip1ip2_cart, int2e_yp_ip1ip2_optimizer;
+    // four-center one-electron overlap
+    #[cfg(feature = "with-4c1e")]
+    "int4c1e",          4, 1  => int4c1e_sph, int4c1e_cart, int4c1e_optimizer;
+}
+
+/// the catalog entry of the integral `name`, e.g. "int2e_ip1"
+pub fn find_integral(name: &str) -> Option<&'static CintIntegral> {
+    CINT_CATALOG.iter().find(|intor| intor.name == name)
+}
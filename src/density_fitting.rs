@@ -0,0 +1,296 @@
+//! Density fitting (resolution-of-identity, RI) tensors built on `cint_2c2e` and `cint_3c2e`.
+//!
+//! Both the orbital basis and the auxiliary basis are given as ranges of shells of the
+//! same `CINTR2CDATA`. With the Coulomb metric `V_{PQ} = (P|Q)` and the three-center
+//! integrals `(ij|P)`, the fitting factors are
+//!
+//! B^P_{ij} = \sum_Q (ij|Q) [V^{-1/2}]_{QP}
+//!
+//! such that `(ij|kl) ≈ \sum_P B^P_{ij} B^P_{kl}`. The factors are stored in the packed-ij
+//! convention, i.e. only `i>=j` is kept with the pair index `ij = i*(i+1)/2+j`, and the
+//! whole tensor is a `column-major` matrix of `[npair, naux]`.
+use std::ops::Range;
+
+use crate::{CINTR2CDATA, linalg};
+use crate::engine::{self, IntegralEngine};
+
+/// How the Coulomb metric `V` is decomposed to get `V^{-1/2}`.
+#[derive(Clone,Copy,Debug)]
+pub enum MetricDecomposition {
+    /// `V = L L^T` and `V^{-1/2}` is taken as `L^{-T}`.
+    Cholesky,
+    /// `V = U w U^T`; the eigenvectors with eigenvalues below the threshold are discarded
+    /// to remove the linear dependency of the auxiliary basis.
+    Eigen(f64),
+}
+
+/// The density-fitting factors B^P_{ij} in packed-ij storage.
+#[derive(Clone,Debug)]
+pub struct RIFactors {
+    /// the number of orbital basis functions
+    pub nao: usize,
+    /// the number of fitting vectors retained after the decomposition of the metric
+    pub naux: usize,
+    /// `[npair, naux]` with `npair = nao*(nao+1)/2`
+    pub ri3fn: Vec<f64>,
+}
+
+impl RIFactors {
+    pub fn npair(&self) -> usize {
+        self.nao*(self.nao+1)/2
+    }
+    /// B^P_{ij} for any ordering of i and j
+    pub fn get(&self, i: usize, j: usize, p: usize) -> f64 {
+        self.ri3fn[pair_index(i,j) + self.npair()*p]
+    }
+    /// the packed-ij column of the fitting vector P
+    pub fn column(&self, p: usize) -> &[f64] {
+        let npair = self.npair();
+        &self.ri3fn[npair*p..npair*(p+1)]
+    }
+}
+
+/// the packed lower-triangle index of the pair (i,j)
+pub fn pair_index(i: usize, j: usize) -> usize {
+    if i >= j {i*(i+1)/2 + j} else {j*(j+1)/2 + i}
+}
+
+impl CINTR2CDATA {
+    /// The Coulomb metric `V_{PQ} = (P|Q)` over the shells `aux_shls`, as a `column-major` matrix of `[naux, naux]`.
+    ///
+    /// The 2c2e optimizer is (re-)initialized by this driver.
+    pub fn int2c2e_metric(&mut self, aux_shls: Range<i32>) -> Vec<f64> {
+        int2c2e_metric(self, engine::shell_range(aux_shls))
+    }
+
+    /// The three-center integrals `(ij|P)` with `i,j` in `orb_shls` and `P` in `aux_shls`,
+    /// stored in packed-ij order as a `column-major` matrix of `[npair, naux]`.
+    ///
+    /// The 3c2e optimizer is (re-)initialized by this driver.
+    pub fn int3c2e_packed(&mut self, orb_shls: Range<i32>, aux_shls: Range<i32>) -> Vec<f64> {
+        int3c2e_packed(self, engine::shell_range(orb_shls), engine::shell_range(aux_shls))
+    }
+
+    /// Build the density-fitting factors B^P_{ij} for the orbital shells `orb_shls`
+    /// and the auxiliary shells `aux_shls` of the same basis data.
+    pub fn ri3fn_factors(&mut self, orb_shls: Range<i32>, aux_shls: Range<i32>, decomposition: MetricDecomposition) -> RIFactors {
+        ri3fn_factors(self, engine::shell_range(orb_shls), engine::shell_range(aux_shls), decomposition)
+    }
+}
+
+/// `CINTR2CDATA::int2c2e_metric` for any engine: the Coulomb metric over the shells `aux_shls`, `[naux, naux]`.
+///
+/// "int2c2e" is prepared by this driver.
+pub fn int2c2e_metric<E: IntegralEngine>(engine: &mut E, aux_shls: Range<usize>) -> Vec<f64> {
+    let ao_loc = engine.ao_loc();
+    let p0 = ao_loc[aux_shls.start];
+    let naux = ao_loc[aux_shls.end] - p0;
+    let mut metric = vec![0.0; naux*naux];
+    engine.prepare("int2c2e");
+    let mut buf = vec![0.0; engine.max_block_size("int2c2e")];
+    let mut workspace = engine.workspace("int2c2e");
+    for k in aux_shls.clone() {
+        let k0 = ao_loc[k] - p0;
+        let dk = ao_loc[k+1] - ao_loc[k];
+        for l in aux_shls.start..=k {
+            let l0 = ao_loc[l] - p0;
+            let dl = ao_loc[l+1] - ao_loc[l];
+            engine.eval_2c2e(k, l, &mut buf, &mut workspace);
+            for q in 0..dl {
+                for p in 0..dk {
+                    let v = buf[p + dk*q];
+                    metric[(k0+p) + naux*(l0+q)] = v;
+                    metric[(l0+q) + naux*(k0+p)] = v;
+                }
+            }
+        }
+    }
+    metric
+}
+
+/// `CINTR2CDATA::int3c2e_packed` for any engine: `(ij|P)` in packed-ij order, `[npair, naux]`.
+///
+/// "int3c2e" is prepared by this driver.
+pub fn int3c2e_packed<E: IntegralEngine>(engine: &mut E, orb_shls: Range<usize>, aux_shls: Range<usize>) -> Vec<f64> {
+    let ao_loc = engine.ao_loc();
+    let i_off = ao_loc[orb_shls.start];
+    let nao = ao_loc[orb_shls.end] - i_off;
+    let npair = nao*(nao+1)/2;
+    let p_off = ao_loc[aux_shls.start];
+    let naux = ao_loc[aux_shls.end] - p_off;
+    let mut tensor = vec![0.0; npair*naux];
+    engine.prepare("int3c2e");
+    let mut buf = vec![0.0; engine.max_block_size("int3c2e")];
+    let mut workspace = engine.workspace("int3c2e");
+    for k in aux_shls {
+        let k0 = ao_loc[k] - p_off;
+        let dk = ao_loc[k+1] - ao_loc[k];
+        for i in orb_shls.clone() {
+            let i0 = ao_loc[i] - i_off;
+            let di = ao_loc[i+1] - ao_loc[i];
+            for j in orb_shls.start..=i {
+                let j0 = ao_loc[j] - i_off;
+                let dj = ao_loc[j+1] - ao_loc[j];
+                engine.eval_3c2e(i, j, k, &mut buf, &mut workspace);
+                for p in 0..dk {
+                    let tensor_p = &mut tensor[npair*(k0+p)..npair*(k0+p+1)];
+                    for jj in 0..dj {
+                        for ii in 0..di {
+                            if i0+ii < j0+jj {continue};
+                            tensor_p[pair_index(i0+ii,j0+jj)] = buf[ii + di*(jj + dj*p)];
+                        }
+                    }
+                }
+            }
+        }
+    }
+    tensor
+}
+
+/// `CINTR2CDATA::ri3fn_factors` for any engine: the density-fitting factors B^P_{ij} of the orbital
+/// shells `orb_shls` and the auxiliary shells `aux_shls`.
+pub fn ri3fn_factors<E: IntegralEngine>(engine: &mut E, orb_shls: Range<usize>, aux_shls: Range<usize>, decomposition: MetricDecomposition) -> RIFactors {
+    let ao_loc = engine.ao_loc();
+    let nao = ao_loc[orb_shls.end] - ao_loc[orb_shls.start];
+    let npair = nao*(nao+1)/2;
+    let naux = ao_loc[aux_shls.end] - ao_loc[aux_shls.start];
+    let metric = int2c2e_metric(engine, aux_shls.clone());
+    let tensor = int3c2e_packed(engine, orb_shls, aux_shls);
+    let (ri3fn, naux) = match decomposition {
+        MetricDecomposition::Cholesky => {
+            let l = cholesky_metric(metric, naux);
+            (solve_lower_transpose(tensor, &l, npair, naux), naux)
+        },
+        MetricDecomposition::Eigen(_) => {
+            let (m, nkept) = metric_inverse_sqrt(metric, naux, decomposition);
+            (linalg::matmul(&tensor, &m, npair, naux, nkept), nkept)
+        },
+    };
+    RIFactors {nao, naux, ri3fn}
+}
+
+/// The Cholesky factor `L` of the metric `V = L L^T`
+fn cholesky_metric(metric: Vec<f64>, naux: usize) -> Vec<f64> {
+    let mut l = metric;
+    if let Err(p) = linalg::cholesky_lower(&mut l, naux) {
+        panic!("Error:: the 2c2e metric is not positive definite at the fitting function {}; use MetricDecomposition::Eigen to remove the linear dependency", p)
+    };
+    l
+}
+
+/// The matrix `M` of `[naux, nkept]` with `M M^T = V^{-1}`, together with `nkept`
+pub(crate) fn metric_inverse_sqrt(metric: Vec<f64>, naux: usize, decomposition: MetricDecomposition) -> (Vec<f64>, usize) {
+    match decomposition {
+        MetricDecomposition::Cholesky => {
+            let l = cholesky_metric(metric, naux);
+            let mut identity = vec![0.0; naux*naux];
+            (0..naux).for_each(|p| identity[p + naux*p] = 1.0);
+            (solve_lower_transpose(identity, &l, naux, naux), naux)
+        },
+        MetricDecomposition::Eigen(threshold) => {
+            let (w, u) = linalg::symmetric_eigh(&metric, naux);
+            let kept: Vec<usize> = (0..naux).filter(|p| w[*p] > threshold).collect();
+            let nkept = kept.len();
+            let mut m = vec![0.0; naux*nkept];
+            kept.iter().enumerate().for_each(|(q,p)| {
+                let factor = 1.0/w[*p].sqrt();
+                m[naux*q..naux*(q+1)].iter_mut().zip(u[naux*p..naux*(p+1)].iter())
+                    .for_each(|(x,y)| {*x = y*factor});
+            });
+            (m, nkept)
+        },
+    }
+}
+
+/// `B = T L^{-T}` by the forward substitution over the columns of `T[npair, naux]`
+fn solve_lower_transpose(mut t: Vec<f64>, l: &[f64], npair: usize, naux: usize) -> Vec<f64> {
+    for p in 0..naux {
+        let (done, rest) = t.split_at_mut(npair*p);
+        let t_p = &mut rest[..npair];
+        for q in 0..p {
+            let l_pq = l[p + naux*q];
+            if l_pq == 0.0 {continue};
+            t_p.iter_mut().zip(done[npair*q..npair*(q+1)].iter()).for_each(|(x,y)| {*x -= l_pq*y});
+        }
+        let inv = 1.0/l[p + naux*p];
+        t_p.iter_mut().for_each(|x| {*x *= inv});
+    }
+    t
+}
+
+#[test]
+pub fn test_ri3fn_factors() {
+    use crate::CintType;
+    // H2 with a contracted s and a p shell per atom, followed by the auxiliary shells
+    let mut atm: Vec<Vec<i32>> = vec![];
+    let mut bas: Vec<Vec<i32>> = vec![];
+    let mut env = vec![0.0;20];
+    let mut ptr_env = 20;
+    for z in [-0.7,0.7] {
+        atm.push(vec![1,ptr_env,0,0,0,0]);
+        env.extend(vec![0.0,0.0,z]);
+        ptr_env += 3;
+    }
+    let mut add_shell = |bas: &mut Vec<Vec<i32>>, env: &mut Vec<f64>, atm_id: i32, l: i32, exps: &[f64], coeffs: &[f64]| {
+        env.extend(exps);
+        exps.iter().zip(coeffs).for_each(|(exp,coeff)| env.push(coeff*CINTR2CDATA::gto_norm(l, *exp)));
+        let nprim = exps.len() as i32;
+        bas.push(vec![atm_id,l,nprim,1,0,ptr_env,ptr_env+nprim,0]);
+        ptr_env += 2*nprim;
+    };
+    for iatm in 0..2 {
+        add_shell(&mut bas, &mut env, iatm, 0, &[6.0,2.0,0.8], &[0.7,0.6,0.5]);
+        add_shell(&mut bas, &mut env, iatm, 1, &[0.9], &[1.0]);
+    }
+    for iatm in 0..2 {
+        // even-tempered auxiliary shells, rich enough for an accurate fit
+        for exp in [24.0,12.0,6.0,3.0,1.5,0.75,0.4] {add_shell(&mut bas, &mut env, iatm, 0, &[exp], &[1.0])};
+        for exp in [6.0,3.0,1.5,0.75,0.4] {add_shell(&mut bas, &mut env, iatm, 1, &[exp], &[1.0])};
+        for exp in [3.6,1.8,0.9] {add_shell(&mut bas, &mut env, iatm, 2, &[exp], &[1.0])};
+    }
+    let natm = atm.len() as i32;
+    let nbas = bas.len() as i32;
+    let mut cint_data = CINTR2CDATA::new();
+    cint_data.set_cint_type(&CintType::Spheric);
+    cint_data.initial_r2c(&atm,natm,&bas,nbas,&env);
+
+    let ri_chol = cint_data.ri3fn_factors(0..4, 4..nbas, MetricDecomposition::Cholesky);
+    let ri_eigen = cint_data.ri3fn_factors(0..4, 4..nbas, MetricDecomposition::Eigen(1.0e-12));
+    assert_eq!(ri_chol.nao, 8);
+    // both decompositions reproduce the same fitted (ij|kl)
+    let npair = ri_chol.npair();
+    let fitted = |ri: &RIFactors| linalg::matmul(&ri.ri3fn, &transpose(&ri.ri3fn, npair, ri.naux), npair, ri.naux, npair);
+    fitted(&ri_chol).iter().zip(fitted(&ri_eigen).iter()).for_each(|(x,y)| assert!((x-y).abs() < 1.0e-8));
+    // and approximate the exact (ij|kl), from below on the diagonal as the Coulomb-metric fit does
+    let fitted = fitted(&ri_chol);
+    let ao_loc = cint_data.ao_loc();
+    let d = |s: usize| ao_loc[s+1]-ao_loc[s];
+    let mut max_err: f64 = 0.0;
+    cint_data.cint2e_optimizer_rust();
+    for l in 0..4 {for k in 0..4 {for j in 0..4 {for i in 0..4 {
+        let buf = cint_data.cint_ijkl_by_shell(i as i32,j as i32,k as i32,l as i32);
+        let (di,dj,dk) = (d(i),d(j),d(k));
+        for ll in 0..d(l) {for kk in 0..dk {for jj in 0..dj {for ii in 0..di {
+            let ij = pair_index(ao_loc[i]+ii, ao_loc[j]+jj);
+            let kl = pair_index(ao_loc[k]+kk, ao_loc[l]+ll);
+            let exact = buf[ii+di*(jj+dj*(kk+dk*ll))];
+            max_err = max_err.max((fitted[ij + npair*kl] - exact).abs());
+            if ij == kl {assert!(fitted[ij + npair*kl] <= exact + 1.0e-10)};
+        }}}}
+    }}}}
+    assert!(max_err < 5.0e-3);
+    // the generic driver on the reference engine
+    let mut reference = crate::reference::ReferenceEngine::from_cint_data(&cint_data);
+    let ri_ref = ri3fn_factors(&mut reference, 0..4, 4..nbas as usize, MetricDecomposition::Cholesky);
+    assert_eq!(ri_ref.ri3fn.len(), ri_chol.ri3fn.len());
+    ri_ref.ri3fn.iter().zip(ri_chol.ri3fn.iter()).for_each(|(x,y)| assert!((x-y).abs() < 1.0e-8));
+
+    cint_data.final_c2r();
+}
+
+#[cfg(test)]
+fn transpose(a: &[f64], nrow: usize, ncol: usize) -> Vec<f64> {
+    let mut b = vec![0.0; nrow*ncol];
+    for j in 0..ncol {for i in 0..nrow {b[j + ncol*i] = a[i + nrow*j]}};
+    b
+}
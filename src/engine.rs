@@ -0,0 +1,210 @@
+//! The `IntegralEngine` trait, abstracting the evaluation of integrals by shell blocks.
+//!
+//! The integrals are named as in the libcint catalog ("int1e_ovlp", "int2c2e", "int3c2e", "int2e",
+//! "int1e_ipnuc", ...) and the shell blocks are `column-major` tensors of `[di, dj, ..., ncomp]`.
+//! `CINTR2CDATA` implements the trait with libcint; other engines (or mocks in tests) can be passed
+//! to the generic drivers: `engine::int1e_matrix`, `screening::schwarz_bounds`, `jk::get_jk`,
+//! `density_fitting::ri3fn_factors`, `ri_jk::get_j_ri`/`get_k_ri`, `cholesky_eri::eri_cholesky` and
+//! `ao2mo::ao2mo_general`, among others. The `gradient` drivers stay on `CINTR2CDATA`, as they need the
+//! atoms of the shells, the nuclear charges and the `rinv` origin, which the trait does not abstract.
+use std::ops::Range;
+
+use crate::{CINTR2CDATA, CintType, CintWorkspace};
+
+/// The evaluation of integrals by shell blocks.
+pub trait IntegralEngine {
+    /// the scratch memory reused by `eval_into`
+    type Workspace;
+
+    fn nbas(&self) -> usize;
+    fn cint_type(&self) -> CintType;
+    /// the number of basis functions of the shell `ish`
+    fn shell_dim(&self, ish: usize) -> usize;
+    /// the number of components of `intor`, e.g. 3 for "int1e_ipovlp"
+    fn ncomp(&self, intor: &str) -> usize;
+    /// the number of shells of a block of `intor`, e.g. 4 for "int2e"
+    fn ncenter(&self, intor: &str) -> usize;
+    /// Prepare the evaluation of `intor`, e.g. the optimizer of libcint.
+    fn prepare(&mut self, intor: &str);
+    /// a workspace sized for all blocks of `intor`
+    fn workspace(&self, intor: &str) -> Self::Workspace;
+    /// Evaluate the block of `intor` for the shells `shls` into `out`. Returns the length of the block.
+    fn eval_into(&self, intor: &str, shls: &[usize], out: &mut [f64], workspace: &mut Self::Workspace) -> usize;
+
+    /// the offsets of the basis functions of all shells, with `ao_loc[nbas]` being the number of basis functions
+    fn ao_loc(&self) -> Vec<usize> {
+        let mut ao_loc = vec![0; self.nbas()+1];
+        (0..self.nbas()).for_each(|i| ao_loc[i+1] = ao_loc[i] + self.shell_dim(i));
+        ao_loc
+    }
+    fn nao(&self) -> usize {
+        (0..self.nbas()).map(|i| self.shell_dim(i)).sum()
+    }
+    /// the length of the block of `intor` for `shls`
+    fn block_size(&self, intor: &str, shls: &[usize]) -> usize {
+        shls.iter().map(|i| self.shell_dim(*i)).product::<usize>()*self.ncomp(intor)
+    }
+    /// the largest block of `intor` over all shells
+    fn max_block_size(&self, intor: &str) -> usize {
+        let max_dim = (0..self.nbas()).map(|i| self.shell_dim(i)).max().unwrap_or(0);
+        max_dim.pow(self.ncenter(intor) as u32)*self.ncomp(intor)
+    }
+    /// the block of `intor` for `shls` in a new buffer
+    fn eval(&self, intor: &str, shls: &[usize]) -> Vec<f64> {
+        let mut out = vec![0.0; self.block_size(intor, shls)];
+        let mut workspace = self.workspace(intor);
+        self.eval_into(intor, shls, &mut out, &mut workspace);
+        out
+    }
+    /// the one-electron integrals `intor` (e.g. "int1e_kin") of `[di, dj, ncomp]`
+    fn eval_1e(&self, intor: &str, i: usize, j: usize, out: &mut [f64], workspace: &mut Self::Workspace) -> usize {
+        self.eval_into(intor, &[i,j], out, workspace)
+    }
+    /// `(i|j)` of "int2c2e", `[di, dj]`
+    fn eval_2c2e(&self, i: usize, j: usize, out: &mut [f64], workspace: &mut Self::Workspace) -> usize {
+        self.eval_into("int2c2e", &[i,j], out, workspace)
+    }
+    /// `(ij|k)` of "int3c2e", `[di, dj, dk]`
+    fn eval_3c2e(&self, i: usize, j: usize, k: usize, out: &mut [f64], workspace: &mut Self::Workspace) -> usize {
+        self.eval_into("int3c2e", &[i,j,k], out, workspace)
+    }
+    /// `(ij|kl)` of "int2e", `[di, dj, dk, dl]`
+    fn eval_4c2e(&self, i: usize, j: usize, k: usize, l: usize, out: &mut [f64], workspace: &mut Self::Workspace) -> usize {
+        self.eval_into("int2e", &[i,j,k,l], out, workspace)
+    }
+}
+
+impl IntegralEngine for CINTR2CDATA {
+    type Workspace = CintWorkspace;
+
+    fn nbas(&self) -> usize {
+        self.c_nbas as usize
+    }
+    fn cint_type(&self) -> CintType {
+        self.cint_type
+    }
+    fn shell_dim(&self, ish: usize) -> usize {
+        self.cint_cgto_rust(ish as i32) as usize
+    }
+    fn ncomp(&self, intor: &str) -> usize {
+        CINTR2CDATA::intor_ncomp(intor)
+    }
+    fn ncenter(&self, intor: &str) -> usize {
+        CINTR2CDATA::intor_ncenter(intor)
+    }
+    fn prepare(&mut self, intor: &str) {
+        self.intor_optimizer_rust(intor);
+    }
+    fn workspace(&self, intor: &str) -> CintWorkspace {
+        self.intor_workspace(intor)
+    }
+    fn eval_into(&self, intor: &str, shls: &[usize], out: &mut [f64], workspace: &mut CintWorkspace) -> usize {
+        // at most 4 centers in the catalog, converted on the stack
+        if shls.len() > 4 {
+            panic!("Error:: {} shells are given, but libcint integrals have at most 4 centers", shls.len())
+        }
+        let mut c_shls = [0i32; 4];
+        c_shls.iter_mut().zip(shls).for_each(|(c, i)| *c = *i as i32);
+        self.cint_intor_into(intor, out, &c_shls[..shls.len()], workspace)
+    }
+    fn ao_loc(&self) -> Vec<usize> {
+        CINTR2CDATA::ao_loc(self)
+    }
+    fn max_block_size(&self, intor: &str) -> usize {
+        CINTR2CDATA::max_block_size(self, intor)
+    }
+}
+
+/// the shells `shls` of the `CINTR2CDATA` drivers as the shells of the generic drivers
+pub(crate) fn shell_range(shls: Range<i32>) -> Range<usize> {
+    shls.start as usize..shls.end as usize
+}
+
+/// The one-electron integrals `intor` between the shells `i_shls` and `j_shls` as a `column-major`
+/// tensor of `[ni, nj, ncomp]`, for any engine (see `CINTR2CDATA::int1e_matrix` for libcint).
+///
+/// `intor` is prepared by this driver.
+pub fn int1e_matrix<E: IntegralEngine>(engine: &mut E, intor: &str, i_shls: Range<usize>, j_shls: Range<usize>) -> Vec<f64> {
+    let ao_loc = engine.ao_loc();
+    let ncomp = engine.ncomp(intor);
+    let i_off = ao_loc[i_shls.start];
+    let ni = ao_loc[i_shls.end] - i_off;
+    let j_off = ao_loc[j_shls.start];
+    let nj = ao_loc[j_shls.end] - j_off;
+    let mut mat = vec![0.0; ni*nj*ncomp];
+    engine.prepare(intor);
+    let mut buf = vec![0.0; engine.max_block_size(intor)];
+    let mut workspace = engine.workspace(intor);
+    for j in j_shls {
+        let (j0, dj) = (ao_loc[j] - j_off, ao_loc[j+1] - ao_loc[j]);
+        for i in i_shls.clone() {
+            let (i0, di) = (ao_loc[i] - i_off, ao_loc[i+1] - ao_loc[i]);
+            engine.eval_1e(intor, i, j, &mut buf, &mut workspace);
+            for comp in 0..ncomp {
+                for jj in 0..dj {
+                    let col = i0 + ni*(j0 + jj + nj*comp);
+                    mat[col..col + di].copy_from_slice(&buf[di*(jj + dj*comp)..di*(jj + 1 + dj*comp)]);
+                }
+            }
+        }
+    }
+    mat
+}
+
+/// A mock engine of s shells (one function each) with the model integrals
+/// `(ij|kl) = g_ij g_kl + 0.1 (g_ij + g_kl)`, `g_ij = 1/(1+|i-j|)`, and `S_ij = g_ij`.
+#[cfg(test)]
+pub(crate) struct MockEngine {
+    pub nbas: usize,
+    pub prepared: Vec<String>,
+}
+
+#[cfg(test)]
+impl IntegralEngine for MockEngine {
+    type Workspace = ();
+    fn nbas(&self) -> usize {self.nbas}
+    fn cint_type(&self) -> CintType {CintType::Spheric}
+    fn shell_dim(&self, _ish: usize) -> usize {1}
+    fn ncomp(&self, _intor: &str) -> usize {1}
+    fn ncenter(&self, intor: &str) -> usize {
+        match intor {"int2e" => 4, "int3c2e" => 3, _ => 2}
+    }
+    fn prepare(&mut self, intor: &str) {self.prepared.push(intor.to_string())}
+    fn workspace(&self, _intor: &str) {}
+    fn eval_into(&self, intor: &str, shls: &[usize], out: &mut [f64], _workspace: &mut ()) -> usize {
+        let g = |i: usize, j: usize| 1.0/(1.0 + (i as f64 - j as f64).abs());
+        out[0] = match (intor, shls) {
+            ("int2e", [i,j,k,l]) => g(*i,*j)*g(*k,*l) + 0.1*(g(*i,*j) + g(*k,*l)),
+            ("int1e_ovlp", [i,j]) => g(*i,*j),
+            _ => panic!("Error:: {} is not provided by the mock engine", intor),
+        };
+        1
+    }
+}
+
+#[test]
+pub fn test_integral_engine() {
+    let mut engine = MockEngine {nbas: 5, prepared: vec![]};
+    assert_eq!(engine.ao_loc(), vec![0,1,2,3,4,5]);
+    assert_eq!(engine.max_block_size("int2e"), 1);
+    let ovlp = int1e_matrix(&mut engine, "int1e_ovlp", 1..3, 0..5);
+    assert_eq!(ovlp.len(), 10);
+    assert_eq!(ovlp[1 + 2*4], 1.0/(1.0 + 2.0));
+    assert_eq!(engine.prepared, vec!["int1e_ovlp".to_string()]);
+    assert_eq!(engine.eval("int2e", &[0,0,1,1]), vec![1.2]);
+
+    // J/K through the generic drivers, against the explicit contraction
+    let n = engine.nbas;
+    let dm: Vec<f64> = (0..n*n).map(|pq| 0.1*(pq as f64).sin()).collect();
+    let screening = crate::screening::schwarz_screening(&mut engine, 0.0);
+    let jk = crate::jk::get_jk(&mut engine, std::slice::from_ref(&dm), &screening, true, true);
+    let eri = |p: usize, q: usize, r: usize, s: usize| engine.eval("int2e", &[p,q,r,s])[0];
+    for q in 0..n {
+        for p in 0..n {
+            let j_ref: f64 = (0..n*n).map(|rs| eri(p,q,rs%n,rs/n)*dm[rs/n + n*(rs%n)]).sum();
+            let k_ref: f64 = (0..n*n).map(|rs| eri(p,rs%n,q,rs/n)*dm[rs/n + n*(rs%n)]).sum();
+            assert!((jk.vj[0][p + n*q] - j_ref).abs() < 1.0e-12);
+            assert!((jk.vk[0][p + n*q] - k_ref).abs() < 1.0e-12);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! `faer` matrices of the AO integrals (feature `faer`).
+//!
+//! The `column-major` buffers of the crate are viewed as `MatRef`/`MatMut` without copying by
+//! `mat_ref` and `mat_mut`. `Mat<f64>` owns a storage of its own, aligned and with padded columns,
+//! so `mat` and the full-matrix drivers below copy the buffers once into it; `mat_to_vec` gives the
+//! `column-major` buffer of any matrix back, e.g. for the density matrices of `get_jk`.
+use std::ops::Range;
+
+use faer::{Mat, MatMut, MatRef};
+
+use crate::CINTR2CDATA;
+use crate::engine::{self, IntegralEngine};
+
+fn check_shape(len: usize, nrow: usize, ncol: usize) {
+    if len != nrow*ncol {
+        panic!("Error:: the buffer of {} elements does not match the {}x{} matrix", len, nrow, ncol)
+    }
+}
+
+/// The `column-major` buffer `buf` of `nrow x ncol` as a matrix view, without copying.
+pub fn mat_ref(buf: &[f64], nrow: usize, ncol: usize) -> MatRef<'_, f64> {
+    check_shape(buf.len(), nrow, ncol);
+    MatRef::from_column_major_slice(buf, nrow, ncol)
+}
+
+/// The `column-major` buffer `buf` of `nrow x ncol` as a mutable matrix view, without copying.
+pub fn mat_mut(buf: &mut [f64], nrow: usize, ncol: usize) -> MatMut<'_, f64> {
+    check_shape(buf.len(), nrow, ncol);
+    MatMut::from_column_major_slice_mut(buf, nrow, ncol)
+}
+
+/// The `column-major` buffer `buf` of `nrow x ncol` copied into a matrix.
+pub fn mat(buf: &[f64], nrow: usize, ncol: usize) -> Mat<f64> {
+    mat_ref(buf, nrow, ncol).to_owned()
+}
+
+/// The `column-major` buffer of `mat`.
+pub fn mat_to_vec(mat: MatRef<'_, f64>) -> Vec<f64> {
+    (0..mat.ncols()).flat_map(|j| (0..mat.nrows()).map(move |i| mat[(i,j)])).collect()
+}
+
+/// The one-electron integrals `intor` between `i_shls` and `j_shls` of any engine, one `[ni, nj]`
+/// matrix per component.
+pub fn int1e_mats<E: IntegralEngine>(engine: &mut E, intor: &str, i_shls: Range<usize>, j_shls: Range<usize>) -> Vec<Mat<f64>> {
+    let ao_loc = engine.ao_loc();
+    let ni = ao_loc[i_shls.end] - ao_loc[i_shls.start];
+    let nj = ao_loc[j_shls.end] - ao_loc[j_shls.start];
+    // an empty range gives no chunks to split
+    if ni*nj == 0 {return vec![Mat::zeros(ni, nj); engine.ncomp(intor)]}
+    let buf = engine::int1e_matrix(engine, intor, i_shls, j_shls);
+    buf.chunks_exact(ni*nj).map(|comp| mat(comp, ni, nj)).collect()
+}
+
+impl CINTR2CDATA {
+    /// `int1e_matrix` as a `[ni, nj]` matrix
+    pub fn int1e_matrix_mat(&mut self, op_name: &str, i_shls: Range<i32>, j_shls: Range<i32>) -> Mat<f64> {
+        let ao_loc = self.ao_loc();
+        let ni = ao_loc[i_shls.end as usize] - ao_loc[i_shls.start as usize];
+        let nj = ao_loc[j_shls.end as usize] - ao_loc[j_shls.start as usize];
+        mat(&self.int1e_matrix(op_name, i_shls, j_shls), ni, nj)
+    }
+    /// `int2c2e_metric` as a `[naux, naux]` matrix
+    pub fn int2c2e_metric_mat(&mut self, aux_shls: Range<i32>) -> Mat<f64> {
+        let ao_loc = self.ao_loc();
+        let naux = ao_loc[aux_shls.end as usize] - ao_loc[aux_shls.start as usize];
+        mat(&self.int2c2e_metric(aux_shls), naux, naux)
+    }
+}
+
+#[test]
+pub fn test_faer() {
+    let mut cint_data = crate::test_h2_data();
+    let mut buf = cint_data.int1e_matrix("ovlp", 0..4, 1..3);
+    // the views share the buffer
+    let view = mat_ref(&buf, 8, 4);
+    assert_eq!(view[(5,3)], buf[5 + 8*3]);
+    assert_eq!(view.as_ptr(), buf.as_ptr());
+    let ovlp = cint_data.int1e_matrix_mat("ovlp", 0..4, 1..3);
+    assert_eq!(ovlp, view);
+    assert_eq!(mat_to_vec(ovlp.as_ref()), buf);
+    mat_mut(&mut buf, 8, 4)[(1,2)] = 0.5;
+    assert_eq!(buf[1 + 8*2], 0.5);
+
+    // the components of the ip integrals, against the buffer of the generic driver
+    let nao = cint_data.ao_loc()[4];
+    let ipovlp = int1e_mats(&mut cint_data, "int1e_ipovlp", 0..4, 0..4);
+    assert_eq!(ipovlp.len(), 3);
+    assert_eq!((ipovlp[2].nrows(), ipovlp[2].ncols()), (nao, nao));
+    let buf = engine::int1e_matrix(&mut cint_data, "int1e_ipovlp", 0..4, 0..4);
+    assert_eq!(ipovlp[1][(6,2)], buf[6 + nao*2 + nao*nao]);
+    let metric = cint_data.int2c2e_metric_mat(0..4);
+    assert_eq!(metric.transpose(), metric.as_ref());
+    // an empty range gives empty matrices, one per component
+    let empty = int1e_mats(&mut cint_data, "int1e_ipovlp", 0..4, 1..1);
+    assert_eq!(empty.len(), 3);
+    assert_eq!((empty[2].nrows(), empty[2].ncols()), (nao, 0));
+}
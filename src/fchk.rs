@@ -0,0 +1,370 @@
+//! Gaussian formatted checkpoint (FCHK) files.
+//!
+//! The geometry, the basis set, the MO coefficients and energies and the SCF densities are
+//! exchanged with Gaussian without the external `rest2fch` library. As in `molden`, the contracted
+//! GTOs are written as normalized contractions of normalized primitives, and the coefficients
+//! and densities are converted by `ao_convention::AoConvention::Gaussian`.
+//!
+//! The shell type of FCHK is `l` for Cartesian and `-l` for spheric shells (with s and p the same),
+//! and `-1` for the SP shells, which are split into an s and a p shell on reading.
+use std::collections::HashMap;
+use std::fmt::Write as FmtWrite;
+use std::io;
+
+use crate::{CINTR2CDATA, CintType};
+use crate::ao_convention::AoConvention;
+use crate::molden::{ContractedShell, MolecularOrbitals, cint_layout, invalid_data, parse_f64};
+
+// slots of atm
+const CHARGE_OF: usize = 0;
+const PTR_COORD: usize = 1;
+
+/// The wavefunction data of an FCHK file besides the geometry and the basis set.
+#[derive(Clone,Debug)]
+pub struct FchkWavefunction {
+    pub title: String,
+    /// e.g. "SP", "FOpt"
+    pub job_type: String,
+    /// e.g. "RHF", "UB3LYP"
+    pub method: String,
+    pub basis_name: String,
+    pub charge: i32,
+    pub multiplicity: i32,
+    pub total_energy: Option<f64>,
+    /// one set for restricted orbitals, the alpha and beta sets for unrestricted ones
+    pub orbitals: Vec<MolecularOrbitals>,
+    /// the `column-major` `[nao, nao]` total density in the AO basis of `libcint`
+    pub total_density: Option<Vec<f64>>,
+    /// the `column-major` `[nao, nao]` spin (alpha minus beta) density in the AO basis of `libcint`
+    pub spin_density: Option<Vec<f64>>,
+}
+
+/// The content of an FCHK file in the data layout of `libcint`.
+#[derive(Clone,Debug)]
+pub struct FchkData {
+    pub atm: Vec<Vec<i32>>,
+    pub bas: Vec<Vec<i32>>,
+    pub env: Vec<f64>,
+    pub cint_type: CintType,
+    pub wavefunction: FchkWavefunction,
+}
+
+impl FchkData {
+    /// A new `CINTR2CDATA` of the basis set, which should be released by `final_c2r`.
+    pub fn cint_data(&self) -> CINTR2CDATA {
+        let mut cint_data = CINTR2CDATA::new();
+        cint_data.set_cint_type(&self.cint_type);
+        cint_data.initial_r2c(&self.atm, self.atm.len() as i32, &self.bas, self.bas.len() as i32, &self.env);
+        cint_data
+    }
+}
+
+/// `x` in the Fortran format `ESw.d`, e.g. " 1.00000000E+00"
+fn fortran_e(x: f64, width: usize, digits: usize) -> String {
+    let s = format!("{:.*E}", digits, x);
+    let (mantissa, exponent) = s.split_once('E').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    format!("{:>width$}", format!("{}E{}{:02}", mantissa, if exponent < 0 {'-'} else {'+'}, exponent.abs()), width = width)
+}
+
+fn write_int(out: &mut String, name: &str, value: i64) {
+    writeln!(out, "{:<40}   I     {:>12}", name, value).unwrap();
+}
+
+fn write_real(out: &mut String, name: &str, value: f64) {
+    writeln!(out, "{:<40}   R     {}", name, fortran_e(value, 22, 15)).unwrap();
+}
+
+fn write_int_array(out: &mut String, name: &str, values: &[i64]) {
+    writeln!(out, "{:<40}   I   N={:>12}", name, values.len()).unwrap();
+    for chunk in values.chunks(6) {
+        chunk.iter().for_each(|v| write!(out, "{:>12}", v).unwrap());
+        writeln!(out).unwrap();
+    }
+}
+
+fn write_real_array(out: &mut String, name: &str, values: &[f64]) {
+    writeln!(out, "{:<40}   R   N={:>12}", name, values.len()).unwrap();
+    for chunk in values.chunks(5) {
+        chunk.iter().for_each(|v| write!(out, "{}", fortran_e(*v, 16, 8)).unwrap());
+        writeln!(out).unwrap();
+    }
+}
+
+/// the lower triangle of a symmetric `[n, n]` matrix by rows, as stored in FCHK
+fn pack_lower(mat: &[f64], n: usize) -> Vec<f64> {
+    (0..n).flat_map(|i| (0..=i).map(move |j| mat[i + n*j])).collect()
+}
+
+fn unpack_lower(packed: &[f64], n: usize) -> Vec<f64> {
+    let mut mat = vec![0.0; n*n];
+    let mut ij = 0;
+    for i in 0..n {
+        for j in 0..=i {
+            mat[i + n*j] = packed[ij];
+            mat[j + n*i] = packed[ij];
+            ij += 1;
+        }
+    }
+    mat
+}
+
+impl CINTR2CDATA {
+    /// The density-like matrix `dm` of `[nao, nao]` on the normalized AOs in the ordering of `conv`.
+    fn density_to_convention(&self, dm: &[f64], conv: AoConvention) -> Vec<f64> {
+        let (_, ao_norm) = self.normalized_contractions();
+        let nao = ao_norm.len();
+        let scaled: Vec<f64> = dm.iter().enumerate().map(|(ij, d)| d*ao_norm[ij%nao]*ao_norm[ij/nao]).collect();
+        self.ao_reorder(conv).transform_density(&scaled)
+    }
+
+    /// The FCHK file of the geometry, the basis set and the wavefunction `wfn`.
+    pub fn to_fchk_string(&self, wfn: &FchkWavefunction) -> String {
+        let env = self.get_env();
+        let atm = self.get_atm();
+        let (shells, _) = self.normalized_contractions();
+        let nao = self.ao_loc()[self.c_nbas as usize];
+        let natm = atm.len();
+        let nuc_charge: i32 = atm.iter().map(|iatm| iatm[CHARGE_OF]).sum();
+        let nelec = nuc_charge - wfn.charge;
+        let nalpha = (nelec + wfn.multiplicity - 1)/2;
+        let coords: Vec<f64> = atm.iter().flat_map(|iatm| {
+            let p = iatm[PTR_COORD] as usize;
+            env[p..p+3].to_vec()
+        }).collect();
+        let sign = match self.cint_type {CintType::Spheric => -1, CintType::Cartesian => 1};
+
+        let mut out = String::new();
+        writeln!(out, "{}", wfn.title).unwrap();
+        writeln!(out, "{:<10}{:<30}{:<30}", wfn.job_type, wfn.method, wfn.basis_name).unwrap();
+        write_int(&mut out, "Number of atoms", natm as i64);
+        write_int(&mut out, "Charge", wfn.charge as i64);
+        write_int(&mut out, "Multiplicity", wfn.multiplicity as i64);
+        write_int(&mut out, "Number of electrons", nelec as i64);
+        write_int(&mut out, "Number of alpha electrons", nalpha as i64);
+        write_int(&mut out, "Number of beta electrons", (nelec - nalpha) as i64);
+        write_int(&mut out, "Number of basis functions", nao as i64);
+        write_int(&mut out, "Number of independent functions", wfn.orbitals.first().map_or(nao, |mo| mo.nmo()) as i64);
+        write_int_array(&mut out, "Atomic numbers", &atm.iter().map(|iatm| iatm[CHARGE_OF] as i64).collect::<Vec<i64>>());
+        write_real_array(&mut out, "Nuclear charges", &atm.iter().map(|iatm| iatm[CHARGE_OF] as f64).collect::<Vec<f64>>());
+        write_real_array(&mut out, "Current cartesian coordinates", &coords);
+        write_int(&mut out, "Number of contracted shells", shells.len() as i64);
+        write_int(&mut out, "Number of primitive shells", shells.iter().map(|s| s.exps.len()).sum::<usize>() as i64);
+        write_int(&mut out, "Highest angular momentum", shells.iter().map(|s| s.l).max().unwrap_or(0) as i64);
+        write_int(&mut out, "Largest degree of contraction", shells.iter().map(|s| s.exps.len()).max().unwrap_or(0) as i64);
+        write_int_array(&mut out, "Shell types", &shells.iter().map(|s| if s.l >= 2 {sign*s.l as i64} else {s.l as i64}).collect::<Vec<i64>>());
+        write_int_array(&mut out, "Number of primitives per shell", &shells.iter().map(|s| s.exps.len() as i64).collect::<Vec<i64>>());
+        write_int_array(&mut out, "Shell to atom map", &shells.iter().map(|s| s.atm_id as i64 + 1).collect::<Vec<i64>>());
+        write_real_array(&mut out, "Primitive exponents", &shells.iter().flat_map(|s| s.exps.clone()).collect::<Vec<f64>>());
+        write_real_array(&mut out, "Contraction coefficients", &shells.iter().flat_map(|s| s.coeffs.clone()).collect::<Vec<f64>>());
+        write_real_array(&mut out, "Coordinates of each shell", &shells.iter().flat_map(|s| coords[3*s.atm_id..3*s.atm_id+3].to_vec()).collect::<Vec<f64>>());
+        if let Some(energy) = wfn.total_energy {write_real(&mut out, "Total Energy", energy)};
+        for (ispin, mo) in wfn.orbitals.iter().enumerate() {
+            let spin = if ispin == 0 {"Alpha"} else {"Beta"};
+            write_real_array(&mut out, &format!("{} Orbital Energies", spin), &mo.mo_energy);
+            write_real_array(&mut out, &format!("{} MO coefficients", spin),
+                             &self.mo_coeff_to_convention(&mo.mo_coeff, mo.nmo(), AoConvention::Gaussian));
+        }
+        if let Some(dm) = &wfn.total_density {
+            write_real_array(&mut out, "Total SCF Density", &pack_lower(&self.density_to_convention(dm, AoConvention::Gaussian), nao));
+        }
+        if let Some(dm) = &wfn.spin_density {
+            write_real_array(&mut out, "Spin SCF Density", &pack_lower(&self.density_to_convention(dm, AoConvention::Gaussian), nao));
+        }
+        out
+    }
+
+    pub fn write_fchk(&self, path: &str, wfn: &FchkWavefunction) -> io::Result<()> {
+        std::fs::write(path, self.to_fchk_string(wfn))
+    }
+}
+
+/// the entries of an FCHK file
+enum FchkEntry {
+    Int(Vec<i64>),
+    Real(Vec<f64>),
+    Other,
+}
+
+fn parse_entries(content: &str) -> io::Result<(String, String, HashMap<String, FchkEntry>)> {
+    let mut lines = content.lines();
+    let title = lines.next().unwrap_or("").trim_end().to_string();
+    let job_line = lines.next().unwrap_or("").to_string();
+    let mut entries = HashMap::new();
+    while let Some(line) = lines.next() {
+        if line.trim().is_empty() {continue};
+        if line.len() < 44 {return Err(invalid_data(format!("Error:: invalid FCHK line: {}", line)))};
+        let name = line[..40].trim().to_string();
+        let words: Vec<&str> = line[40..].split_whitespace().collect();
+        let kind = words.first().copied().unwrap_or("");
+        if words.get(1) == Some(&"N=") {
+            let n: usize = words.get(2).and_then(|w| w.parse().ok())
+                .ok_or(invalid_data(format!("Error:: invalid FCHK array: {}", line)))?;
+            match kind {
+                "I" | "R" => {
+                    let mut tokens: Vec<String> = vec![];
+                    while tokens.len() < n {
+                        let data = lines.next().ok_or(invalid_data(format!("Error:: incomplete FCHK array '{}'", name)))?;
+                        tokens.extend(data.split_whitespace().map(|t| t.to_string()));
+                    }
+                    let entry = if kind == "I" {
+                        FchkEntry::Int(tokens.iter().map(|t| t.parse::<i64>()
+                            .map_err(|_| invalid_data(format!("Error:: cannot parse '{}' as an integer", t)))).collect::<io::Result<Vec<i64>>>()?)
+                    } else {
+                        FchkEntry::Real(tokens.iter().map(|t| parse_f64(t)).collect::<io::Result<Vec<f64>>>()?)
+                    };
+                    entries.insert(name, entry);
+                },
+                _ => {
+                    // 5 words of the character arrays or 72 logicals per line
+                    let per_line = if kind == "L" {72} else {5};
+                    for _ in 0..n.div_ceil(per_line) {lines.next();}
+                    entries.insert(name, FchkEntry::Other);
+                },
+            }
+        } else {
+            let value = words.get(1).copied().unwrap_or("");
+            let entry = match kind {
+                "I" => FchkEntry::Int(vec![value.parse::<i64>().map_err(|_| invalid_data(format!("Error:: cannot parse '{}' as an integer", value)))?]),
+                "R" => FchkEntry::Real(vec![parse_f64(value)?]),
+                _ => FchkEntry::Other,
+            };
+            entries.insert(name, entry);
+        }
+    }
+    Ok((title, job_line, entries))
+}
+
+/// Read the geometry, the basis set and the wavefunction of an FCHK file.
+pub fn read_fchk(path: &str) -> io::Result<FchkData> {
+    parse_fchk(&std::fs::read_to_string(path)?)
+}
+
+/// Parse the content of an FCHK file.
+pub fn parse_fchk(content: &str) -> io::Result<FchkData> {
+    let (title, job_line, entries) = parse_entries(content)?;
+    let ints = |name: &str| match entries.get(name) {
+        Some(FchkEntry::Int(v)) => Ok(v.clone()),
+        _ => Err(invalid_data(format!("Error:: the integer entry '{}' is missing in the FCHK file", name))),
+    };
+    let reals = |name: &str| match entries.get(name) {
+        Some(FchkEntry::Real(v)) => Ok(v.clone()),
+        _ => Err(invalid_data(format!("Error:: the real entry '{}' is missing in the FCHK file", name))),
+    };
+
+    let numbers = ints("Atomic numbers")?;
+    let coords = reals("Current cartesian coordinates")?;
+    let atoms: Vec<(i32, [f64;3])> = numbers.iter().enumerate()
+        .map(|(i, z)| (*z as i32, [coords[3*i], coords[3*i+1], coords[3*i+2]])).collect();
+
+    let shell_types = ints("Shell types")?;
+    let nprims = ints("Number of primitives per shell")?;
+    let shell_atoms = ints("Shell to atom map")?;
+    let exps = reals("Primitive exponents")?;
+    let coeffs = reals("Contraction coefficients")?;
+    let sp_coeffs = reals("P(S=P) Contraction coefficients").ok();
+    let mut shells = vec![];
+    let mut pure = vec![];
+    let mut p0 = 0;
+    for ((stype, nprim), atm_id) in shell_types.iter().zip(nprims.iter()).zip(shell_atoms.iter()) {
+        let range = p0..p0 + *nprim as usize;
+        let atm_id = *atm_id as usize - 1;
+        if *stype == -1 {
+            let sp_coeffs = sp_coeffs.as_ref().ok_or(invalid_data("Error:: the P(S=P) coefficients are missing for the SP shells".to_string()))?;
+            shells.push(ContractedShell {l: 0, atm_id, exps: exps[range.clone()].to_vec(), coeffs: coeffs[range.clone()].to_vec()});
+            shells.push(ContractedShell {l: 1, atm_id, exps: exps[range.clone()].to_vec(), coeffs: sp_coeffs[range.clone()].to_vec()});
+        } else {
+            let l = stype.unsigned_abs() as usize;
+            if l >= 2 {pure.push(*stype < 0)};
+            shells.push(ContractedShell {l, atm_id, exps: exps[range.clone()].to_vec(), coeffs: coeffs[range.clone()].to_vec()});
+        }
+        p0 = range.end;
+    }
+    let cint_type = if pure.iter().all(|p| *p) {
+        CintType::Spheric
+    } else if pure.iter().all(|p| !*p) {
+        CintType::Cartesian
+    } else {
+        return Err(invalid_data("Error:: mixed spheric and Cartesian shells are not supported".to_string()))
+    };
+    let (atm, bas, env) = cint_layout(&atoms, &shells);
+
+    let mut cint_data = CINTR2CDATA::new();
+    cint_data.set_cint_type(&cint_type);
+    cint_data.initial_r2c(&atm, atm.len() as i32, &bas, bas.len() as i32, &env);
+    let nao = cint_data.ao_loc()[cint_data.c_nbas as usize];
+    let back = cint_data.ao_reorder(AoConvention::Gaussian).inverse();
+    cint_data.final_c2r();
+
+    let scalar = |name: &str| ints(name).ok().and_then(|v| v.first().copied());
+    let charge = scalar("Charge").unwrap_or(0) as i32;
+    let multiplicity = scalar("Multiplicity").unwrap_or(1) as i32;
+    let nalpha = scalar("Number of alpha electrons").unwrap_or(0) as usize;
+    let nbeta = scalar("Number of beta electrons").unwrap_or(0) as usize;
+    let mut orbitals = vec![];
+    for spin in ["Alpha", "Beta"] {
+        let (Ok(mo_energy), Ok(coeff)) = (reals(&format!("{} Orbital Energies", spin)), reals(&format!("{} MO coefficients", spin))) else {continue};
+        let nmo = mo_energy.len();
+        if coeff.len() != nao*nmo {
+            return Err(invalid_data(format!("Error:: {} MO coefficients are given for {} AOs and {} MOs", coeff.len(), nao, nmo)))
+        }
+        orbitals.push(MolecularOrbitals {nao, mo_coeff: back.transform_mo_coeff(&coeff, nmo), mo_energy, mo_occ: vec![0.0; nmo]});
+    }
+    // the aufbau occupations from the numbers of electrons
+    match orbitals.len() {
+        1 => orbitals[0].mo_occ.iter_mut().enumerate().for_each(|(i, occ)| {
+            *occ = if i < nbeta {2.0} else if i < nalpha {1.0} else {0.0}
+        }),
+        2 => for (mo, nocc) in orbitals.iter_mut().zip([nalpha, nbeta]) {
+            mo.mo_occ.iter_mut().enumerate().for_each(|(i, occ)| *occ = if i < nocc {1.0} else {0.0});
+        },
+        _ => {},
+    }
+    let density = |name: &str| reals(name).ok().map(|packed| back.transform_density(&unpack_lower(&packed, nao)));
+    let mut job = job_line.split_whitespace();
+
+    let wavefunction = FchkWavefunction {
+        title,
+        job_type: job.next().unwrap_or("").to_string(),
+        method: job.next().unwrap_or("").to_string(),
+        basis_name: job.next().unwrap_or("").to_string(),
+        charge,
+        multiplicity,
+        total_energy: reals("Total Energy").ok().and_then(|v| v.first().copied()),
+        orbitals,
+        total_density: density("Total SCF Density"),
+        spin_density: density("Spin SCF Density"),
+    };
+    Ok(FchkData {atm, bas, env, cint_type, wavefunction})
+}
+
+#[test]
+pub fn test_fchk() {
+    let mut cint_data = crate::test_h2_data();
+    let nao = 8;
+    let mo_coeff: Vec<f64> = (0..nao*nao).map(|i| 0.1*(i as f64).cos()).collect();
+    // normalized contractions, such that the coefficients are kept
+    let (shells, _) = cint_data.normalized_contractions();
+    let atoms: Vec<(i32, [f64;3])> = vec![(1, [0.0,0.0,-0.8]), (1, [0.0,0.0,0.8])];
+    let (atm, bas, env) = cint_layout(&atoms, &shells);
+    cint_data.final_c2r();
+    let mut cint_data = CINTR2CDATA::new();
+    cint_data.set_cint_type(&CintType::Spheric);
+    cint_data.initial_r2c(&atm, 2, &bas, 4, &env);
+    let dm: Vec<f64> = (0..nao*nao).map(|ij| ((ij%nao + ij/nao) as f64).sin()).collect();
+    let wfn = FchkWavefunction {
+        title: "H2".to_string(), job_type: "SP".to_string(), method: "RHF".to_string(), basis_name: "Gen".to_string(),
+        charge: 0, multiplicity: 1, total_energy: Some(-1.1),
+        orbitals: vec![MolecularOrbitals {nao, mo_coeff: mo_coeff.clone(), mo_energy: (0..nao).map(|i| i as f64 - 0.5).collect(), mo_occ: vec![0.0; nao]}],
+        total_density: Some(dm.clone()), spin_density: None,
+    };
+    let content = cint_data.to_fchk_string(&wfn);
+    assert!(content.contains("Total Energy                               R     -1.100000000000000E+00"));
+    let data = parse_fchk(&content).unwrap();
+    assert_eq!(data.bas, bas);
+    assert_eq!(data.wavefunction.orbitals[0].mo_occ[..2], [2.0, 0.0]);
+    assert_eq!(data.wavefunction.method, "RHF");
+    data.wavefunction.orbitals[0].mo_coeff.iter().zip(mo_coeff.iter()).for_each(|(a, b)| assert!((a - b).abs() < 1.0e-8));
+    data.wavefunction.total_density.unwrap().iter().zip(dm.iter()).for_each(|(a, b)| assert!((a - b).abs() < 1.0e-7));
+    cint_data.final_c2r();
+}
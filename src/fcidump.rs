@@ -0,0 +1,261 @@
+//! FCIDUMP files of the MO-basis integrals for external CI and DMRG programs.
+//!
+//! For the MO coefficients `C = [C_core, C_act, ...]`, the frozen core enters by
+//!
+//! E_core = E_nuc + \sum_{ij} D_{ij} (h_{ij} + 1/2 (J_{ij} - 1/2 K_{ij})),   h^eff = h + J - 1/2 K
+//!
+//! with `D = 2 C_core C_core^T`, `h` the kinetic plus nuclear attraction integrals and `J`/`K` built by `jk`.
+//! The active-space integrals, transformed by `ao2mo`, `h_{pq} = (C_act^T h^eff C_act)_{pq}` and `(pq|rs)` are kept under the
+//! 8-fold permutational symmetry, i.e. for `p>=q`, `r>=s` and `pq>=rs` (`density_fitting::pair_index`).
+use std::fmt::Write as FmtWrite;
+use std::io;
+
+use crate::{CINTR2CDATA, linalg};
+use crate::ao2mo::Ao2moOptions;
+use crate::density_fitting::pair_index;
+use crate::molden::{invalid_data, parse_f64};
+
+// slots of atm
+const CHARGE_OF: usize = 0;
+const PTR_COORD: usize = 1;
+
+/// The integrals and the header of an FCIDUMP file.
+#[derive(Clone,Debug,PartialEq)]
+pub struct FcidumpData {
+    pub norb: usize,
+    pub nelec: usize,
+    pub ms2: i32,
+    pub isym: i32,
+    /// the irreducible representations of the orbitals, 1-based
+    pub orbsym: Vec<i32>,
+    /// the nuclear repulsion plus the frozen-core energy
+    pub ecore: f64,
+    /// `column-major` `[norb, norb]`
+    pub h1e: Vec<f64>,
+    /// `(pq|rs)` with the index `pair_index(pair_index(p,q), pair_index(r,s))`
+    pub eri: Vec<f64>,
+}
+
+impl FcidumpData {
+    /// `(pq|rs)` for any order of the indices
+    pub fn eri(&self, p: usize, q: usize, r: usize, s: usize) -> f64 {
+        let pq = if p >= q {pair_index(p,q)} else {pair_index(q,p)};
+        let rs = if r >= s {pair_index(r,s)} else {pair_index(s,r)};
+        self.eri[if pq >= rs {pair_index(pq,rs)} else {pair_index(rs,pq)}]
+    }
+
+    /// The content of the FCIDUMP file, skipping the integrals with magnitudes below `tol`.
+    pub fn to_fcidump_string(&self, tol: f64) -> String {
+        let norb = self.norb;
+        let mut out = String::new();
+        writeln!(out, " &FCI NORB={:>4},NELEC={:>3},MS2={},", norb, self.nelec, self.ms2).unwrap();
+        writeln!(out, "  ORBSYM={}", self.orbsym.iter().map(|s| format!("{},", s)).collect::<String>()).unwrap();
+        writeln!(out, "  ISYM={},", self.isym).unwrap();
+        writeln!(out, " &END").unwrap();
+        let mut write_line = |v: f64, p: usize, q: usize, r: usize, s: usize| {
+            if v.abs() >= tol {writeln!(out, " {:>24.16e}{:>5}{:>5}{:>5}{:>5}", v, p, q, r, s).unwrap()};
+        };
+        for p in 0..norb {
+            for q in 0..=p {
+                for r in 0..=p {
+                    let s_max = if r == p {q} else {r};
+                    for s in 0..=s_max {write_line(self.eri(p,q,r,s), p+1, q+1, r+1, s+1)};
+                }
+            }
+        }
+        for p in 0..norb {
+            for q in 0..=p {write_line(self.h1e[p + norb*q], p+1, q+1, 0, 0)};
+        }
+        writeln!(out, " {:>24.16e}{:>5}{:>5}{:>5}{:>5}", self.ecore, 0, 0, 0, 0).unwrap();
+        out
+    }
+
+    pub fn write_fcidump(&self, path: &str, tol: f64) -> io::Result<()> {
+        std::fs::write(path, self.to_fcidump_string(tol))
+    }
+}
+
+/// Read an FCIDUMP file.
+pub fn read_fcidump(path: &str) -> io::Result<FcidumpData> {
+    parse_fcidump(&std::fs::read_to_string(path)?)
+}
+
+/// Parse the content of an FCIDUMP file.
+pub fn parse_fcidump(content: &str) -> io::Result<FcidumpData> {
+    let mut lines = content.lines();
+    // the namelist up to &END (or /)
+    let mut header = String::new();
+    for line in lines.by_ref() {
+        let upper = line.trim().to_uppercase();
+        if upper.starts_with("&END") || upper == "/" {break};
+        header.push_str(&upper.replace("&FCI", ""));
+        header.push(',');
+    }
+    let mut keys: Vec<(String, Vec<String>)> = vec![];
+    for item in header.split(',').map(|x| x.trim()).filter(|x| !x.is_empty()) {
+        if let Some((key, value)) = item.split_once('=') {
+            keys.push((key.trim().to_string(), vec![value.trim().to_string()]));
+        } else if let Some(last) = keys.last_mut() {
+            last.1.push(item.to_string());
+        }
+    }
+    let values = |name: &str| keys.iter().find(|(key, _)| key == name).map(|(_, v)| v.clone());
+    let int = |name: &str, default: Option<i64>| -> io::Result<i64> {
+        match values(name) {
+            Some(v) => v[0].parse::<i64>().map_err(|_| invalid_data(format!("Error:: cannot parse {}={} in FCIDUMP", name, v[0]))),
+            None => default.ok_or(invalid_data(format!("Error:: {} is missing in the FCIDUMP header", name))),
+        }
+    };
+    let norb = int("NORB", None)? as usize;
+    let nelec = int("NELEC", None)? as usize;
+    let ms2 = int("MS2", Some(0))? as i32;
+    let isym = int("ISYM", Some(1))? as i32;
+    let orbsym = match values("ORBSYM") {
+        Some(v) => v.iter().map(|s| s.parse::<i32>().map_err(|_| invalid_data(format!("Error:: cannot parse ORBSYM {}", s))))
+            .collect::<io::Result<Vec<i32>>>()?,
+        None => vec![1; norb],
+    };
+
+    let npair = norb*(norb+1)/2;
+    let mut data = FcidumpData {norb, nelec, ms2, isym, orbsym, ecore: 0.0,
+                                h1e: vec![0.0; norb*norb], eri: vec![0.0; npair*(npair+1)/2]};
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.is_empty() {continue};
+        if words.len() < 5 {return Err(invalid_data(format!("Error:: invalid integral line in FCIDUMP: {}", line)))};
+        let v = parse_f64(words[0])?;
+        let idx = words[1..5].iter().map(|w| w.parse::<usize>().map_err(|_| invalid_data(format!("Error:: invalid index '{}' in FCIDUMP", w))))
+            .collect::<io::Result<Vec<usize>>>()?;
+        if idx.iter().any(|i| *i > norb) {return Err(invalid_data(format!("Error:: the orbital index in '{}' exceeds NORB", line.trim())))};
+        match (idx[0], idx[1], idx[2], idx[3]) {
+            (0, 0, 0, 0) => data.ecore = v,
+            (p, q, 0, 0) => {
+                data.h1e[p-1 + norb*(q-1)] = v;
+                data.h1e[q-1 + norb*(p-1)] = v;
+            },
+            (p, q, r, s) if p > 0 && q > 0 && r > 0 && s > 0 => {
+                let pq = if p >= q {pair_index(p-1,q-1)} else {pair_index(q-1,p-1)};
+                let rs = if r >= s {pair_index(r-1,s-1)} else {pair_index(s-1,r-1)};
+                data.eri[if pq >= rs {pair_index(pq,rs)} else {pair_index(rs,pq)}] = v;
+            },
+            // orbital energies (p,0,0,0) are not needed
+            _ => {},
+        }
+    }
+    Ok(data)
+}
+
+impl CINTR2CDATA {
+    /// The nuclear repulsion energy `\sum_{A<B} Z_A Z_B / R_AB`.
+    pub fn nuclear_repulsion_energy(&self) -> f64 {
+        let atm = self.get_atm();
+        let env = self.get_env();
+        let coord = |iatm: &Vec<i32>| {let p = iatm[PTR_COORD] as usize; [env[p], env[p+1], env[p+2]]};
+        let mut energy = 0.0;
+        for (a, iatm) in atm.iter().enumerate() {
+            for jatm in atm[..a].iter() {
+                let (ra, rb) = (coord(iatm), coord(jatm));
+                let dist = ((ra[0]-rb[0]).powi(2) + (ra[1]-rb[1]).powi(2) + (ra[2]-rb[2]).powi(2)).sqrt();
+                if dist == 0.0 {continue};
+                energy += (iatm[CHARGE_OF]*jatm[CHARGE_OF]) as f64/dist;
+            }
+        }
+        energy
+    }
+
+    /// The MO ERIs of the orbitals `c_act` of `[nao, nact]` under the 8-fold symmetry (see `FcidumpData::eri`).
+    fn mo_eri_8fold(&mut self, c_act: &[f64], nact: usize) -> Vec<f64> {
+        let npair_mo = nact*(nact+1)/2;
+        let mut eri = vec![0.0; npair_mo*(npair_mo+1)/2];
+        self.ao2mo_for_each_block([c_act; 4], &Ao2moOptions::default(), |pq_range, block| {
+            let nrows = pq_range.len();
+            for (row, pq_full) in pq_range.enumerate() {
+                let (p, q) = (pq_full%nact, pq_full/nact);
+                if p < q {continue};
+                let pq = pair_index(p,q);
+                for s in 0..nact {
+                    for r in s..nact {
+                        let rs = pair_index(r,s);
+                        if rs <= pq {eri[pair_index(pq,rs)] = block[row + nrows*(r + nact*s)]};
+                    }
+                }
+            }
+        });
+        eri
+    }
+
+    /// The FCIDUMP integrals of the active orbitals `ncore..ncore+nact` of `mo_coeff` (`[nao, nmo]`),
+    /// with the first `ncore` orbitals doubly occupied and frozen, for `nelec` active electrons and `2S = ms2`.
+    pub fn fcidump_integrals(&mut self, mo_coeff: &[f64], nmo: usize, ncore: usize, nact: usize, nelec: usize, ms2: i32) -> FcidumpData {
+        let nbas = self.c_nbas;
+        let nao = self.ao_loc()[nbas as usize];
+        if ncore + nact > nmo || mo_coeff.len() != nao*nmo {
+            panic!("Error:: {} core and {} active orbitals are requested from {} MOs of {} AOs", ncore, nact, nmo, nao)
+        }
+        let kinetic = self.int1e_matrix("kinetic", 0..nbas, 0..nbas);
+        let nuclear = self.int1e_matrix("nuclear", 0..nbas, 0..nbas);
+        let mut h_eff: Vec<f64> = kinetic.iter().zip(nuclear.iter()).map(|(t, v)| t + v).collect();
+        let mut ecore = self.nuclear_repulsion_energy();
+        if ncore > 0 {
+            let c_core = &mo_coeff[..nao*ncore];
+            let mut dm = vec![0.0; nao*nao];
+            for m in 0..ncore {
+                let c = &c_core[nao*m..nao*(m+1)];
+                for j in 0..nao {
+                    for i in 0..nao {dm[i + nao*j] += 2.0*c[i]*c[j]};
+                }
+            }
+            let screening = self.screening(0.0, 0.0);
+            let jk = self.get_jk(std::slice::from_ref(&dm), &screening, true, true);
+            for ij in 0..nao*nao {
+                let veff = jk.vj[0][ij] - 0.5*jk.vk[0][ij];
+                ecore += dm[ij]*(h_eff[ij] + 0.5*veff);
+                h_eff[ij] += veff;
+            }
+        }
+        let c_act = &mo_coeff[nao*ncore..nao*(ncore+nact)];
+        let h1e = linalg::matmul_tn(c_act, &linalg::matmul(&h_eff, c_act, nao, nao, nact), nact, nao, nact);
+        let eri = self.mo_eri_8fold(c_act, nact);
+        FcidumpData {norb: nact, nelec, ms2, isym: 1, orbsym: vec![1; nact], ecore, h1e, eri}
+    }
+}
+
+#[test]
+pub fn test_fcidump() {
+    let mut cint_data = crate::test_h2_data();
+    let nao = 8;
+    let nmo = 4;
+    let mo_coeff: Vec<f64> = (0..nao*nmo).map(|i| 0.2*((i*i) as f64).cos()).collect();
+    let data = cint_data.fcidump_integrals(&mo_coeff, nmo, 1, 3, 2, 0);
+
+    // the reference from the full AO ERI tensor
+    let ao_loc = cint_data.ao_loc();
+    let nbas = cint_data.get_nbas() as usize;
+    let eri = crate::full_eri(&mut cint_data);
+    let c = |mu: usize, m: usize| mo_coeff[mu + nao*m];
+    let mo_eri = |p: usize, q: usize, r: usize, s: usize| {
+        let mut v = 0.0;
+        for a in 0..nao {for b in 0..nao {for e in 0..nao {for f in 0..nao {
+            v += c(a,p)*c(b,q)*c(e,r)*c(f,s)*eri[a + nao*(b + nao*(e + nao*f))];
+        }}}}
+        v
+    };
+    assert!((data.eri(0,1,2,1) - mo_eri(1,2,3,2)).abs() < 1.0e-10);
+    assert!((data.eri(2,2,0,1) - mo_eri(1,2,3,3)).abs() < 1.0e-10);
+    // the frozen core of one doubly occupied orbital
+    let nuc = cint_data.nuclear_repulsion_energy();
+    assert!((nuc - 1.0/1.6).abs() < 1.0e-12);
+    let kinetic = cint_data.int1e_matrix("kinetic", 0..4, 0..4);
+    let nuclear = cint_data.int1e_matrix("nuclear", 0..4, 0..4);
+    let h_mo = |p: usize, q: usize| (0..nao*nao).map(|ab| c(ab%nao,p)*c(ab/nao,q)*(kinetic[ab] + nuclear[ab])).sum::<f64>();
+    assert!((data.ecore - (nuc + 2.0*h_mo(0,0) + mo_eri(0,0,0,0))).abs() < 1.0e-10);
+    assert!((data.h1e[5] - (h_mo(3,2) + 2.0*mo_eri(3,2,0,0) - mo_eri(3,0,0,2))).abs() < 1.0e-10); // (2,1)
+
+    let content = data.to_fcidump_string(0.0);
+    let read = parse_fcidump(&content).unwrap();
+    assert_eq!((read.norb, read.nelec, read.ms2), (3, 2, 0));
+    read.eri.iter().zip(data.eri.iter()).for_each(|(a, b)| assert!((a - b).abs() < 1.0e-14));
+    read.h1e.iter().zip(data.h1e.iter()).for_each(|(a, b)| assert!((a - b).abs() < 1.0e-14));
+    assert!((read.ecore - data.ecore).abs() < 1.0e-14);
+    cint_data.final_c2r();
+}
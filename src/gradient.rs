@@ -0,0 +1,389 @@
+//! Contractions of the derivative integrals for analytic SCF nuclear gradients.
+//!
+//! All gradients are returned as `column-major` matrices of `[3, natm]`, i.e. the x, y and z
+//! components of each atom of the `CINTR2CDATA`, and the density matrices are symmetric
+//! `[nao, nao]` matrices. For the SCF energy
+//!
+//! E = \sum_{ij} D_{ij} h_{ij} + E_2e[D] + E_nuc
+//!
+//! the one-electron terms are
+//! 1) `grad_ovlp`: `-\sum_{ij} W_{ij} dS_{ij}/dR` with the energy-weighted density matrix `W`,
+//! 2) `grad_kin`: `\sum_{ij} D_{ij} dT_{ij}/dR`,
+//! 3) `grad_nuc`: the basis-function (Pulay) part of `\sum_{ij} D_{ij} dV_{ij}/dR`,
+//! 4) `grad_hellmann_feynman`: the operator part of `\sum_{ij} D_{ij} dV_{ij}/dR`, from `int1e_iprinv` at each nucleus.
+//!
+//! The density-fitted two-electron terms are given by `grad_ri_coulomb` for
+//! `E_J = 1/2 \sum D_{ij} (ij|kl) D_{kl}` and `grad_ri_exchange` for `E_K = 1/2 \sum D_{ik} (ij|kl) D_{jl}`,
+//! such that `E_2e = E_J - 1/2 E_K[D]` for restricted and `E_2e = E_J - E_K[D_a] - E_K[D_b]`
+//! for unrestricted Hartree-Fock. When the auxiliary basis is attached by `conc_cint_data`, its shells
+//! belong to the duplicated atoms after the orbital ones, whose gradients should be added to the original atoms.
+//!
+//! Unlike the other drivers, these are not generic over `IntegralEngine`: they need the atoms of the shells,
+//! the nuclear charges and the `rinv` origin of the basis data.
+use std::ops::Range;
+
+use crate::{CINTR2CDATA, linalg};
+use crate::density_fitting::{MetricDecomposition, metric_inverse_sqrt, pair_index};
+
+// slots of atm
+const CHARGE_OF: usize = 0;
+const PTR_COORD: usize = 1;
+// slot of env
+const PTR_RINV_ORIG: usize = 4;
+
+/// The one-electron contributions to the nuclear gradients, each of `[3, natm]`.
+#[derive(Clone,Debug)]
+pub struct OneElectronGradient {
+    pub ovlp: Vec<f64>,
+    pub kin: Vec<f64>,
+    pub nuc: Vec<f64>,
+    pub hellmann_feynman: Vec<f64>,
+}
+
+impl OneElectronGradient {
+    pub fn total(&self) -> Vec<f64> {
+        (0..self.ovlp.len()).map(|x| self.ovlp[x] + self.kin[x] + self.nuc[x] + self.hellmann_feynman[x]).collect()
+    }
+}
+
+impl CINTR2CDATA {
+    fn ip1e_optimizer_rust(&mut self, op_name: &str) {
+        match op_name.to_lowercase().as_str() {
+            "ipovlp" => self.int1e_ipovlp_optimizer_rust(),
+            "ipkin" => self.int1e_ipkin_optimizer_rust(),
+            "ipnuc" => self.int1e_ipnuc_optimizer_rust(),
+            "iprinv" => self.int1e_iprinv_optimizer_rust(),
+            _ => panic!("Error:: Unknown operator for GTO-ij-ip integrals {}", op_name),
+        }
+    }
+
+    /// `\sum_{i on A, j} <\nabla i|op|j> M_{ij}` for each atom `A`, `[3, natm]`
+    fn contract_ip1e_by_atom(&mut self, op_name: &str, mat: &[f64]) -> Vec<f64> {
+        let ao_loc = self.ao_loc();
+        let nbas = self.c_nbas;
+        let nao = ao_loc[nbas as usize];
+        let shell_atoms = self.shell_atoms();
+        let mut grad = vec![0.0; 3*self.c_natm as usize];
+        self.ip1e_optimizer_rust(op_name);
+        for i in 0..nbas {
+            let (i0, di) = (ao_loc[i as usize], ao_loc[i as usize+1]-ao_loc[i as usize]);
+            let atm_id = shell_atoms[i as usize];
+            for j in 0..nbas {
+                let (j0, dj) = (ao_loc[j as usize], ao_loc[j as usize+1]-ao_loc[j as usize]);
+                let buf = self.cint_ip_ij(i,j,op_name);
+                for x in 0..3 {
+                    let mut v = 0.0;
+                    for jj in 0..dj {
+                        for ii in 0..di {
+                            v += buf[ii + di*(jj + dj*x)]*mat[i0+ii + nao*(j0+jj)];
+                        }
+                    }
+                    grad[x + 3*atm_id] += v;
+                }
+            }
+        }
+        grad
+    }
+
+    /// `-\sum_{ij} W_{ij} dS_{ij}/dR` with the energy-weighted density matrix `dme`
+    pub fn grad_ovlp(&mut self, dme: &[f64]) -> Vec<f64> {
+        self.contract_ip1e_by_atom("ipovlp", dme).iter().map(|x| 2.0*x).collect()
+    }
+
+    /// `\sum_{ij} D_{ij} dT_{ij}/dR`
+    pub fn grad_kin(&mut self, dm: &[f64]) -> Vec<f64> {
+        self.contract_ip1e_by_atom("ipkin", dm).iter().map(|x| -2.0*x).collect()
+    }
+
+    /// the derivatives of the basis functions in `\sum_{ij} D_{ij} dV_{ij}/dR`
+    pub fn grad_nuc(&mut self, dm: &[f64]) -> Vec<f64> {
+        self.contract_ip1e_by_atom("ipnuc", dm).iter().map(|x| -2.0*x).collect()
+    }
+
+    /// the derivatives of the nuclear attraction operator in `\sum_{ij} D_{ij} dV_{ij}/dR`
+    ///
+    /// The rinv origin of `env` is moved to each nucleus in turn and restored on exit.
+    pub fn grad_hellmann_feynman(&mut self, dm: &[f64]) -> Vec<f64> {
+        let atm = self.get_atm();
+        let env = self.get_env();
+        let rinv_origin = [env[PTR_RINV_ORIG], env[PTR_RINV_ORIG+1], env[PTR_RINV_ORIG+2]];
+        let ao_loc = self.ao_loc();
+        let nbas = self.c_nbas;
+        let nao = ao_loc[nbas as usize];
+        let op = "iprinv";
+        let mut grad = vec![0.0; 3*atm.len()];
+        for (atm_id, iatm) in atm.iter().enumerate() {
+            let charge = iatm[CHARGE_OF] as f64;
+            if charge == 0.0 {continue};
+            let ptr = iatm[PTR_COORD] as usize;
+            self.set_rinv_origin(&[env[ptr], env[ptr+1], env[ptr+2]]);
+            self.int1e_iprinv_optimizer_rust();
+            for i in 0..nbas {
+                let (i0, di) = (ao_loc[i as usize], ao_loc[i as usize+1]-ao_loc[i as usize]);
+                for j in 0..nbas {
+                    let (j0, dj) = (ao_loc[j as usize], ao_loc[j as usize+1]-ao_loc[j as usize]);
+                    let buf = self.cint_ip_ij(i,j,op);
+                    for x in 0..3 {
+                        for jj in 0..dj {
+                            for ii in 0..di {
+                                grad[x + 3*atm_id] -= 2.0*charge*buf[ii + di*(jj + dj*x)]*dm[i0+ii + nao*(j0+jj)];
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        self.set_rinv_origin(&rinv_origin);
+        grad
+    }
+
+    /// All one-electron contributions with the density matrix `dm` and the energy-weighted density matrix `dme`.
+    pub fn grad_1e(&mut self, dm: &[f64], dme: &[f64]) -> OneElectronGradient {
+        OneElectronGradient {
+            ovlp: self.grad_ovlp(dme),
+            kin: self.grad_kin(dm),
+            nuc: self.grad_nuc(dm),
+            hellmann_feynman: self.grad_hellmann_feynman(dm),
+        }
+    }
+
+    /// The gradients of the nuclear repulsion energy.
+    pub fn grad_nuclear_repulsion(&self) -> Vec<f64> {
+        let atm = self.get_atm();
+        let env = self.get_env();
+        let coord = |iatm: &Vec<i32>| {let p = iatm[PTR_COORD] as usize; [env[p], env[p+1], env[p+2]]};
+        let mut grad = vec![0.0; 3*atm.len()];
+        for (a, iatm) in atm.iter().enumerate() {
+            let (za, ra) = (iatm[CHARGE_OF] as f64, coord(iatm));
+            for (b, jatm) in atm.iter().enumerate() {
+                if a == b {continue};
+                let (zb, rb) = (jatm[CHARGE_OF] as f64, coord(jatm));
+                let r = [ra[0]-rb[0], ra[1]-rb[1], ra[2]-rb[2]];
+                let dist = (r[0]*r[0] + r[1]*r[1] + r[2]*r[2]).sqrt();
+                if dist == 0.0 {continue};
+                (0..3).for_each(|x| grad[x + 3*a] -= za*zb*r[x]/dist.powi(3));
+            }
+        }
+        grad
+    }
+
+    /// The gradients of the density-fitted Coulomb energy `E_J = 1/2 \sum D_{ij} (ij|kl) D_{kl}`.
+    pub fn grad_ri_coulomb(&mut self, dm: &[f64], orb_shls: Range<i32>, aux_shls: Range<i32>, decomposition: MetricDecomposition) -> Vec<f64> {
+        let ao_loc = self.ao_loc();
+        let nao = ao_loc[orb_shls.end as usize] - ao_loc[orb_shls.start as usize];
+        let npair = nao*(nao+1)/2;
+        let naux = ao_loc[aux_shls.end as usize] - ao_loc[aux_shls.start as usize];
+        let mut dm_packed = vec![0.0; npair];
+        for j in 0..nao {
+            for i in j..nao {
+                dm_packed[pair_index(i,j)] = if i == j {dm[i + nao*i]} else {dm[i + nao*j] + dm[j + nao*i]};
+            }
+        }
+        let metric = self.int2c2e_metric(aux_shls.clone());
+        let (m, nkept) = metric_inverse_sqrt(metric, naux, decomposition);
+        let tensor = self.int3c2e_packed(orb_shls.clone(), aux_shls.clone());
+        let gamma = linalg::matmul_tn(&tensor, &dm_packed, naux, npair, 1);
+        let coeff = linalg::matmul(&m, &linalg::matmul_tn(&m, &gamma, nkept, naux, 1), naux, nkept, 1);
+
+        let g_3c = |i: usize, j: usize, p: usize| dm[i + nao*j]*coeff[p];
+        let w_2c: Vec<f64> = (0..naux*naux).map(|pq| coeff[pq%naux]*coeff[pq/naux]).collect();
+        self.contract_ri_derivatives(orb_shls, aux_shls, g_3c, &w_2c)
+    }
+
+    /// The gradients of the density-fitted exchange-type energy `E_K = 1/2 \sum D_{ik} (ij|kl) D_{jl}`.
+    pub fn grad_ri_exchange(&mut self, dm: &[f64], orb_shls: Range<i32>, aux_shls: Range<i32>, decomposition: MetricDecomposition) -> Vec<f64> {
+        let ao_loc = self.ao_loc();
+        let nao = ao_loc[orb_shls.end as usize] - ao_loc[orb_shls.start as usize];
+        let nao2 = nao*nao;
+        let npair = nao*(nao+1)/2;
+        let naux = ao_loc[aux_shls.end as usize] - ao_loc[aux_shls.start as usize];
+        let metric = self.int2c2e_metric(aux_shls.clone());
+        let (m, nkept) = metric_inverse_sqrt(metric, naux, decomposition);
+        let vinv = linalg::matmul(&m, &transpose(&m, naux, nkept), naux, nkept, naux);
+        let tensor = self.int3c2e_packed(orb_shls.clone(), aux_shls.clone());
+        // A^P = \sum_Q V^{-1}_{PQ} (ij|Q) and G^P = D A^P D, both of [nao*nao, naux]
+        let a_packed = linalg::matmul(&tensor, &vinv, npair, naux, naux);
+        let mut a_full = vec![0.0; nao2*naux];
+        let mut g_full = vec![0.0; nao2*naux];
+        for p in 0..naux {
+            let a_p = &mut a_full[nao2*p..nao2*(p+1)];
+            for j in 0..nao {
+                for i in 0..nao {a_p[i + nao*j] = a_packed[pair_index(i,j) + npair*p]};
+            }
+            let g_p = linalg::matmul(&linalg::matmul(dm, a_p, nao, nao, nao), dm, nao, nao, nao);
+            g_full[nao2*p..nao2*(p+1)].copy_from_slice(&g_p);
+        }
+        let w_2c = linalg::matmul_tn(&a_full, &g_full, naux, nao2, naux);
+        let g_3c = |i: usize, j: usize, p: usize| g_full[i + nao*j + nao2*p];
+        self.contract_ri_derivatives(orb_shls, aux_shls, g_3c, &w_2c)
+    }
+
+    /// `\sum_{ijP} G^P_{ij} d(ij|P)/dR - 1/2 \sum_{PQ} W_{PQ} d(P|Q)/dR` for a symmetric `G^P` and `W`,
+    /// using only the `ip1` integrals and the translational invariance for the derivatives on the other centers.
+    fn contract_ri_derivatives<G>(&mut self, orb_shls: Range<i32>, aux_shls: Range<i32>, g_3c: G, w_2c: &[f64]) -> Vec<f64>
+    where G: Fn(usize,usize,usize) -> f64 {
+        let ao_loc = self.ao_loc();
+        let i_off = ao_loc[orb_shls.start as usize];
+        let p_off = ao_loc[aux_shls.start as usize];
+        let naux = ao_loc[aux_shls.end as usize] - p_off;
+        let shell_atoms = self.shell_atoms();
+        let mut grad = vec![0.0; 3*self.c_natm as usize];
+        let op = "ip1";
+
+        // d(ij|P)/dR_A = -(\nabla i j|P) for i on A, and the same with the opposite sign for P on A,
+        // the contribution of j being included by the symmetry of G^P
+        self.cint3c2e_ip1_optimizer_rust();
+        for k in aux_shls.clone() {
+            let (k0, dk) = (ao_loc[k as usize] - p_off, ao_loc[k as usize+1]-ao_loc[k as usize]);
+            let atm_k = shell_atoms[k as usize];
+            for i in orb_shls.clone() {
+                let (i0, di) = (ao_loc[i as usize] - i_off, ao_loc[i as usize+1]-ao_loc[i as usize]);
+                let atm_i = shell_atoms[i as usize];
+                for j in orb_shls.clone() {
+                    let (j0, dj) = (ao_loc[j as usize] - i_off, ao_loc[j as usize+1]-ao_loc[j as usize]);
+                    let buf = self.cint_ip_3c2e(i,j,k,op);
+                    for x in 0..3 {
+                        let mut v = 0.0;
+                        for p in 0..dk {
+                            for jj in 0..dj {
+                                for ii in 0..di {
+                                    v += buf[ii + di*(jj + dj*(p + dk*x))]*g_3c(i0+ii, j0+jj, k0+p);
+                                }
+                            }
+                        }
+                        grad[x + 3*atm_i] -= 2.0*v;
+                        grad[x + 3*atm_k] += 2.0*v;
+                    }
+                }
+            }
+        }
+        // -1/2 \sum_{PQ} W_{PQ} d(P|Q)/dR_A = \sum_{P on A, Q} W_{PQ} (\nabla P|Q) for a symmetric W
+        self.cint2c2e_ip1_optimizer_rust();
+        for k in aux_shls.clone() {
+            let (k0, dk) = (ao_loc[k as usize] - p_off, ao_loc[k as usize+1]-ao_loc[k as usize]);
+            let atm_k = shell_atoms[k as usize];
+            for l in aux_shls.clone() {
+                let (l0, dl) = (ao_loc[l as usize] - p_off, ao_loc[l as usize+1]-ao_loc[l as usize]);
+                let buf = self.cint_ip_2c2e(k,l);
+                for x in 0..3 {
+                    let mut v = 0.0;
+                    for q in 0..dl {
+                        for p in 0..dk {
+                            v += buf[p + dk*(q + dl*x)]*w_2c[k0+p + naux*(l0+q)];
+                        }
+                    }
+                    grad[x + 3*atm_k] += v;
+                }
+            }
+        }
+        grad
+    }
+}
+
+fn transpose(a: &[f64], nrow: usize, ncol: usize) -> Vec<f64> {
+    let mut b = vec![0.0; nrow*ncol];
+    for j in 0..ncol {
+        for i in 0..nrow {b[j + ncol*i] = a[i + nrow*j]};
+    }
+    b
+}
+
+#[test]
+pub fn test_gradient_translational_invariance() {
+    let mut cint_data = crate::test_h2_data();
+    let nao = 8;
+    let dm: Vec<f64> = (0..nao*nao).map(|pq| {let (p,q) = (pq%nao, pq/nao); 0.2*((p*q) as f64).cos()}).collect();
+    let dme: Vec<f64> = dm.iter().map(|x| -0.5*x).collect();
+    let grad_1e = cint_data.grad_1e(&dm, &dme);
+    let natm = cint_data.get_natm() as usize;
+    // the sum over all atoms vanishes for each term of the hamiltonian
+    let sum_atoms = |g: &[f64], x: usize| (0..g.len()/3).map(|a| g[x + 3*a]).sum::<f64>();
+    let h1 = (0..3*natm).map(|x| grad_1e.kin[x] + grad_1e.nuc[x] + grad_1e.hellmann_feynman[x]).collect::<Vec<f64>>();
+    for x in 0..3 {
+        assert!(sum_atoms(&grad_1e.ovlp, x).abs() < 1.0e-10);
+        assert!(sum_atoms(&grad_1e.kin, x).abs() < 1.0e-10);
+        assert!(sum_atoms(&h1, x).abs() < 1.0e-10);
+        assert!(sum_atoms(&cint_data.grad_nuclear_repulsion(), x).abs() < 1.0e-10);
+    }
+
+    let mut aux = crate::test_h2_aux_data();
+    let mut ri_data = cint_data.conc_cint_data(&aux);
+    let nbas = ri_data.get_nbas();
+    let grad_j = ri_data.grad_ri_coulomb(&dm, 0..4, 4..nbas, MetricDecomposition::Cholesky);
+    let grad_k = ri_data.grad_ri_exchange(&dm, 0..4, 4..nbas, MetricDecomposition::Cholesky);
+    assert_eq!(grad_j.len(), 3*ri_data.get_natm() as usize);
+    for x in 0..3 {
+        assert!(sum_atoms(&grad_j, x).abs() < 1.0e-10);
+        assert!(sum_atoms(&grad_k, x).abs() < 1.0e-10);
+    }
+    ri_data.final_c2r();
+    aux.final_c2r();
+    cint_data.final_c2r();
+}
+
+/// a copy of `cint_data` with the atoms `atoms` displaced by `h` along the axis `x`
+#[cfg(test)]
+fn displaced(cint_data: &CINTR2CDATA, atoms: &[usize], x: usize, h: f64) -> CINTR2CDATA {
+    let atm = cint_data.get_atm();
+    let bas = cint_data.get_bas();
+    let mut env = cint_data.get_env();
+    atoms.iter().for_each(|a| env[atm[*a][PTR_COORD] as usize + x] += h);
+    let mut displaced = CINTR2CDATA::new();
+    displaced.set_cint_type(&cint_data.get_cint_type());
+    displaced.initial_r2c(&atm, atm.len() as i32, &bas, bas.len() as i32, &env);
+    displaced
+}
+
+#[test]
+pub fn test_gradient_finite_difference() {
+    let mut cint_data = crate::test_h2_data();
+    let nao = 8;
+    let dm: Vec<f64> = (0..nao*nao).map(|pq| {let (p,q) = (pq%nao, pq/nao); 0.2*((p*q) as f64).cos()}).collect();
+    let dme: Vec<f64> = dm.iter().map(|x| -0.5*x).collect();
+    let dot = |a: &[f64], b: &[f64]| a.iter().zip(b).map(|(x,y)| x*y).sum::<f64>();
+    let h = 1.0e-4;
+
+    // E_1e = \sum D (T + V) - \sum W S for the fixed D and W
+    cint_data.set_rinv_origin(&[0.1, 0.2, 0.3]);
+    let grad_1e = cint_data.grad_1e(&dm, &dme).total();
+    assert_eq!(&cint_data.get_env()[PTR_RINV_ORIG..PTR_RINV_ORIG+3], &[0.1, 0.2, 0.3]);
+    let energy_1e = |data: &mut CINTR2CDATA| {
+        let e = dot(&dm, &data.int1e_matrix("kinetic", 0..4, 0..4)) + dot(&dm, &data.int1e_matrix("nuclear", 0..4, 0..4))
+            - dot(&dme, &data.int1e_matrix("ovlp", 0..4, 0..4));
+        data.final_c2r();
+        e
+    };
+    for a in 0..2 {
+        for x in 0..3 {
+            let fd = (energy_1e(&mut displaced(&cint_data, &[a], x, h)) - energy_1e(&mut displaced(&cint_data, &[a], x, -h)))/(2.0*h);
+            assert!((grad_1e[x + 3*a] - fd).abs() < 1.0e-6, "1e gradient {} of atom {}: {} vs {}", x, a, grad_1e[x + 3*a], fd);
+        }
+    }
+
+    // E_J = 1/2 \sum D J[D] and E_K = 1/2 \sum D K[D], with the auxiliary atoms moved along
+    let mut aux = crate::test_h2_aux_data();
+    let mut ri_data = cint_data.conc_cint_data(&aux);
+    let nbas = ri_data.get_nbas();
+    let grad_j = ri_data.grad_ri_coulomb(&dm, 0..4, 4..nbas, MetricDecomposition::Cholesky);
+    let grad_k = ri_data.grad_ri_exchange(&dm, 0..4, 4..nbas, MetricDecomposition::Cholesky);
+    let energy_jk = |data: &mut CINTR2CDATA| {
+        let dms = [dm.clone()];
+        let e_j = 0.5*dot(&dm, &data.get_j_ri(&dms, 0..4, 4..nbas, MetricDecomposition::Cholesky, 100)[0]);
+        let e_k = 0.5*dot(&dm, &data.get_k_ri(&dms, 0..4, 4..nbas, MetricDecomposition::Cholesky, 100)[0]);
+        data.final_c2r();
+        (e_j, e_k)
+    };
+    for a in 0..2 {
+        for x in 0..3 {
+            let (j_plus, k_plus) = energy_jk(&mut displaced(&ri_data, &[a, a+2], x, h));
+            let (j_minus, k_minus) = energy_jk(&mut displaced(&ri_data, &[a, a+2], x, -h));
+            let (fd_j, fd_k) = ((j_plus - j_minus)/(2.0*h), (k_plus - k_minus)/(2.0*h));
+            let (g_j, g_k) = (grad_j[x + 3*a] + grad_j[x + 3*(a+2)], grad_k[x + 3*a] + grad_k[x + 3*(a+2)]);
+            assert!((g_j - fd_j).abs() < 1.0e-6, "RI-J gradient {} of atom {}: {} vs {}", x, a, g_j, fd_j);
+            assert!((g_k - fd_k).abs() < 1.0e-6, "RI-K gradient {} of atom {}: {} vs {}", x, a, g_k, fd_k);
+        }
+    }
+    ri_data.final_c2r();
+    aux.final_c2r();
+    cint_data.final_c2r();
+}
@@ -0,0 +1,433 @@
+//! HDF5 storage of the integral tensors and the basis data (feature `hdf5`).
+//!
+//! The datasets hold the `column-major` buffers of the crate unchanged, with the HDF5 dimensions
+//! given in the reversed (row-major) order, so that `h5py`/`numpy` see the transposes of the
+//! matrices, e.g. `[naux, npair]` for the RI factors of `[npair, naux]`. `atm` and `bas` are
+//! stored row by row as `[natm, 6]` and `[nbas, 8]`, as in PySCF.
+//!
+//! The attributes `cint_type`, `nao`, `unit` and `version` are attached to the basis group, and
+//! the dimensions (`nao`, `naux`, `symmetry`) to the integral datasets, so that a restart can check
+//! the stored integrals against its own basis before skipping their evaluation.
+use std::ffi::CString;
+use std::io;
+use std::os::raw::{c_char, c_int, c_uint, c_void};
+
+use crate::{CINTR2CDATA, CintType};
+use crate::density_fitting::RIFactors;
+
+#[allow(non_camel_case_types)]
+type hid_t = i64;
+#[allow(non_camel_case_types)]
+type herr_t = c_int;
+#[allow(non_camel_case_types)]
+type htri_t = c_int;
+#[allow(non_camel_case_types)]
+type hsize_t = u64;
+
+const H5F_ACC_RDONLY: c_uint = 0x0000;
+const H5F_ACC_RDWR: c_uint = 0x0001;
+const H5F_ACC_TRUNC: c_uint = 0x0002;
+const H5P_DEFAULT: hid_t = 0;
+const H5S_ALL: hid_t = 0;
+const H5S_SCALAR: c_int = 0;
+
+// the subset of the HDF5 (>= 1.10) C API used here
+extern "C" {
+    static H5T_NATIVE_DOUBLE_g: hid_t;
+    static H5T_NATIVE_INT_g: hid_t;
+    static H5T_NATIVE_LLONG_g: hid_t;
+    static H5T_C_S1_g: hid_t;
+    fn H5open() -> herr_t;
+    fn H5Fcreate(name: *const c_char, flags: c_uint, fcpl_id: hid_t, fapl_id: hid_t) -> hid_t;
+    fn H5Fopen(name: *const c_char, flags: c_uint, fapl_id: hid_t) -> hid_t;
+    fn H5Fclose(file_id: hid_t) -> herr_t;
+    fn H5Gcreate2(loc_id: hid_t, name: *const c_char, lcpl_id: hid_t, gcpl_id: hid_t, gapl_id: hid_t) -> hid_t;
+    fn H5Gclose(group_id: hid_t) -> herr_t;
+    fn H5Lexists(loc_id: hid_t, name: *const c_char, lapl_id: hid_t) -> htri_t;
+    fn H5Ldelete(loc_id: hid_t, name: *const c_char, lapl_id: hid_t) -> herr_t;
+    fn H5Oopen(loc_id: hid_t, name: *const c_char, lapl_id: hid_t) -> hid_t;
+    fn H5Oclose(object_id: hid_t) -> herr_t;
+    fn H5Screate(type_: c_int) -> hid_t;
+    fn H5Screate_simple(rank: c_int, dims: *const hsize_t, maxdims: *const hsize_t) -> hid_t;
+    fn H5Sget_simple_extent_ndims(space_id: hid_t) -> c_int;
+    fn H5Sget_simple_extent_dims(space_id: hid_t, dims: *mut hsize_t, maxdims: *mut hsize_t) -> c_int;
+    fn H5Sclose(space_id: hid_t) -> herr_t;
+    fn H5Dcreate2(loc_id: hid_t, name: *const c_char, type_id: hid_t, space_id: hid_t,
+                  lcpl_id: hid_t, dcpl_id: hid_t, dapl_id: hid_t) -> hid_t;
+    fn H5Dopen2(loc_id: hid_t, name: *const c_char, dapl_id: hid_t) -> hid_t;
+    fn H5Dget_space(dset_id: hid_t) -> hid_t;
+    fn H5Dwrite(dset_id: hid_t, mem_type_id: hid_t, mem_space_id: hid_t, file_space_id: hid_t,
+                plist_id: hid_t, buf: *const c_void) -> herr_t;
+    fn H5Dread(dset_id: hid_t, mem_type_id: hid_t, mem_space_id: hid_t, file_space_id: hid_t,
+               plist_id: hid_t, buf: *mut c_void) -> herr_t;
+    fn H5Dclose(dset_id: hid_t) -> herr_t;
+    fn H5Acreate2(loc_id: hid_t, attr_name: *const c_char, type_id: hid_t, space_id: hid_t,
+                  acpl_id: hid_t, aapl_id: hid_t) -> hid_t;
+    fn H5Aopen(obj_id: hid_t, attr_name: *const c_char, aapl_id: hid_t) -> hid_t;
+    fn H5Aexists(obj_id: hid_t, attr_name: *const c_char) -> htri_t;
+    fn H5Adelete(loc_id: hid_t, attr_name: *const c_char) -> herr_t;
+    fn H5Awrite(attr_id: hid_t, type_id: hid_t, buf: *const c_void) -> herr_t;
+    fn H5Aread(attr_id: hid_t, type_id: hid_t, buf: *mut c_void) -> herr_t;
+    fn H5Aget_type(attr_id: hid_t) -> hid_t;
+    fn H5Aclose(attr_id: hid_t) -> herr_t;
+    fn H5Tcopy(type_id: hid_t) -> hid_t;
+    fn H5Tset_size(type_id: hid_t, size: usize) -> herr_t;
+    fn H5Tget_size(type_id: hid_t) -> usize;
+    fn H5Tclose(type_id: hid_t) -> herr_t;
+}
+
+fn h5_error(msg: String) -> io::Error {
+    io::Error::other(msg)
+}
+
+fn c_name(name: &str) -> io::Result<CString> {
+    CString::new(name).map_err(|_| h5_error(format!("Error:: invalid HDF5 name '{}'", name)))
+}
+
+fn check_id(id: hid_t, what: &str) -> io::Result<hid_t> {
+    if id < 0 {Err(h5_error(format!("Error:: HDF5 failed to {}", what)))} else {Ok(id)}
+}
+
+fn check_err(err: herr_t, what: &str) -> io::Result<()> {
+    if err < 0 {Err(h5_error(format!("Error:: HDF5 failed to {}", what)))} else {Ok(())}
+}
+
+/// The attribute values supported on the datasets and groups.
+#[derive(Clone,Debug,PartialEq)]
+pub enum Hdf5Attr {
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+/// An HDF5 file, closed on drop.
+pub struct Hdf5File {
+    file_id: hid_t,
+}
+
+impl Hdf5File {
+    /// Create (or truncate) the file `path`.
+    pub fn create(path: &str) -> io::Result<Hdf5File> {
+        let name = c_name(path)?;
+        let file_id = unsafe {
+            H5open();
+            H5Fcreate(name.as_ptr(), H5F_ACC_TRUNC, H5P_DEFAULT, H5P_DEFAULT)
+        };
+        Ok(Hdf5File {file_id: check_id(file_id, &format!("create {}", path))?})
+    }
+
+    /// Open the existing file `path`, read-only unless `writable`.
+    pub fn open(path: &str, writable: bool) -> io::Result<Hdf5File> {
+        let name = c_name(path)?;
+        let flags = if writable {H5F_ACC_RDWR} else {H5F_ACC_RDONLY};
+        let file_id = unsafe {
+            H5open();
+            H5Fopen(name.as_ptr(), flags, H5P_DEFAULT)
+        };
+        Ok(Hdf5File {file_id: check_id(file_id, &format!("open {}", path))?})
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        let mut path = String::new();
+        for part in name.split('/').filter(|p| !p.is_empty()) {
+            path = if path.is_empty() {part.to_string()} else {format!("{}/{}", path, part)};
+            let Ok(c_path) = c_name(&path) else {return false};
+            if unsafe {H5Lexists(self.file_id, c_path.as_ptr(), H5P_DEFAULT)} <= 0 {return false};
+        }
+        true
+    }
+
+    /// Create the missing groups of the path `name`, e.g. "basis" for "basis/atm".
+    fn ensure_parent_groups(&self, name: &str) -> io::Result<()> {
+        let parts: Vec<&str> = name.split('/').filter(|p| !p.is_empty()).collect();
+        let mut path = String::new();
+        for part in parts[..parts.len().saturating_sub(1)].iter() {
+            path = if path.is_empty() {part.to_string()} else {format!("{}/{}", path, part)};
+            if !self.exists(&path) {
+                let c_path = c_name(&path)?;
+                let group_id = unsafe {H5Gcreate2(self.file_id, c_path.as_ptr(), H5P_DEFAULT, H5P_DEFAULT, H5P_DEFAULT)};
+                check_id(group_id, &format!("create the group {}", path))?;
+                unsafe {H5Gclose(group_id)};
+            }
+        }
+        Ok(())
+    }
+
+    /// Create the group `name` (and its parents) if missing.
+    pub fn create_group(&self, name: &str) -> io::Result<()> {
+        self.ensure_parent_groups(&format!("{}/_", name.trim_end_matches('/')))
+    }
+
+    fn write_raw(&self, name: &str, type_id: hid_t, buf: *const c_void, dims: &[usize]) -> io::Result<()> {
+        self.ensure_parent_groups(name)?;
+        let c_path = c_name(name)?;
+        if self.exists(name) {
+            check_err(unsafe {H5Ldelete(self.file_id, c_path.as_ptr(), H5P_DEFAULT)}, &format!("replace {}", name))?;
+        }
+        // the row-major dimensions of the column-major buffer
+        let h5_dims: Vec<hsize_t> = dims.iter().rev().map(|d| *d as hsize_t).collect();
+        unsafe {
+            let space_id = check_id(H5Screate_simple(h5_dims.len() as c_int, h5_dims.as_ptr(), std::ptr::null()), "create a dataspace")?;
+            let dset_id = H5Dcreate2(self.file_id, c_path.as_ptr(), type_id, space_id, H5P_DEFAULT, H5P_DEFAULT, H5P_DEFAULT);
+            H5Sclose(space_id);
+            let dset_id = check_id(dset_id, &format!("create the dataset {}", name))?;
+            let err = H5Dwrite(dset_id, type_id, H5S_ALL, H5S_ALL, H5P_DEFAULT, buf);
+            H5Dclose(dset_id);
+            check_err(err, &format!("write the dataset {}", name))
+        }
+    }
+
+    /// the `column-major` dimensions of the dataset `name`
+    pub fn dims(&self, name: &str) -> io::Result<Vec<usize>> {
+        let c_path = c_name(name)?;
+        unsafe {
+            let dset_id = check_id(H5Dopen2(self.file_id, c_path.as_ptr(), H5P_DEFAULT), &format!("open the dataset {}", name))?;
+            let space_id = H5Dget_space(dset_id);
+            let rank = H5Sget_simple_extent_ndims(space_id).max(0) as usize;
+            let mut h5_dims = vec![0 as hsize_t; rank];
+            H5Sget_simple_extent_dims(space_id, h5_dims.as_mut_ptr(), std::ptr::null_mut());
+            H5Sclose(space_id);
+            H5Dclose(dset_id);
+            Ok(h5_dims.iter().rev().map(|d| *d as usize).collect())
+        }
+    }
+
+    fn read_raw(&self, name: &str, type_id: hid_t, buf: *mut c_void) -> io::Result<()> {
+        let c_path = c_name(name)?;
+        unsafe {
+            let dset_id = check_id(H5Dopen2(self.file_id, c_path.as_ptr(), H5P_DEFAULT), &format!("open the dataset {}", name))?;
+            let err = H5Dread(dset_id, type_id, H5S_ALL, H5S_ALL, H5P_DEFAULT, buf);
+            H5Dclose(dset_id);
+            check_err(err, &format!("read the dataset {}", name))
+        }
+    }
+
+    /// Write the `column-major` array `data` of the dimensions `dims` to the dataset `name`, replacing an existing one.
+    pub fn write_f64(&self, name: &str, data: &[f64], dims: &[usize]) -> io::Result<()> {
+        if dims.iter().product::<usize>() != data.len() {
+            return Err(h5_error(format!("Error:: {} elements do not match the dimensions {:?} of {}", data.len(), dims, name)))
+        }
+        self.write_raw(name, unsafe {H5T_NATIVE_DOUBLE_g}, data.as_ptr() as *const c_void, dims)
+    }
+
+    /// Read the dataset `name` with its `column-major` dimensions.
+    pub fn read_f64(&self, name: &str) -> io::Result<(Vec<f64>, Vec<usize>)> {
+        let dims = self.dims(name)?;
+        let mut data = vec![0.0; dims.iter().product()];
+        self.read_raw(name, unsafe {H5T_NATIVE_DOUBLE_g}, data.as_mut_ptr() as *mut c_void)?;
+        Ok((data, dims))
+    }
+
+    pub fn write_i32(&self, name: &str, data: &[i32], dims: &[usize]) -> io::Result<()> {
+        if dims.iter().product::<usize>() != data.len() {
+            return Err(h5_error(format!("Error:: {} elements do not match the dimensions {:?} of {}", data.len(), dims, name)))
+        }
+        self.write_raw(name, unsafe {H5T_NATIVE_INT_g}, data.as_ptr() as *const c_void, dims)
+    }
+
+    pub fn read_i32(&self, name: &str) -> io::Result<(Vec<i32>, Vec<usize>)> {
+        let dims = self.dims(name)?;
+        let mut data = vec![0; dims.iter().product()];
+        self.read_raw(name, unsafe {H5T_NATIVE_INT_g}, data.as_mut_ptr() as *mut c_void)?;
+        Ok((data, dims))
+    }
+
+    /// Set the attribute `key` of the dataset or group `name` ("/" for the file), replacing an existing one.
+    pub fn set_attr(&self, name: &str, key: &str, value: &Hdf5Attr) -> io::Result<()> {
+        let (c_path, c_key) = (c_name(name)?, c_name(key)?);
+        unsafe {
+            let obj_id = check_id(H5Oopen(self.file_id, c_path.as_ptr(), H5P_DEFAULT), &format!("open {}", name))?;
+            if H5Aexists(obj_id, c_key.as_ptr()) > 0 {H5Adelete(obj_id, c_key.as_ptr());}
+            let space_id = H5Screate(H5S_SCALAR);
+            let (type_id, owned_type) = match value {
+                Hdf5Attr::Int(_) => (H5T_NATIVE_LLONG_g, false),
+                Hdf5Attr::Float(_) => (H5T_NATIVE_DOUBLE_g, false),
+                Hdf5Attr::Str(s) => {
+                    let t = H5Tcopy(H5T_C_S1_g);
+                    H5Tset_size(t, s.len() + 1);
+                    (t, true)
+                },
+            };
+            let attr_id = H5Acreate2(obj_id, c_key.as_ptr(), type_id, space_id, H5P_DEFAULT, H5P_DEFAULT);
+            let err = if attr_id < 0 {-1} else {
+                match value {
+                    Hdf5Attr::Int(v) => H5Awrite(attr_id, type_id, v as *const i64 as *const c_void),
+                    Hdf5Attr::Float(v) => H5Awrite(attr_id, type_id, v as *const f64 as *const c_void),
+                    Hdf5Attr::Str(s) => {
+                        let bytes = CString::new(s.as_str()).unwrap_or_default();
+                        H5Awrite(attr_id, type_id, bytes.as_ptr() as *const c_void)
+                    },
+                }
+            };
+            if attr_id >= 0 {H5Aclose(attr_id);}
+            if owned_type {H5Tclose(type_id);}
+            H5Sclose(space_id);
+            H5Oclose(obj_id);
+            check_err(err, &format!("write the attribute {} of {}", key, name))
+        }
+    }
+
+    /// Get the attribute `key` of the dataset or group `name`, read as the kind of `like`.
+    pub fn get_attr(&self, name: &str, key: &str, like: &Hdf5Attr) -> io::Result<Hdf5Attr> {
+        let (c_path, c_key) = (c_name(name)?, c_name(key)?);
+        unsafe {
+            let obj_id = check_id(H5Oopen(self.file_id, c_path.as_ptr(), H5P_DEFAULT), &format!("open {}", name))?;
+            let attr_id = H5Aopen(obj_id, c_key.as_ptr(), H5P_DEFAULT);
+            if attr_id < 0 {
+                H5Oclose(obj_id);
+                return Err(h5_error(format!("Error:: the attribute {} of {} is missing", key, name)))
+            }
+            let (err, value) = match like {
+                Hdf5Attr::Int(_) => {
+                    let mut v = 0i64;
+                    (H5Aread(attr_id, H5T_NATIVE_LLONG_g, &mut v as *mut i64 as *mut c_void), Hdf5Attr::Int(v))
+                },
+                Hdf5Attr::Float(_) => {
+                    let mut v = 0.0f64;
+                    (H5Aread(attr_id, H5T_NATIVE_DOUBLE_g, &mut v as *mut f64 as *mut c_void), Hdf5Attr::Float(v))
+                },
+                Hdf5Attr::Str(_) => {
+                    let type_id = H5Aget_type(attr_id);
+                    let mut buf = vec![0u8; H5Tget_size(type_id) + 1];
+                    let err = H5Aread(attr_id, type_id, buf.as_mut_ptr() as *mut c_void);
+                    H5Tclose(type_id);
+                    let end = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+                    (err, Hdf5Attr::Str(String::from_utf8_lossy(&buf[..end]).to_string()))
+                },
+            };
+            H5Aclose(attr_id);
+            H5Oclose(obj_id);
+            check_err(err, &format!("read the attribute {} of {}", key, name))?;
+            Ok(value)
+        }
+    }
+
+    fn get_int_attr(&self, name: &str, key: &str) -> io::Result<usize> {
+        match self.get_attr(name, key, &Hdf5Attr::Int(0))? {
+            Hdf5Attr::Int(v) => Ok(v as usize),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Write the symmetric or general AO matrix `mat` of `[nao, nao]`.
+    pub fn write_ao_matrix(&self, name: &str, mat: &[f64], nao: usize) -> io::Result<()> {
+        self.write_f64(name, mat, &[nao, nao])?;
+        self.set_attr(name, "nao", &Hdf5Attr::Int(nao as i64))
+    }
+
+    pub fn read_ao_matrix(&self, name: &str) -> io::Result<(Vec<f64>, usize)> {
+        let (mat, _) = self.read_f64(name)?;
+        Ok((mat, self.get_int_attr(name, "nao")?))
+    }
+
+    /// Write the packed ERIs of `nao` AOs, either 4-fold (`[npair, npair]`) or 8-fold (`npair*(npair+1)/2`).
+    pub fn write_packed_eri(&self, name: &str, eri: &[f64], nao: usize) -> io::Result<()> {
+        let npair = nao*(nao+1)/2;
+        let (dims, symmetry) = if eri.len() == npair*npair {
+            (vec![npair, npair], 4)
+        } else if eri.len() == npair*(npair+1)/2 {
+            (vec![npair*(npair+1)/2], 8)
+        } else {
+            return Err(h5_error(format!("Error:: {} elements are neither 4-fold nor 8-fold packed ERIs of {} AOs", eri.len(), nao)))
+        };
+        self.write_f64(name, eri, &dims)?;
+        self.set_attr(name, "nao", &Hdf5Attr::Int(nao as i64))?;
+        self.set_attr(name, "symmetry", &Hdf5Attr::Int(symmetry))
+    }
+
+    /// the packed ERIs with `nao` and the permutational symmetry (4 or 8)
+    pub fn read_packed_eri(&self, name: &str) -> io::Result<(Vec<f64>, usize, usize)> {
+        let (eri, _) = self.read_f64(name)?;
+        Ok((eri, self.get_int_attr(name, "nao")?, self.get_int_attr(name, "symmetry")?))
+    }
+
+    /// Write the packed 3c2e integrals `(ij|P)` (e.g. of `int3c2e_packed`) or the RI factors, `[npair, naux]`.
+    pub fn write_int3c2e(&self, name: &str, data: &[f64], nao: usize, naux: usize) -> io::Result<()> {
+        self.write_f64(name, data, &[nao*(nao+1)/2, naux])?;
+        self.set_attr(name, "nao", &Hdf5Attr::Int(nao as i64))?;
+        self.set_attr(name, "naux", &Hdf5Attr::Int(naux as i64))
+    }
+
+    /// the packed 3c2e integrals with `nao` and `naux`
+    pub fn read_int3c2e(&self, name: &str) -> io::Result<(Vec<f64>, usize, usize)> {
+        let (data, _) = self.read_f64(name)?;
+        Ok((data, self.get_int_attr(name, "nao")?, self.get_int_attr(name, "naux")?))
+    }
+
+    pub fn write_ri_factors(&self, name: &str, ri: &RIFactors) -> io::Result<()> {
+        self.write_int3c2e(name, &ri.ri3fn, ri.nao, ri.naux)
+    }
+
+    pub fn read_ri_factors(&self, name: &str) -> io::Result<RIFactors> {
+        let (ri3fn, nao, naux) = self.read_int3c2e(name)?;
+        Ok(RIFactors {nao, naux, ri3fn})
+    }
+
+    /// Write `atm`, `bas` and `env` of `cint_data` to the group `group`, with the attributes
+    /// `cint_type`, `nao`, `unit` and `version`.
+    pub fn write_basis(&self, group: &str, cint_data: &CINTR2CDATA) -> io::Result<()> {
+        let atm = cint_data.get_atm();
+        let bas = cint_data.get_bas();
+        let env = cint_data.get_env();
+        self.create_group(group)?;
+        // the rows of atm and bas are contiguous in the row-major [natm, 6] and [nbas, 8]
+        self.write_i32(&format!("{}/atm", group), &atm.concat(), &[atm.first().map_or(6, |r| r.len()), atm.len()])?;
+        self.write_i32(&format!("{}/bas", group), &bas.concat(), &[bas.first().map_or(8, |r| r.len()), bas.len()])?;
+        self.write_f64(&format!("{}/env", group), &env, &[env.len()])?;
+        let cint_type = match cint_data.get_cint_type() {CintType::Spheric => "spheric", CintType::Cartesian => "cartesian"};
+        self.set_attr(group, "cint_type", &Hdf5Attr::Str(cint_type.to_string()))?;
+        self.set_attr(group, "nao", &Hdf5Attr::Int(cint_data.ao_loc()[cint_data.get_nbas() as usize] as i64))?;
+        self.set_attr(group, "unit", &Hdf5Attr::Str("bohr".to_string()))?;
+        self.set_attr(group, "version", &Hdf5Attr::Str(env!("CARGO_PKG_VERSION").to_string()))
+    }
+
+    /// Read the basis of the group `group` into a new `CINTR2CDATA`, which should be released by `final_c2r`.
+    pub fn read_basis(&self, group: &str) -> io::Result<CINTR2CDATA> {
+        let (atm, atm_dims) = self.read_i32(&format!("{}/atm", group))?;
+        let (bas, bas_dims) = self.read_i32(&format!("{}/bas", group))?;
+        let (env, _) = self.read_f64(&format!("{}/env", group))?;
+        let cint_type = match self.get_attr(group, "cint_type", &Hdf5Attr::Str(String::new()))? {
+            Hdf5Attr::Str(s) if s == "cartesian" => CintType::Cartesian,
+            Hdf5Attr::Str(s) if s == "spheric" => CintType::Spheric,
+            other => return Err(h5_error(format!("Error:: unknown cint_type {:?} in {}", other, group))),
+        };
+        let atm: Vec<Vec<i32>> = atm.chunks(atm_dims[0].max(1)).map(|r| r.to_vec()).collect();
+        let bas: Vec<Vec<i32>> = bas.chunks(bas_dims[0].max(1)).map(|r| r.to_vec()).collect();
+        let mut cint_data = CINTR2CDATA::new();
+        cint_data.set_cint_type(&cint_type);
+        cint_data.initial_r2c(&atm, atm.len() as i32, &bas, bas.len() as i32, &env);
+        Ok(cint_data)
+    }
+}
+
+impl Drop for Hdf5File {
+    fn drop(&mut self) {
+        unsafe {H5Fclose(self.file_id);}
+    }
+}
+
+#[test]
+pub fn test_hdf5() {
+    let path = std::env::temp_dir().join(format!("rest_libcint_test_{}.h5", std::process::id()));
+    let path = path.to_str().unwrap();
+    let mut cint_data = crate::test_h2_data();
+    let mat: Vec<f64> = (0..64).map(|i| i as f64*0.5).collect();
+    let eri: Vec<f64> = (0..36*37/2).map(|i| (i as f64).sqrt()).collect();
+    {
+        let file = Hdf5File::create(path).unwrap();
+        file.write_basis("basis", &cint_data).unwrap();
+        file.write_ao_matrix("int1e/ovlp", &mat, 8).unwrap();
+        file.write_packed_eri("int2e", &eri, 8).unwrap();
+    }
+    let file = Hdf5File::open(path, false).unwrap();
+    let mut read = file.read_basis("basis").unwrap();
+    assert_eq!(read.get_bas(), cint_data.get_bas());
+    assert_eq!(read.get_env(), cint_data.get_env());
+    assert_eq!(file.get_attr("basis", "nao", &Hdf5Attr::Int(0)).unwrap(), Hdf5Attr::Int(8));
+    assert_eq!(file.read_ao_matrix("int1e/ovlp").unwrap(), (mat, 8));
+    assert_eq!(file.read_packed_eri("int2e").unwrap(), (eri, 8, 8));
+    read.final_c2r();
+    cint_data.final_c2r();
+    drop(file);
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,132 @@
+//! Direct Coulomb and exchange matrices from density matrices.
+//!
+//! For a set of density matrices `D`, each a `column-major` matrix of `[nao, nao]`,
+//!
+//! J_{ij} = \sum_{kl} (ij|kl) D_{lk},   K_{ij} = \sum_{kl} (ik|jl) D_{lk}
+//!
+//! are accumulated shell quartet by shell quartet without storing the ERIs. Only the unique quartets
+//! under the 8-fold permutational symmetry are computed, and the screening of `screening::Screening`
+//! (including the density-weighted bounds) is applied. The density matrices need not be symmetric,
+//! so that restricted (total density), unrestricted (alpha and beta densities) and response
+//! (transition densities) builds are all covered by passing the corresponding list of matrices.
+use crate::CINTR2CDATA;
+use crate::engine::IntegralEngine;
+use crate::screening::{for_each_screened_quartet, Screening, ScreeningStats};
+
+/// The Coulomb and exchange matrices, in the same order as the input density matrices.
+#[derive(Clone,Debug)]
+pub struct JKMatrices {
+    /// empty if the Coulomb matrices were not requested
+    pub vj: Vec<Vec<f64>>,
+    /// empty if the exchange matrices were not requested
+    pub vk: Vec<Vec<f64>>,
+    pub stats: ScreeningStats,
+}
+
+impl CINTR2CDATA {
+    /// Build the Coulomb (`with_j`) and/or exchange (`with_k`) matrices of all density matrices `dms`.
+    ///
+    /// The density-weighted bounds are added to `screening` for `dms`. The 2e optimizer is (re-)initialized by this driver.
+    pub fn get_jk(&mut self, dms: &[Vec<f64>], screening: &Screening, with_j: bool, with_k: bool) -> JKMatrices {
+        get_jk(self, dms, screening, with_j, with_k)
+    }
+
+    /// The Coulomb matrices of all density matrices `dms` (see `get_jk`)
+    pub fn get_j(&mut self, dms: &[Vec<f64>], screening: &Screening) -> Vec<Vec<f64>> {
+        self.get_jk(dms, screening, true, false).vj
+    }
+
+    /// The exchange matrices of all density matrices `dms` (see `get_jk`)
+    pub fn get_k(&mut self, dms: &[Vec<f64>], screening: &Screening) -> Vec<Vec<f64>> {
+        self.get_jk(dms, screening, false, true).vk
+    }
+}
+
+/// `CINTR2CDATA::get_jk` for any engine: the Coulomb and/or exchange matrices of all density matrices `dms`.
+///
+/// "int2e" is prepared by this driver.
+pub fn get_jk<E: IntegralEngine>(engine: &mut E, dms: &[Vec<f64>], screening: &Screening, with_j: bool, with_k: bool) -> JKMatrices {
+    let ao_loc = engine.ao_loc();
+    let nao = ao_loc[engine.nbas()];
+    let ndm = dms.len();
+    dms.iter().for_each(|dm| if dm.len() != nao*nao {
+        panic!("Error:: the density matrix should be of [{},{}], but {} elements are given", nao, nao, dm.len())
+    });
+    let mut vj = if with_j {vec![vec![0.0; nao*nao]; ndm]} else {vec![]};
+    let mut vk = if with_k {vec![vec![0.0; nao*nao]; ndm]} else {vec![]};
+    let screening = screening.clone().with_density(dms);
+
+    let stats = for_each_screened_quartet(engine, &screening, |i,j,k,l,buf| {
+        let (i0, di) = (ao_loc[i as usize], ao_loc[i as usize+1]-ao_loc[i as usize]);
+        let (j0, dj) = (ao_loc[j as usize], ao_loc[j as usize+1]-ao_loc[j as usize]);
+        let (k0, dk) = (ao_loc[k as usize], ao_loc[k as usize+1]-ao_loc[k as usize]);
+        let (l0, dl) = (ao_loc[l as usize], ao_loc[l as usize+1]-ao_loc[l as usize]);
+        // remove the multiple counting of the degenerate shell quartets
+        let mut scale = 1.0;
+        if i == j {scale *= 0.5};
+        if k == l {scale *= 0.5};
+        if i == k && j == l {scale *= 0.5};
+        for ll in 0..dl {
+            let l = l0+ll;
+            for kk in 0..dk {
+                let k = k0+kk;
+                for jj in 0..dj {
+                    let j = j0+jj;
+                    for ii in 0..di {
+                        let i = i0+ii;
+                        let v = scale*buf[ii + di*(jj + dj*(kk + dk*ll))];
+                        if v == 0.0 {continue};
+                        // all 8 permutations (p,q,r,s) of (ij|kl)
+                        let perms = [(i,j,k,l),(j,i,k,l),(i,j,l,k),(j,i,l,k),
+                                     (k,l,i,j),(l,k,i,j),(k,l,j,i),(l,k,j,i)];
+                        for (idm, dm) in dms.iter().enumerate() {
+                            if with_j {
+                                let vj = &mut vj[idm];
+                                perms.iter().for_each(|(p,q,r,s)| vj[p + nao*q] += v*dm[s + nao*r]);
+                            }
+                            if with_k {
+                                let vk = &mut vk[idm];
+                                perms.iter().for_each(|(p,q,r,s)| vk[p + nao*r] += v*dm[s + nao*q]);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    });
+    JKMatrices {vj, vk, stats}
+}
+
+#[test]
+pub fn test_get_jk() {
+    let mut cint_data = crate::test_h2_data();
+    let ao_loc = cint_data.ao_loc();
+    let nbas = cint_data.get_nbas() as usize;
+    let nao = ao_loc[nbas];
+    // the full ERI tensor of [nao,nao,nao,nao]
+    let eri = crate::full_eri(&mut cint_data);
+    // a symmetric and a non-symmetric density matrix
+    let dm_sym: Vec<f64> = (0..nao*nao).map(|pq| {let (p,q) = (pq%nao, pq/nao); 0.1*((p+q) as f64).cos()}).collect();
+    let dm_gen: Vec<f64> = (0..nao*nao).map(|pq| 0.05*(pq as f64).sin()).collect();
+    let dms = vec![dm_sym, dm_gen];
+
+    let screening = cint_data.screening(0.0, 0.0);
+    let jk = cint_data.get_jk(&dms, &screening, true, true);
+    for (idm, dm) in dms.iter().enumerate() {
+        for q in 0..nao {
+            for p in 0..nao {
+                let mut j_ref = 0.0;
+                let mut k_ref = 0.0;
+                for s in 0..nao {
+                    for r in 0..nao {
+                        j_ref += eri[p + nao*(q + nao*(r + nao*s))]*dm[s + nao*r];
+                        k_ref += eri[p + nao*(r + nao*(q + nao*s))]*dm[s + nao*r];
+                    }
+                }
+                assert!((jk.vj[idm][p + nao*q] - j_ref).abs() < 1.0e-10);
+                assert!((jk.vk[idm][p + nao*q] - k_ref).abs() < 1.0e-10);
+            }
+        }
+    }
+    cint_data.final_c2r();
+}
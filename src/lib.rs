@@ -41,7 +41,7 @@
 //! // Transfer `atm`, `bas`, and `env` to the raw pointers,
 //! // and organize them by the data structure of `CINTR2CDATA`.
 //! //=============================================================================
-//! use rust_libcint::{CINTR2CDATA,CintType};
+//! use rest_libcint::{CINTR2CDATA,CintType};
 //! let mut cint_data = CINTR2CDATA::new();
 //! cint_data.initial_r2c(&atm,natm,&bas,nbas,&env);
 //! //=============================================================================
@@ -52,7 +52,7 @@
 //! //  `CintType::Cartesian` on the following line:
 //! cint_data.set_cint_type(&CintType::Spheric);
 //! cint_data.cint2e_optimizer_rust();
-//! let buf = cint_data.cint_ijkl(0,1,1,0);
+//! let buf = cint_data.cint_ijkl_by_shell(0,1,1,0);
 //! let mut v1:f64=0.0;
 //! &buf.into_iter().for_each(|i| {v1 += i.abs()});
 //! println!("The reference data for cint2e ERIs: 0.5745411555937561; v1: {:18.16}; ",v1);
@@ -63,7 +63,7 @@
 //! // The GTO functions considered here are spheric
 //! cint_data.set_cint_type(&CintType::Spheric);
 //! cint_data.cint1e_ovlp_optimizer_rust();
-//! let buf = cint_data.cint_ijovlp(0,1);
+//! let buf = cint_data.cint_ij(0,1,"ovlp");
 //! let mut v1:f64=0.0;
 //! &buf.into_iter().for_each(|i| {v1 += i.abs()});
 //! println!("The reference data for cint1e_ovlp: 0.7096366827378776; v1: {:18.16}; ",v1);
@@ -74,7 +74,7 @@
 //! // The GTO functions considered here are Cartesian
 //! cint_data.set_cint_type(&CintType::Cartesian);
 //! cint_data.cint1e_kin_optimizer_rust();
-//! let buf = cint_data.cint_ijkin(0,1);
+//! let buf = cint_data.cint_ij(0,1,"kinetic");
 //! let mut v1:f64=0.0;
 //! &buf.into_iter().for_each(|i| {v1 += i.abs()});
 //! println!("The reference data for cint1e_kin : 1.5780816190296618; v1: {:18.16}; ",v1);
@@ -85,7 +85,7 @@
 //! // The GTO functions considered here are Cartesian
 //! cint_data.set_cint_type(&CintType::Cartesian);
 //! cint_data.cint1e_nuc_optimizer_rust();
-//! let buf = cint_data.cint_ijnuc(0,1);
+//! let buf = cint_data.cint_ij(0,1,"nuclear");
 //! let mut v1:f64=0.0;
 //! &buf.into_iter().for_each(|i| {v1 += i.abs()});
 //! println!("The reference data for cint1e_nuc : 4.0007622494430706; v1: {:18.16}; ",v1);
@@ -99,11 +99,40 @@
 #![allow(unused)]
 use std::os::raw::c_int;
 use std::mem::ManuallyDrop;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 mod cint;
-use crate::cint::{CINTOpt,CINTdel_optimizer};
-
-#[derive(Clone,Copy)]
+mod linalg;
+pub mod density_fitting;
+pub mod mixed_basis;
+pub mod cholesky_eri;
+pub mod screening;
+pub mod jk;
+pub mod ri_jk;
+pub mod gradient;
+pub mod basis_info;
+pub mod cart2sph;
+pub mod ao_convention;
+pub mod molden;
+pub mod fchk;
+pub mod fcidump;
+pub mod ao2mo;
+pub mod npy;
+pub mod engine;
+pub mod reference;
+pub mod rys;
+#[cfg(feature = "ndarray")]
+pub mod ndarray_ext;
+#[cfg(feature = "nalgebra")]
+pub mod nalgebra_ext;
+#[cfg(feature = "faer")]
+pub mod faer_ext;
+#[cfg(feature = "hdf5")]
+pub mod hdf5_io;
+use crate::cint::{CINTOpt,CINTdel_optimizer,CintIntegral};
+
+#[derive(Clone,Copy,Debug,PartialEq)]
 pub enum CintType {
    Spheric,
    Cartesian,
@@ -128,7 +157,6 @@ pub enum IP3C2E {
     IP2,
 }
 
-#[derive(Clone)]
 pub struct CINTR2CDATA {
     c_atm: (*const i32, usize, usize),
     c_bas: (*const i32, usize, usize),
@@ -138,6 +166,50 @@ pub struct CINTR2CDATA {
     c_opt: (*mut CINTOpt, usize, usize),
     c_ao_loc: (*const i32, usize,usize),
     cint_type: CintType,
+    /// a unique id of the data of each `initial_r2c` (0 before it), keying the `CintWorkspace`s
+    basis_id: u64,
+}
+
+/// the source of `CINTR2CDATA::basis_id`
+static NEXT_BASIS_ID: AtomicU64 = AtomicU64::new(1);
+
+/// The reusable scratch memory of libcint for the `_into` evaluators, e.g. `cint_ijkl_into`.
+///
+/// It is sized on first use (or by `CINTR2CDATA::intor_workspace`) for the largest shells of the
+/// basis and then reused without allocation, as long as the integral and the basis are unchanged;
+/// it is resized when used with another basis or after `initial_r2c`, even of the same number of shells.
+#[derive(Clone,Debug,Default)]
+pub struct CintWorkspace {
+    cache: Vec<f64>,
+    intor: Option<&'static str>,
+    basis_id: u64,
+    cint_type: Option<CintType>,
+}
+
+impl CintWorkspace {
+    pub fn new() -> CintWorkspace {
+        CintWorkspace::default()
+    }
+    /// the size of the scratch memory in `f64`
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+}
+
+/// A deep copy with buffers of its own, so that `initial_r2c` and `final_c2r` on either copy leave
+/// the other intact; the optimizer is not copied.
+impl Clone for CINTR2CDATA {
+    fn clone(&self) -> CINTR2CDATA {
+        let mut cint_data = CINTR2CDATA::new();
+        cint_data.set_cint_type(&self.cint_type);
+        if !self.c_atm.0.is_null() {
+            cint_data.initial_r2c(&self.get_atm(), self.c_natm, &self.get_bas(), self.c_nbas, &self.get_env());
+        }
+        cint_data
+    }
 }
 
 impl CINTR2CDATA {
@@ -152,6 +224,7 @@ impl CINTR2CDATA {
             c_nbas: 0 as c_int,
             c_natm: 0 as c_int,
             cint_type: CintType::Spheric,
+            basis_id: 0,
             }
     }
     pub fn set_cint_type(&mut self, ctype: &CintType) {
@@ -162,11 +235,12 @@ impl CINTR2CDATA {
                     atm: &Vec<Vec<i32>>, natm:i32, 
                     bas: &Vec<Vec<i32>>, nbas:i32, 
                     env: &Vec<f64>) {
+        // release the data of a previous initialization, if any
         unsafe {
-            let r_atm = Vec::from_raw_parts(self.c_atm.0 as *mut i32,self.c_atm.1,self.c_atm.2);
-            let r_bas = Vec::from_raw_parts(self.c_bas.0 as *mut i32,self.c_bas.1,self.c_bas.2);
-            let r_env = Vec::from_raw_parts(self.c_env.0 as *mut f64,self.c_env.1,self.c_env.2);
-            let r_ao_loc = Vec::from_raw_parts(self.c_ao_loc.0 as *mut i32,self.c_ao_loc.1,self.c_ao_loc.2);
+            if !self.c_atm.0.is_null() {let r_atm = Vec::from_raw_parts(self.c_atm.0 as *mut i32,self.c_atm.1,self.c_atm.2);}
+            if !self.c_bas.0.is_null() {let r_bas = Vec::from_raw_parts(self.c_bas.0 as *mut i32,self.c_bas.1,self.c_bas.2);}
+            if !self.c_env.0.is_null() {let r_env = Vec::from_raw_parts(self.c_env.0 as *mut f64,self.c_env.1,self.c_env.2);}
+            if !self.c_ao_loc.0.is_null() {let r_ao_loc = Vec::from_raw_parts(self.c_ao_loc.0 as *mut i32,self.c_ao_loc.1,self.c_ao_loc.2);}
         }
 
         let dim = bas.iter().map(|ibas| {match self.cint_type {
@@ -201,14 +275,23 @@ impl CINTR2CDATA {
         self.c_nbas = nbas as c_int;
 
         self.c_opt = (unsafe {std::ptr::null::<CINTOpt>() as *mut CINTOpt}, 0,0);
+        self.basis_id = NEXT_BASIS_ID.fetch_add(1, Ordering::Relaxed);
     }
     pub fn final_c2r(&mut self) {
         ///```println!("Clean the unsafe data and transfer the ownership of the raw pointers in CINTR2CDATA to Rust");```
         unsafe {
-            let r_atm = Vec::from_raw_parts(self.c_atm.0 as *mut i32,self.c_atm.1,self.c_atm.2);
-            let r_bas = Vec::from_raw_parts(self.c_bas.0 as *mut i32,self.c_bas.1,self.c_bas.2);
-            let r_env = Vec::from_raw_parts(self.c_env.0 as *mut f64,self.c_env.1,self.c_env.2);
+            if !self.c_atm.0.is_null() {let r_atm = Vec::from_raw_parts(self.c_atm.0 as *mut i32,self.c_atm.1,self.c_atm.2);}
+            if !self.c_bas.0.is_null() {let r_bas = Vec::from_raw_parts(self.c_bas.0 as *mut i32,self.c_bas.1,self.c_bas.2);}
+            if !self.c_env.0.is_null() {let r_env = Vec::from_raw_parts(self.c_env.0 as *mut f64,self.c_env.1,self.c_env.2);}
+            if !self.c_ao_loc.0.is_null() {let r_ao_loc = Vec::from_raw_parts(self.c_ao_loc.0 as *mut i32,self.c_ao_loc.1,self.c_ao_loc.2);}
         }
+        self.c_atm = (std::ptr::null::<i32>(), 0,0);
+        self.c_bas = (std::ptr::null::<i32>(), 0,0);
+        self.c_env = (std::ptr::null::<f64>(), 0,0);
+        self.c_ao_loc = (std::ptr::null::<i32>(), 0,0);
+        self.c_natm = 0;
+        self.c_nbas = 0;
+        self.basis_id = 0;
         self.cint_del_optimizer_rust();
         //r_env
     }
@@ -217,129 +300,55 @@ impl CINTR2CDATA {
             CINTdel_optimizer(&mut self.c_opt.0);
         }
     }
-    pub fn cint2c2e_optimizer_rust(&mut self){
+    /// (re-)initialize the optimizer of the integral `intor` of the libcint catalog, e.g. "int2e_ip1"
+    pub fn intor_optimizer_rust(&mut self, intor: &str) {
+        let intor = CINTR2CDATA::find_intor(intor);
         self.cint_del_optimizer_rust();
-        //self.cint_init_2e_optimizer_rust();
         unsafe {
-            cint::cint2c2e_optimizer(&mut self.c_opt.0, 
-                                       self.c_atm.0, self.c_natm, 
-                                       self.c_bas.0, self.c_nbas, 
-                                       self.c_env.0);
+            (intor.optimizer)(&mut self.c_opt.0,
+                              self.c_atm.0, self.c_natm,
+                              self.c_bas.0, self.c_nbas,
+                              self.c_env.0);
         }
     }
+    pub fn cint2c2e_optimizer_rust(&mut self){
+        self.intor_optimizer_rust("int2c2e");
+    }
     pub fn cint2c2e_ip1_optimizer_rust(&mut self){
-        self.cint_del_optimizer_rust();
-        //self.cint_init_2e_optimizer_rust();
-        unsafe {
-            cint::cint2c2e_ip1_optimizer(&mut self.c_opt.0, 
-                                       self.c_atm.0, self.c_natm, 
-                                       self.c_bas.0, self.c_nbas, 
-                                       self.c_env.0);
-        }
+        self.intor_optimizer_rust("int2c2e_ip1");
     }
     pub fn cint3c2e_optimizer_rust(&mut self){
-        self.cint_del_optimizer_rust();
-        //self.cint_init_2e_optimizer_rust();
-        unsafe {
-            cint::cint3c2e_optimizer(&mut self.c_opt.0, 
-                                       self.c_atm.0, self.c_natm, 
-                                       self.c_bas.0, self.c_nbas, 
-                                       self.c_env.0);
-        }
+        self.intor_optimizer_rust("int3c2e");
     }
     pub fn cint3c2e_ip1_optimizer_rust(&mut self){
-        self.cint_del_optimizer_rust();
-        unsafe {
-            cint::int3c2e_ip1_optimizer(&mut self.c_opt.0, 
-                                       self.c_atm.0, self.c_natm, 
-                                       self.c_bas.0, self.c_nbas, 
-                                       self.c_env.0);
-        }
+        self.intor_optimizer_rust("int3c2e_ip1");
     }
     pub fn cint3c2e_ip2_optimizer_rust(&mut self){
-        self.cint_del_optimizer_rust();
-        unsafe {
-            cint::int3c2e_ip2_optimizer(&mut self.c_opt.0, 
-                                       self.c_atm.0, self.c_natm, 
-                                       self.c_bas.0, self.c_nbas, 
-                                       self.c_env.0);
-        }
+        self.intor_optimizer_rust("int3c2e_ip2");
     }
     pub fn cint2e_optimizer_rust(&mut self){
-        self.cint_del_optimizer_rust();
-        //self.cint_init_2e_optimizer_rust();
-        unsafe {
-            cint::cint2e_optimizer(&mut self.c_opt.0, 
-                                       self.c_atm.0, self.c_natm, 
-                                       self.c_bas.0, self.c_nbas, 
-                                       self.c_env.0);
-        }
+        self.intor_optimizer_rust("int2e");
     }
     pub fn cint1e_ovlp_optimizer_rust(&mut self){
-        self.cint_del_optimizer_rust();
-        //self.cint_init_optimizer_rust();
-        unsafe {
-            cint::cint1e_ovlp_optimizer(&mut self.c_opt.0, 
-                                       self.c_atm.0, self.c_natm, 
-                                       self.c_bas.0, self.c_nbas, 
-                                       self.c_env.0);
-        }
+        self.intor_optimizer_rust("int1e_ovlp");
     }
     pub fn cint1e_nuc_optimizer_rust(&mut self){
-        self.cint_del_optimizer_rust();
-        //self.cint_init_optimizer_rust();
-        unsafe {
-            cint::cint1e_nuc_optimizer(&mut self.c_opt.0, 
-                                       self.c_atm.0, self.c_natm, 
-                                       self.c_bas.0, self.c_nbas, 
-                                       self.c_env.0);
-        }
+        self.intor_optimizer_rust("int1e_nuc");
     }
     pub fn cint1e_kin_optimizer_rust(&mut self){
-        self.cint_del_optimizer_rust();
-        //self.cint_init_optimizer_rust();
-        unsafe {
-            cint::int1e_kin_optimizer(&mut self.c_opt.0, 
-                                       self.c_atm.0, self.c_natm, 
-                                       self.c_bas.0, self.c_nbas, 
-                                       self.c_env.0);
-        }
+        self.intor_optimizer_rust("int1e_kin");
     }
     pub fn int1e_ipovlp_optimizer_rust(&mut self){
-        self.cint_del_optimizer_rust();
-        unsafe {
-            cint::int1e_ipovlp_optimizer(&mut self.c_opt.0, 
-                                        self.c_atm.0, self.c_natm, 
-                                        self.c_bas.0, self.c_nbas, 
-                                        self.c_env.0)
-        }
+        self.intor_optimizer_rust("int1e_ipovlp");
     }
     pub fn int1e_ipkin_optimizer_rust(&mut self){
-        self.cint_del_optimizer_rust();
-        unsafe {
-            cint::int1e_ipkin_optimizer(&mut self.c_opt.0, 
-                                        self.c_atm.0, self.c_natm, 
-                                        self.c_bas.0, self.c_nbas, 
-                                        self.c_env.0)
-        }
+        self.intor_optimizer_rust("int1e_ipkin");
     }
     pub fn int1e_ipnuc_optimizer_rust(&mut self){
-        self.cint_del_optimizer_rust();
-        unsafe {
-            cint::int1e_ipnuc_optimizer(&mut self.c_opt.0, 
-                                        self.c_atm.0, self.c_natm, 
-                                        self.c_bas.0, self.c_nbas, 
-                                        self.c_env.0)
-        }
+        self.intor_optimizer_rust("int1e_ipnuc");
     }
     pub fn int1e_iprinv_optimizer_rust(&mut self){
-        self.cint_del_optimizer_rust();
-        unsafe {
-            cint::int1e_iprinv_optimizer(&mut self.c_opt.0, 
-                                        self.c_atm.0, self.c_natm, 
-                                        self.c_bas.0, self.c_nbas, 
-                                        self.c_env.0)
-        }
+        self.intor_optimizer_rust("int1e_iprinv");
     }
     pub fn cint_cgto_rust(&self, index: i32) -> i32 {
         let mut dim: i32;
@@ -351,129 +360,213 @@ impl CINTR2CDATA {
         }
         dim
     }
-    pub fn cint_2c2e(&mut self, i:i32,j:i32) -> Vec<f64> {
-        let mut di = self.cint_cgto_rust(i);
-        let mut dj = self.cint_cgto_rust(j);
-        let mut shls: Vec<c_int> = vec![i as c_int,j as c_int];
-        let mut shls = ManuallyDrop::new(shls);
-        let (c_shls,shls_len,shls_cap) = (shls.as_mut_ptr() as *mut c_int,shls.len(),shls.capacity());
-        let mut buf: Vec<f64> = vec![0.0;(di*dj) as usize];
-        let mut buf = ManuallyDrop::new(buf);
-        let (c_buf, buf_len, buf_cap) = (buf.as_mut_ptr() as *mut f64, buf.len(), buf.capacity());
-        let mut new_buf:Vec<f64>;
-        unsafe {
-            match self.cint_type {
-                CintType::Spheric => cint::cint2c2e_sph(c_buf, c_shls,
-                                                    self.c_atm.0, self.c_natm,
-                                                    self.c_bas.0,self.c_nbas,
-                                                    self.c_env.0,
-                                                    self.c_opt.0),
-                CintType::Cartesian => cint::cint2c2e_cart(c_buf, c_shls,
-                                                    self.c_atm.0, self.c_natm,
-                                                    self.c_bas.0,self.c_nbas,
-                                                    self.c_env.0,
-                                                    self.c_opt.0),
+    /// the offsets of the basis functions of all shells for the current `CintType`,
+    /// with `ao_loc[nbas]` being the total number of basis functions
+    pub fn ao_loc(&self) -> Vec<usize> {
+        let mut ao_loc = vec![0usize; self.c_nbas as usize + 1];
+        self.get_bas().iter().enumerate().for_each(|(i,ibas)| {
+            let (l, nctr) = (ibas[1] as usize, ibas[3] as usize);
+            let ncomp = match self.cint_type {
+                CintType::Spheric => 2*l+1,
+                CintType::Cartesian => (l+1)*(l+2)/2,
             };
-            //println!("debug 1 {}", &c_buf.read());
-            //let shls = Vec::from_raw_parts(c_shls, shls_len, shls_cap);
-            new_buf = Vec::from_raw_parts(c_buf, buf_len, buf_cap);
-        }
-       new_buf
+            ao_loc[i+1] = ao_loc[i] + ncomp*nctr;
+        });
+        ao_loc
     }
-    pub fn cint_ip_2c2e(&mut self, i:i32,j:i32) -> Vec<f64> {
-        let mut di = self.cint_cgto_rust(i);
-        let mut dj = self.cint_cgto_rust(j);
-        let mut shls: Vec<c_int> = vec![i as c_int,j as c_int];
-        let mut shls = ManuallyDrop::new(shls);
-        let (c_shls,shls_len,shls_cap) = (shls.as_mut_ptr() as *mut c_int,shls.len(),shls.capacity());
-        let mut buf: Vec<f64> = vec![0.0;(di*dj*3) as usize];
-        let mut buf = ManuallyDrop::new(buf);
-        let (c_buf, buf_len, buf_cap) = (buf.as_mut_ptr() as *mut f64, buf.len(), buf.capacity());
-        let mut new_buf:Vec<f64>;
+    pub fn get_natm(&self) -> i32 {
+        self.c_natm
+    }
+    pub fn get_nbas(&self) -> i32 {
+        self.c_nbas
+    }
+    pub fn get_cint_type(&self) -> CintType {
+        self.cint_type
+    }
+    /// a copy of `atm` in the layout accepted by `initial_r2c`
+    pub fn get_atm(&self) -> Vec<Vec<i32>> {
+        if self.c_atm.1 == 0 {return vec![]};
+        let atm = unsafe {std::slice::from_raw_parts(self.c_atm.0, self.c_atm.1)};
+        atm.chunks(6).map(|x| x.to_vec()).collect()
+    }
+    /// a copy of `bas` in the layout accepted by `initial_r2c`
+    pub fn get_bas(&self) -> Vec<Vec<i32>> {
+        if self.c_bas.1 == 0 {return vec![]};
+        let bas = unsafe {std::slice::from_raw_parts(self.c_bas.0, self.c_bas.1)};
+        bas.chunks(8).map(|x| x.to_vec()).collect()
+    }
+    /// a copy of `env`
+    pub fn get_env(&self) -> Vec<f64> {
+        if self.c_env.1 == 0 {return vec![]};
+        unsafe {std::slice::from_raw_parts(self.c_env.0, self.c_env.1)}.to_vec()
+    }
+    /// set the origin of the `1/|r-R|` operator (`PTR_RINV_ORIG` of `env`) used by the `rinv` integrals
+    pub fn set_rinv_origin(&mut self, origin: &[f64;3]) {
+        if self.c_env.1 < 7 {panic!("Error:: env is too short to hold the rinv origin")};
         unsafe {
-            match self.cint_type {
-                CintType::Spheric => cint::cint2c2e_ip1_sph(c_buf, c_shls,
-                                                    self.c_atm.0, self.c_natm,
-                                                    self.c_bas.0,self.c_nbas,
-                                                    self.c_env.0,
-                                                    self.c_opt.0),
-                CintType::Cartesian => cint::cint2c2e_ip1_cart(c_buf, c_shls,
-                                                    self.c_atm.0, self.c_natm,
-                                                    self.c_bas.0,self.c_nbas,
-                                                    self.c_env.0,
-                                                    self.c_opt.0),
-            };
-            //println!("debug 1 {}", &c_buf.read());
-            //let shls = Vec::from_raw_parts(c_shls, shls_len, shls_cap);
-            new_buf = Vec::from_raw_parts(c_buf, buf_len, buf_cap);
+            let env = std::slice::from_raw_parts_mut(self.c_env.0 as *mut f64, self.c_env.1);
+            env[4..7].copy_from_slice(origin);
         }
-       new_buf
     }
-    pub fn cint_3c2e(&mut self, i:i32,j:i32,k:i32) -> Vec<f64> {
-        let mut di = self.cint_cgto_rust(i);
-        let mut dj = self.cint_cgto_rust(j);
-        let mut dk = self.cint_cgto_rust(k);
-        let mut shls: Vec<c_int> = vec![i as c_int,j as c_int,k as c_int];
-        let mut shls = ManuallyDrop::new(shls);
-        let (c_shls,shls_len,shls_cap) = (shls.as_mut_ptr() as *mut c_int,shls.len(),shls.capacity());
-        let mut buf: Vec<f64> = vec![0.0;(di*dj*dk) as usize];
-        let mut buf = ManuallyDrop::new(buf);
-        let (c_buf, buf_len, buf_cap) = (buf.as_mut_ptr() as *mut f64, buf.len(), buf.capacity());
-        let mut new_buf:Vec<f64>;
+    fn find_intor(intor: &str) -> &'static CintIntegral {
+        cint::find_integral(intor).unwrap_or_else(|| panic!("Error:: Unknown libcint integral {}", intor))
+    }
+    /// the number of components of the integral `intor` of the libcint catalog, e.g. 3 for "int1e_ipovlp"
+    pub fn intor_ncomp(intor: &str) -> usize {
+        CINTR2CDATA::find_intor(intor).ncomp
+    }
+    /// the number of shells of a block of the integral `intor`, e.g. 3 for "int3c2e"
+    pub fn intor_ncenter(intor: &str) -> usize {
+        CINTR2CDATA::find_intor(intor).ncenter
+    }
+    /// Evaluate `intor` for the shells `shls` into `out`, laid out as `[dims[0], dims[1], ..., ncomp]`
+    /// (the shell sizes for `dims = None`), with `cache` as the scratch memory of libcint if given.
+    /// Returns `false` if the block is screened out by libcint (and zeroed).
+    fn cint_eval(&self, intor: &CintIntegral, out: &mut [f64], dims: Option<&[c_int]>, shls: &[c_int], cache: Option<&mut [f64]>) -> bool {
+        // the other stand-ins would panic inside `extern "C"`, which aborts instead of unwinding
+        #[cfg(not(feature = "libcint"))]
+        if !reference::ReferenceEngine::supports(intor.name) {
+            panic!("Error:: {} is not provided by the reference backend; enable the `libcint` feature", intor.name)
+        }
+        if shls.len() != intor.ncenter {
+            panic!("Error:: {} takes {} shells, but {} are given", intor.name, intor.ncenter, shls.len())
+        }
+        if shls.iter().any(|i| *i < 0 || *i >= self.c_nbas) {
+            panic!("Error:: the shells {:?} are out of the range of {} shells", shls, self.c_nbas)
+        }
+        // at most 4 centers in the catalog; no allocation on the path of the `_into` evaluators
+        let mut sizes = [0 as c_int; 4];
+        sizes.iter_mut().zip(shls).for_each(|(d, i)| *d = self.cint_cgto_rust(*i));
+        let sizes = &sizes[..shls.len()];
+        let dims_of_out = dims.unwrap_or(sizes);
+        if dims_of_out.len() != shls.len() || dims_of_out.iter().zip(sizes).any(|(d, s)| d < s) {
+            panic!("Error:: the dimensions {:?} of the output cannot hold the block {:?} of {}", dims_of_out, sizes, intor.name)
+        }
+        if sizes.contains(&0) {return false}
+        // the last element written: the last component of the last function of all shells
+        let block_size: usize = dims_of_out.iter().map(|d| *d as usize).product();
+        let mut stride = 1;
+        let mut last = (intor.ncomp-1)*block_size;
+        dims_of_out.iter().zip(sizes).for_each(|(d, s)| {
+            last += (*s as usize-1)*stride;
+            stride *= *d as usize;
+        });
+        if out.len() <= last {
+            panic!("Error:: the output of {} elements is too short for the block of {}", out.len(), intor.name)
+        }
+        let c_dims = dims.map_or(std::ptr::null(), |d| d.as_ptr());
+        let c_cache = cache.map_or(std::ptr::null_mut(), |c| c.as_mut_ptr());
+        let func = match self.cint_type {
+            CintType::Spheric => intor.sph,
+            CintType::Cartesian => intor.cart,
+        };
         unsafe {
-            match self.cint_type {
-                CintType::Spheric => cint::cint3c2e_sph(c_buf, c_shls,
-                                                    self.c_atm.0, self.c_natm,
-                                                    self.c_bas.0,self.c_nbas,
-                                                    self.c_env.0,
-                                                    self.c_opt.0),
-                CintType::Cartesian => cint::cint3c2e_cart(c_buf, c_shls,
-                                                    self.c_atm.0, self.c_natm,
-                                                    self.c_bas.0,self.c_nbas,
-                                                    self.c_env.0,
-                                                    self.c_opt.0),
-            };
-            //println!("debug 1 {}", &c_buf.read());
-            //let shls = Vec::from_raw_parts(c_shls, shls_len, shls_cap);
-            new_buf = Vec::from_raw_parts(c_buf, buf_len, buf_cap);
+            func(out.as_mut_ptr(), c_dims, shls.as_ptr(),
+                 self.c_atm.0, self.c_natm,
+                 self.c_bas.0, self.c_nbas,
+                 self.c_env.0, self.c_opt.0, c_cache) != 0
         }
-       new_buf
+    }
+    /// The integrals `intor` of the libcint catalog (e.g. "int2e_ip1") for the shells `shls`,
+    /// as a `column-major` tensor of `[di, dj, ..., ncomp]`.
+    ///
+    /// The optimizer, if any, should be initialized for `intor` by `intor_optimizer_rust`.
+    pub fn cint_intor(&self, intor: &str, shls: &[i32]) -> Vec<f64> {
+        let intor = CINTR2CDATA::find_intor(intor);
+        let size: usize = shls.iter().map(|i| self.cint_cgto_rust(*i) as usize).product();
+        let mut buf = vec![0.0; size*intor.ncomp];
+        self.cint_eval(intor, &mut buf, None, shls, None);
+        buf
+    }
+    pub fn cint_2c2e(&mut self, i:i32,j:i32) -> Vec<f64> {
+        self.cint_intor("int2c2e", &[i,j])
+    }
+    pub fn cint_ip_2c2e(&mut self, i:i32,j:i32) -> Vec<f64> {
+        self.cint_intor("int2c2e_ip1", &[i,j])
+    }
+    pub fn cint_3c2e(&mut self, i:i32,j:i32,k:i32) -> Vec<f64> {
+        self.cint_intor("int3c2e", &[i,j,k])
     }
     pub fn cint_ijkl_by_shell(&mut self, i:i32,j:i32,k:i32,l:i32) -> Vec<f64> {
-        let mut di = self.cint_cgto_rust(i);
-        let mut dj = self.cint_cgto_rust(j);
-        let mut dk = self.cint_cgto_rust(k);
-        let mut dl = self.cint_cgto_rust(l);
-    
-        let mut shls: Vec<c_int> = vec![i as c_int,j as c_int,k as c_int,l as c_int];
-        //shls.shrink_to_fit();
-        let mut shls = ManuallyDrop::new(shls);
-        let (c_shls,shls_len,shls_cap) = (shls.as_mut_ptr() as *mut c_int,shls.len(),shls.capacity());
-    
-        let mut buf: Vec<f64> = vec![0.0;(di*dj*dk*dl) as usize];
-        //buf.shrink_to_fit();
-        let mut buf = ManuallyDrop::new(buf);
-        let (c_buf, buf_len, buf_cap) = (buf.as_mut_ptr() as *mut f64, buf.len(), buf.capacity());
-
-        let mut new_buf:Vec<f64>;
-        unsafe {
-            match self.cint_type {
-                CintType::Spheric => cint::cint2e_sph(c_buf, c_shls,
-                                                    self.c_atm.0, self.c_natm,
-                                                    self.c_bas.0,self.c_nbas,
-                                                    self.c_env.0,
-                                                    self.c_opt.0),
-                CintType::Cartesian => cint::cint2e_cart(c_buf, c_shls,
-                                                    self.c_atm.0, self.c_natm,
-                                                    self.c_bas.0,self.c_nbas,
-                                                    self.c_env.0,
-                                                    self.c_opt.0),
-            };
-            //println!("debug 1 {}", &c_buf.read());
-            //let shls = Vec::from_raw_parts(c_shls, shls_len, shls_cap);
-            new_buf = Vec::from_raw_parts(c_buf, buf_len, buf_cap);
+        self.cint_intor("int2e", &[i,j,k,l])
+    }
+
+    /// the number of elements of the block of `intor` for `shls`, including the components
+    pub fn intor_block_size(&self, intor: &str, shls: &[i32]) -> usize {
+        let intor = CINTR2CDATA::find_intor(intor);
+        shls.iter().map(|i| self.cint_cgto_rust(*i) as usize).product::<usize>()*intor.ncomp
+    }
+    /// the largest block of `intor` over all shells, i.e. the buffer length sufficient for any `_into` call of `intor`
+    pub fn max_block_size(&self, intor: &str) -> usize {
+        let intor = CINTR2CDATA::find_intor(intor);
+        let max_dim = (0..self.c_nbas).map(|i| self.cint_cgto_rust(i) as usize).max().unwrap_or(0);
+        max_dim.pow(intor.ncenter as u32)*intor.ncomp
+    }
+    /// the largest scratch memory (in `f64`) that libcint needs for `intor` over all shells
+    pub fn max_cache_size(&self, intor: &str) -> usize {
+        let intor = CINTR2CDATA::find_intor(intor);
+        let func = match self.cint_type {
+            CintType::Spheric => intor.sph,
+            CintType::Cartesian => intor.cart,
+        };
+        // the cache grows with the angular momenta and the primitives, both bounded by the diagonal quartets
+        (0..self.c_nbas).map(|i| {
+            let shls = [i as c_int; 4];
+            unsafe {
+                func(std::ptr::null_mut(), std::ptr::null(), shls.as_ptr(),
+                     self.c_atm.0, self.c_natm,
+                     self.c_bas.0, self.c_nbas,
+                     self.c_env.0, std::ptr::null(), std::ptr::null_mut()).max(0) as usize
+            }
+        }).max().unwrap_or(0)
+    }
+    /// a workspace sized for all `_into` calls of `intor` on this basis
+    pub fn intor_workspace(&self, intor: &str) -> CintWorkspace {
+        let mut workspace = CintWorkspace::new();
+        self.prepare_workspace(CINTR2CDATA::find_intor(intor), &mut workspace);
+        workspace
+    }
+    fn prepare_workspace(&self, intor: &'static CintIntegral, workspace: &mut CintWorkspace) {
+        if workspace.intor != Some(intor.name) || workspace.basis_id != self.basis_id || workspace.cint_type != Some(self.cint_type) {
+            let size = self.max_cache_size(intor.name);
+            if workspace.cache.len() < size {workspace.cache.resize(size, 0.0)};
+            workspace.intor = Some(intor.name);
+            workspace.basis_id = self.basis_id;
+            workspace.cint_type = Some(self.cint_type);
+        }
+    }
+    /// `cint_intor` writing into `out`, whose length should be at least `intor_block_size`,
+    /// with `workspace` as the reusable scratch memory. Returns the length of the block.
+    pub fn cint_intor_into(&self, intor: &str, out: &mut [f64], shls: &[i32], workspace: &mut CintWorkspace) -> usize {
+        let intor = CINTR2CDATA::find_intor(intor);
+        let size = shls.iter().map(|i| self.cint_cgto_rust(*i) as usize).product::<usize>()*intor.ncomp;
+        if out.len() < size {
+            panic!("Error:: the output of {} elements is shorter than the block of {} elements of {} for the shells {:?}",
+                   out.len(), size, intor.name, shls)
         }
-       new_buf
+        self.prepare_workspace(intor, workspace);
+        self.cint_eval(intor, &mut out[..size], None, shls, Some(&mut workspace.cache));
+        size
+    }
+    /// `cint_intor_into` writing into `out` laid out as `[dims[0], dims[1], ..., ncomp]`, e.g. from the
+    /// offset of the block in a larger `column-major` matrix, with `dims[n]` at least the size of `shls[n]`.
+    /// Returns `false` if the block is screened out by libcint (and zeroed).
+    pub fn cint_intor_into_dims(&self, intor: &str, out: &mut [f64], dims: &[i32], shls: &[i32], workspace: &mut CintWorkspace) -> bool {
+        let intor = CINTR2CDATA::find_intor(intor);
+        self.prepare_workspace(intor, workspace);
+        self.cint_eval(intor, out, Some(dims), shls, Some(&mut workspace.cache))
+    }
+    pub fn cint_2c2e_into(&self, out: &mut [f64], i:i32,j:i32, workspace: &mut CintWorkspace) -> usize {
+        self.cint_intor_into("int2c2e", out, &[i,j], workspace)
+    }
+    pub fn cint_ip_2c2e_into(&self, out: &mut [f64], i:i32,j:i32, workspace: &mut CintWorkspace) -> usize {
+        self.cint_intor_into("int2c2e_ip1", out, &[i,j], workspace)
+    }
+    pub fn cint_3c2e_into(&self, out: &mut [f64], i:i32,j:i32,k:i32, workspace: &mut CintWorkspace) -> usize {
+        self.cint_intor_into("int3c2e", out, &[i,j,k], workspace)
+    }
+    pub fn cint_ijkl_into(&self, out: &mut [f64], i:i32,j:i32,k:i32,l:i32, workspace: &mut CintWorkspace) -> usize {
+        self.cint_intor_into("int2e", out, &[i,j,k,l], workspace)
     }
 
     pub fn gto_norm(n:i32,a:f64) -> f64 {
@@ -483,298 +576,163 @@ impl CINTR2CDATA {
         }
         r
     }
-    pub fn cint_ij(&mut self, i:i32,j:i32,op_name: &String) -> Vec<f64> {
-        // for 1e integrals: ovlp, kinetic, and nuclear
-        let op_type = if op_name.to_lowercase() ==String::from("ovlp") {
-            IJOPT::Ovlp
-        } else if op_name.to_lowercase() ==String::from("kinetic") {
-            IJOPT::Kinetic
-        } else if op_name.to_lowercase() ==String::from("nuclear") {
-            IJOPT::Nuclear
-        } else {
-            panic!("Error:: Unknown operator for GTO-ij integrals {}", op_name)
-        };
-        let mut di: i32 = self.cint_cgto_rust(i);
-        let mut dj: i32 = self.cint_cgto_rust(j);
-    
-        let mut shls: Vec<c_int> = vec![i as c_int,j as c_int];
-        //shls.shrink_to_fit();
-        let mut shls = ManuallyDrop::new(shls);
-        let (c_shls,shls_len,shls_cap) = (shls.as_mut_ptr() as *mut c_int,shls.len(),shls.capacity());
-    
-        let mut buf: Vec<f64> = [0.0f64].repeat((di*dj) as usize);
-        //buf.shrink_to_fit();
-        let mut buf = ManuallyDrop::new(buf);
-        let (c_buf, buf_len, buf_cap) = (buf.as_mut_ptr() as *mut f64, buf.len(), buf.capacity());
-    
-        let mut new_buf:Vec<f64>;
-        unsafe {
-            match op_type {
-                IJOPT::Ovlp => {
-                    match self.cint_type {
-                        CintType::Spheric => cint::cint1e_ovlp_sph(
-                                      c_buf, c_shls,
-                                        self.c_atm.0, self.c_natm,
-                                        self.c_bas.0,self.c_nbas,
-                                        self.c_env.0,
-                                        self.c_opt.0),
-                        CintType::Cartesian => cint::cint1e_ovlp_cart(
-                                      c_buf, c_shls,
-                                        self.c_atm.0, self.c_natm,
-                                        self.c_bas.0,self.c_nbas,
-                                        self.c_env.0,
-                                        self.c_opt.0),
-                    }
-                },
-                IJOPT::Kinetic => {    
-                    match self.cint_type {
-                        CintType::Spheric => cint::cint1e_kin_sph(
-                                      c_buf, c_shls,
-                                        self.c_atm.0, self.c_natm,
-                                        self.c_bas.0,self.c_nbas,
-                                        self.c_env.0,
-                                        self.c_opt.0),
-                        CintType::Cartesian => cint::cint1e_kin_cart(
-                                      c_buf, c_shls,
-                                        self.c_atm.0, self.c_natm,
-                                        self.c_bas.0,self.c_nbas,
-                                        self.c_env.0,
-                                        self.c_opt.0),
-                    }
-                },
-                IJOPT::Nuclear => {    
-                    match self.cint_type {
-                        CintType::Spheric => cint::cint1e_nuc_sph(
-                                      c_buf, c_shls,
-                                        self.c_atm.0, self.c_natm,
-                                        self.c_bas.0,self.c_nbas,
-                                        self.c_env.0,
-                                        self.c_opt.0),
-                        CintType::Cartesian => cint::cint1e_nuc_cart(
-                                      c_buf, c_shls,
-                                        self.c_atm.0, self.c_natm,
-                                        self.c_bas.0,self.c_nbas,
-                                        self.c_env.0,
-                                        self.c_opt.0),
-                    }
-                },
-            };
-            let shls = Vec::from_raw_parts(c_shls, shls_len, shls_cap);
-            new_buf = Vec::from_raw_parts(c_buf, buf_len, buf_cap);
+    /// the libcint integral of the operators "ovlp", "kinetic" and "nuclear" of `cint_ij`
+    fn ij_intor(op_name: &str) -> &'static str {
+        match op_name.to_lowercase().as_str() {
+            "ovlp" => "int1e_ovlp",
+            "kinetic" => "int1e_kin",
+            "nuclear" => "int1e_nuc",
+            _ => panic!("Error:: Unknown operator for GTO-ij integrals {}", op_name),
         }
-        new_buf
-    }
-
-    pub fn cint_ip_ij(&mut self, i:i32,j:i32,op_name: &String) -> Vec<f64> {
-        // for 1e integrals: ipovlp
-
-        let op_type = if op_name.to_lowercase() == String::from("ipovlp") {
-            IJIPOPT::IPOvlp
-        } else if op_name.to_lowercase() == String::from("ipkin") {
-            IJIPOPT::IPKin
-        } else if op_name.to_lowercase() == String::from("ipnuc") {
-            IJIPOPT::IPNuc
-        } else if op_name.to_lowercase() == String::from("iprinv") {
-            IJIPOPT::IPRInv
-        } else {
-            panic!("Error:: Unknown operator for GTO-ij-ip integrals {}", op_name)
-        };
-        let mut di: i32 = self.cint_cgto_rust(i);
-        let mut dj: i32 = self.cint_cgto_rust(j);
-    
-        let mut shls: Vec<c_int> = vec![i as c_int,j as c_int];
-        //shls.shrink_to_fit();
-        let mut shls = ManuallyDrop::new(shls);
-        let (c_shls,shls_len,shls_cap) = (shls.as_mut_ptr() as *mut c_int,shls.len(),shls.capacity());
-    
-        let mut buf: Vec<f64> = [0.0f64].repeat((3*di*dj) as usize);
-        //buf.shrink_to_fit();
-        let mut buf = ManuallyDrop::new(buf);
-        let (c_buf, buf_len, buf_cap) = (buf.as_mut_ptr() as *mut f64, buf.len(), buf.capacity());
-    
-        let mut new_buf:Vec<f64>;
-        unsafe {
-            match op_type {
-                IJIPOPT::IPOvlp => {
-                    match self.cint_type {
-                        CintType::Spheric => cint::cint1e_ipovlp_sph(
-                                      c_buf, c_shls,
-                                        self.c_atm.0, self.c_natm,
-                                        self.c_bas.0,self.c_nbas,
-                                        self.c_env.0,
-                                        self.c_opt.0),
-                        CintType::Cartesian => cint::cint1e_ipovlp_cart(
-                                      c_buf, c_shls,
-                                        self.c_atm.0, self.c_natm,
-                                        self.c_bas.0,self.c_nbas,
-                                        self.c_env.0,
-                                        self.c_opt.0),
-                    }
-                },
-                IJIPOPT::IPKin => {
-                    match self.cint_type {
-                        CintType::Spheric => cint::cint1e_ipkin_sph(
-                                      c_buf, c_shls,
-                                        self.c_atm.0, self.c_natm,
-                                        self.c_bas.0,self.c_nbas,
-                                        self.c_env.0,
-                                        self.c_opt.0),
-                        CintType::Cartesian => cint::cint1e_ipkin_cart(
-                                      c_buf, c_shls,
-                                        self.c_atm.0, self.c_natm,
-                                        self.c_bas.0,self.c_nbas,
-                                        self.c_env.0,
-                                        self.c_opt.0),
-                    }
-                },
-                IJIPOPT::IPNuc => {
-                    match self.cint_type {
-                        CintType::Spheric => cint::cint1e_ipnuc_sph(
-                                      c_buf, c_shls,
-                                        self.c_atm.0, self.c_natm,
-                                        self.c_bas.0,self.c_nbas,
-                                        self.c_env.0,
-                                        self.c_opt.0),
-                        CintType::Cartesian => cint::cint1e_ipnuc_cart(
-                                      c_buf, c_shls,
-                                        self.c_atm.0, self.c_natm,
-                                        self.c_bas.0,self.c_nbas,
-                                        self.c_env.0,
-                                        self.c_opt.0),
-                    }
-                },
-                IJIPOPT::IPRInv => {
-                    match self.cint_type {
-                        CintType::Spheric => cint::cint1e_iprinv_sph(
-                                      c_buf, c_shls,
-                                        self.c_atm.0, self.c_natm,
-                                        self.c_bas.0,self.c_nbas,
-                                        self.c_env.0,
-                                        self.c_opt.0),
-                        CintType::Cartesian => cint::cint1e_iprinv_cart(
-                                      c_buf, c_shls,
-                                        self.c_atm.0, self.c_natm,
-                                        self.c_bas.0,self.c_nbas,
-                                        self.c_env.0,
-                                        self.c_opt.0),
-                    }
-                }
-            };
-            //let shls = Vec::from_raw_parts(c_shls, shls_len, shls_cap);
-            new_buf = Vec::from_raw_parts(c_buf, buf_len, buf_cap);
-            
-            //println!("i={},j={},di={},dj={}", i,j,di,dj);
-            //println!("new_buf={:?}", new_buf);
-            //println!("new_buf_len={}", new_buf.len());
+    }
+    pub fn cint_ij(&mut self, i:i32,j:i32,op_name: &str) -> Vec<f64> {
+        // for 1e integrals: ovlp, kinetic, and nuclear
+        self.cint_intor(CINTR2CDATA::ij_intor(op_name), &[i,j])
+    }
+
+    /// initialize the optimizer for the 1e integrals of `cint_ij`
+    pub fn cint_ij_optimizer_rust(&mut self, op_name: &str) {
+        self.intor_optimizer_rust(CINTR2CDATA::ij_intor(op_name));
+    }
+
+    /// The 1e integrals ("ovlp", "kinetic" or "nuclear") between the shells `i_shls` and `j_shls`
+    /// as a `column-major` matrix of `[ni, nj]`.
+    ///
+    /// The corresponding optimizer is (re-)initialized by this driver.
+    pub fn int1e_matrix(&mut self, op_name: &str, i_shls: Range<i32>, j_shls: Range<i32>) -> Vec<f64> {
+        let ao_loc = self.ao_loc();
+        let i_off = ao_loc[i_shls.start as usize];
+        let ni = ao_loc[i_shls.end as usize] - i_off;
+        let j_off = ao_loc[j_shls.start as usize];
+        let nj = ao_loc[j_shls.end as usize] - j_off;
+        let mut mat = vec![0.0; ni*nj];
+        self.cint_ij_optimizer_rust(op_name);
+        let intor = CINTR2CDATA::find_intor(CINTR2CDATA::ij_intor(op_name));
+        // the blocks are written in place, with the leading dimension ni
+        let dims = [ni as c_int, nj as c_int];
+        for j in j_shls {
+            let j0 = ao_loc[j as usize] - j_off;
+            for i in i_shls.clone() {
+                let i0 = ao_loc[i as usize] - i_off;
+                self.cint_eval(intor, &mut mat[i0 + ni*j0..], Some(&dims), &[i,j], None);
+            }
         }
-        new_buf
+        mat
     }
 
-    pub fn cint_ip_3c2e(&mut self, i:i32,j:i32,k:i32,op_name: &String) -> Vec<f64> {
-        let op_type = if op_name.to_lowercase() == String::from("ip1") {
-            IP3C2E::IP1
-        } else if op_name.to_lowercase() == String::from("ip2") {
-            IP3C2E::IP2
-        } else {
-            panic!("Error:: Unknown operator for GTO-3c2e-ip integrals {}", op_name)
-        };
+    /// the libcint integral of the operators "ipovlp", "ipkin", "ipnuc" and "iprinv" of `cint_ip_ij`
+    fn ip_ij_intor(op_name: &str) -> &'static str {
+        match op_name.to_lowercase().as_str() {
+            "ipovlp" => "int1e_ipovlp",
+            "ipkin" => "int1e_ipkin",
+            "ipnuc" => "int1e_ipnuc",
+            "iprinv" => "int1e_iprinv",
+            _ => panic!("Error:: Unknown operator for GTO-ij-ip integrals {}", op_name),
+        }
+    }
+    pub fn cint_ip_ij(&mut self, i:i32,j:i32,op_name: &str) -> Vec<f64> {
+        // for 1e integrals: ipovlp, ipkin, ipnuc and iprinv
+        self.cint_intor(CINTR2CDATA::ip_ij_intor(op_name), &[i,j])
+    }
 
-        let mut di = self.cint_cgto_rust(i);
-        let mut dj = self.cint_cgto_rust(j);
-        let mut dk = self.cint_cgto_rust(k);
-        println!("di,dj,dk = {} {} {}", di, dj, dk);
-        let mut shls: Vec<c_int> = vec![i as c_int,j as c_int,k as c_int];
-        let mut shls = ManuallyDrop::new(shls);
-        let (c_shls,shls_len,shls_cap) = (shls.as_mut_ptr() as *mut c_int,shls.len(),shls.capacity());
-        let mut buf: Vec<f64> = vec![0.0_f64].repeat((3*di*dj*dk) as usize);
-        let mut buf = ManuallyDrop::new(buf);
-        let (c_buf, buf_len, buf_cap) = (buf.as_mut_ptr() as *mut f64, buf.len(), buf.capacity());
-        let mut new_buf:Vec<f64>;
-        unsafe {
-            match op_type { 
-                IP3C2E::IP1 => {
-                    match self.cint_type {
-                        CintType::Spheric => cint::cint3c2e_ip1_sph(c_buf, c_shls,
-                                                            self.c_atm.0, self.c_natm,
-                                                            self.c_bas.0,self.c_nbas,
-                                                            self.c_env.0,
-                                                            self.c_opt.0),
-                        CintType::Cartesian => cint::int3c2e_ip1_cart(c_buf, c_shls,
-                                                            self.c_atm.0, self.c_natm,
-                                                            self.c_bas.0,self.c_nbas,
-                                                            self.c_env.0,
-                                                            self.c_opt.0),
-                    }},
-                IP3C2E::IP2 => {
-                    match self.cint_type {
-                        CintType::Spheric => cint::int3c2e_ip2_sph(c_buf, c_shls,
-                                                            self.c_atm.0, self.c_natm,
-                                                            self.c_bas.0,self.c_nbas,
-                                                            self.c_env.0,
-                                                            self.c_opt.0),
-                        CintType::Cartesian => cint::int3c2e_ip2_cart(c_buf, c_shls,
-                                                            self.c_atm.0, self.c_natm,
-                                                            self.c_bas.0,self.c_nbas,
-                                                            self.c_env.0,
-                                                            self.c_opt.0),
-                    }},
-            };
-            //println!("debug 1 {}", &c_buf.read());
-            //let shls = Vec::from_raw_parts(c_shls, shls_len, shls_cap);
-            new_buf = Vec::from_raw_parts(c_buf, buf_len, buf_cap);
+    /// the libcint integral of the operators "ip1" and "ip2" of `cint_ip_3c2e`
+    fn ip_3c2e_intor(op_name: &str) -> &'static str {
+        match op_name.to_lowercase().as_str() {
+            "ip1" => "int3c2e_ip1",
+            "ip2" => "int3c2e_ip2",
+            _ => panic!("Error:: Unknown operator for GTO-3c2e-ip integrals {}", op_name),
         }
-       new_buf
+    }
+    pub fn cint_ip_3c2e(&mut self, i:i32,j:i32,k:i32,op_name: &str) -> Vec<f64> {
+        self.cint_intor(CINTR2CDATA::ip_3c2e_intor(op_name), &[i,j,k])
     }
 
-}
+    pub fn cint_ij_into(&self, out: &mut [f64], i:i32,j:i32,op_name: &str, workspace: &mut CintWorkspace) -> usize {
+        self.cint_intor_into(CINTR2CDATA::ij_intor(op_name), out, &[i,j], workspace)
+    }
+    pub fn cint_ip_ij_into(&self, out: &mut [f64], i:i32,j:i32,op_name: &str, workspace: &mut CintWorkspace) -> usize {
+        self.cint_intor_into(CINTR2CDATA::ip_ij_intor(op_name), out, &[i,j], workspace)
+    }
+    pub fn cint_ip_3c2e_into(&self, out: &mut [f64], i:i32,j:i32,k:i32,op_name: &str, workspace: &mut CintWorkspace) -> usize {
+        self.cint_intor_into(CINTR2CDATA::ip_3c2e_intor(op_name), out, &[i,j,k], workspace)
+    }
 
+}
+/// H2 with a contracted s shell and a p shell on each atom (nbas = 4, nao = 8 for spheric GTOs)
+#[cfg(test)]
+pub(crate) fn test_h2_data() -> CINTR2CDATA {
+    let mut atm: Vec<Vec<i32>> = vec![];
+    let mut bas: Vec<Vec<i32>> = vec![];
+    let mut env = vec![0.0;20];
+    let mut ptr_env = 20;
+    for z in [-0.8,0.8] {
+        atm.push(vec![1,ptr_env,0,0,0,0]);
+        env.extend(vec![0.0,0.0,z]);
+        ptr_env += 3;
+    }
+    for iatm in 0..2 {
+        env.extend(vec![6.0,2.0,0.8]);
+        [6.0,2.0,0.8].into_iter().zip([0.7,0.6,0.5]).for_each(|(exp, coeff)| {
+            env.push(coeff*CINTR2CDATA::gto_norm(0, exp))
+        });
+        bas.push(vec![iatm,0,3,1,0,ptr_env,ptr_env+3,0]);
+        ptr_env += 6;
+        env.push(0.9);
+        env.push(1.0*CINTR2CDATA::gto_norm(1,0.9));
+        bas.push(vec![iatm,1,1,1,0,ptr_env,ptr_env+1,0]);
+        ptr_env += 2;
+    }
+    let mut cint_data = CINTR2CDATA::new();
+    cint_data.set_cint_type(&CintType::Spheric);
+    cint_data.initial_r2c(&atm,atm.len() as i32,&bas,bas.len() as i32,&env);
+    cint_data
+}
 
+/// the full ERI tensor `(pq|rs)` of `[nao, nao, nao, nao]` over all shells
+#[cfg(test)]
+pub(crate) fn full_eri(cint_data: &mut CINTR2CDATA) -> Vec<f64> {
+    let ao_loc = cint_data.ao_loc();
+    let nbas = cint_data.get_nbas() as usize;
+    let nao = ao_loc[nbas];
+    let mut eri = vec![0.0; nao*nao*nao*nao];
+    cint_data.cint2e_optimizer_rust();
+    for i in 0..nbas {for j in 0..nbas {for k in 0..nbas {for l in 0..nbas {
+        let buf = cint_data.cint_ijkl_by_shell(i as i32,j as i32,k as i32,l as i32);
+        let d = |s: usize| ao_loc[s+1]-ao_loc[s];
+        let (di,dj,dk) = (d(i),d(j),d(k));
+        for ll in 0..d(l) {for kk in 0..dk {for jj in 0..dj {for ii in 0..di {
+            let (p,q,r,s) = (ao_loc[i]+ii, ao_loc[j]+jj, ao_loc[k]+kk, ao_loc[l]+ll);
+            eri[p + nao*(q + nao*(r + nao*s))] = buf[ii+di*(jj+dj*(kk+dk*ll))];
+        }}}}
+    }}}}
+    eri
+}
 
-//pub fn cint2e_sph_rust(mut buf: Vec<f64>, mut shls: Vec<i32>, 
-//                   c_atm: & *mut c_int, c_natm:c_int, 
-//                   c_bas: & *mut c_int, c_nbas:c_int, 
-//                   c_env: & *mut f64,
-//                   c_opt: & *mut CINTOpt) -> Vec<f64> {
-//    //
-//    buf.shrink_to_fit();
-//    let mut buf = ManuallyDrop::new(buf);
-//    let (c_buf, buf_len, buf_cap) = (buf.as_mut_ptr() as *mut f64, buf.len(), buf.capacity());
-//
-//    shls.shrink_to_fit();
-//    let mut shls = ManuallyDrop::new(shls);
-//    let (c_shls,shls_len,shls_cap) = (shls.as_mut_ptr() as *mut c_int,shls.len(),shls.capacity());
-//    let mut new_buf:Vec<f64>;
-//    unsafe {
-//        cint::cint2e_sph(c_buf, c_shls, *c_atm, c_natm, *c_bas, c_nbas, *c_env, *c_opt);
-//        //println!("debug 1 {}", &c_buf.read());
-//        let shls = Vec::from_raw_parts(c_shls, shls_len, shls_cap);
-//        new_buf = Vec::from_raw_parts(c_buf, buf_len, buf_cap);
-//        //println!("debug 2");
-//        //vec![0.0,0.0]
-//    }
-//    new_buf
-//}
-//pub fn cint1e_ovlp_sph_rust(mut buf: Vec<f64>, mut shls: Vec<i32>, 
-//                   c_atm: & *mut c_int, c_natm:c_int, 
-//                   c_bas: & *mut c_int, c_nbas:c_int, 
-//                   c_env: & *mut f64,
-//                   c_opt: & *mut CINTOpt) -> Vec<f64> {
-//    //
-//    buf.shrink_to_fit();
-//    let mut buf = ManuallyDrop::new(buf);
-//    let (c_buf, buf_len, buf_cap) = (buf.as_mut_ptr() as *mut f64, buf.len(), buf.capacity());
-//
-//    shls.shrink_to_fit();
-//    let mut shls = ManuallyDrop::new(shls);
-//    let (c_shls,shls_len,shls_cap) = (shls.as_mut_ptr() as *mut c_int,shls.len(),shls.capacity());
-//    let mut new_buf:Vec<f64>;
-//    unsafe {
-//        cint::cint1e_ovlp_sph(c_buf, c_shls, *c_atm, c_natm, *c_bas, c_nbas, *c_env, *c_opt);
-//        let shls = Vec::from_raw_parts(c_shls, shls_len, shls_cap);
-//        new_buf = Vec::from_raw_parts(c_buf, buf_len, buf_cap);
-//    }
-//    new_buf
-//}
+/// even-tempered-like auxiliary shells (s, s, p, d) on the atoms of `test_h2_data`
+#[cfg(test)]
+pub(crate) fn test_h2_aux_data() -> CINTR2CDATA {
+    let mut atm: Vec<Vec<i32>> = vec![];
+    let mut bas: Vec<Vec<i32>> = vec![];
+    let mut env = vec![0.0;20];
+    let mut ptr_env = 20;
+    for z in [-0.8,0.8] {
+        atm.push(vec![1,ptr_env,0,0,0,0]);
+        env.extend(vec![0.0,0.0,z]);
+        ptr_env += 3;
+    }
+    for iatm in 0..2 {
+        for (l, exp) in [(0,4.0),(0,1.0),(1,1.2),(2,0.8)] {
+            env.push(exp);
+            env.push(CINTR2CDATA::gto_norm(l,exp));
+            bas.push(vec![iatm,l,1,1,0,ptr_env,ptr_env+1,0]);
+            ptr_env += 2;
+        }
+    }
+    let mut cint_data = CINTR2CDATA::new();
+    cint_data.set_cint_type(&CintType::Spheric);
+    cint_data.initial_r2c(&atm,atm.len() as i32,&bas,bas.len() as i32,&env);
+    cint_data
+}
 
 #[test]
 pub fn test_1() {
@@ -867,4 +825,55 @@ pub fn test_1() {
     println!("3c2e_ip1: {:?}", &buf);
 
     cint_data.final_c2r();
-}
\ No newline at end of file
+}
+#[test]
+pub fn test_into() {
+    let mut cint_data = test_h2_data();
+    assert_eq!(cint_data.max_block_size("int2e"), 81);
+    assert_eq!(cint_data.max_block_size("int1e_ipovlp"), 27);
+    assert_eq!(cint_data.intor_block_size("int3c2e_ip1", &[0,1,1]), 27);
+    cint_data.cint2e_optimizer_rust();
+    let mut workspace = cint_data.intor_workspace("int2e");
+    assert!(workspace.len() >= cint_data.max_cache_size("int2e"));
+    let mut buf = vec![0.0; cint_data.max_block_size("int2e")];
+    for (i,j,k,l) in [(0,1,2,3),(1,1,0,3),(3,3,3,3)] {
+        let size = cint_data.cint_ijkl_into(&mut buf, i,j,k,l, &mut workspace);
+        assert_eq!(&buf[..size], &cint_data.cint_ijkl_by_shell(i,j,k,l)[..]);
+    }
+    let too_short = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let mut out = vec![0.0; 8];
+        cint_data.cint_ijkl_into(&mut out, 1,1,1,1, &mut CintWorkspace::new())
+    }));
+    assert!(too_short.is_err());
+    // two blocks written in place into the [4, 3] matrix of the shells 0..2 and 1..2
+    cint_data.cint_ij_optimizer_rust("ovlp");
+    let mut workspace_1e = cint_data.intor_workspace("int1e_ovlp");
+    let mut mat = vec![0.0; 4*3];
+    cint_data.cint_intor_into_dims("int1e_ovlp", &mut mat, &[4,3], &[0,1], &mut workspace_1e);
+    cint_data.cint_intor_into_dims("int1e_ovlp", &mut mat[1..], &[4,3], &[1,1], &mut workspace_1e);
+    assert_eq!(mat, cint_data.int1e_matrix("ovlp", 0..2, 1..2));
+    let too_small = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        cint_data.cint_intor_into_dims("int1e_ovlp", &mut mat, &[2,3], &[1,1], &mut workspace_1e)
+    }));
+    assert!(too_small.is_err());
+    // a basis of the same number of shells but larger shells gets a workspace of its own
+    let mut bas = cint_data.get_bas();
+    bas.iter_mut().for_each(|ibas| ibas[1] += 2);
+    let mut larger = CINTR2CDATA::new();
+    larger.initial_r2c(&cint_data.get_atm(), cint_data.get_natm(), &bas, bas.len() as i32, &cint_data.get_env());
+    larger.cint2e_optimizer_rust();
+    let mut buf = vec![0.0; larger.max_block_size("int2e")];
+    larger.cint_ijkl_into(&mut buf, 1,1,1,1, &mut workspace);
+    assert_eq!(workspace.basis_id, larger.basis_id);
+    assert!(workspace.len() >= larger.max_cache_size("int2e"));
+    larger.final_c2r();
+    // a clone owns its buffers: releasing it twice leaves the original intact
+    let ovlp = cint_data.int1e_matrix("ovlp", 0..4, 0..4);
+    let mut copy = cint_data.clone();
+    assert_eq!(copy.int1e_matrix("ovlp", 0..4, 0..4), ovlp);
+    copy.final_c2r();
+    copy.final_c2r();
+    assert_eq!(copy.ao_loc(), vec![0]);
+    assert_eq!(cint_data.int1e_matrix("ovlp", 0..4, 0..4), ovlp);
+    cint_data.final_c2r();
+}
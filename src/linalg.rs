@@ -0,0 +1,372 @@
+//! Small dense linear-algebra kernels used by the integral drivers.
+//!
+//! All matrices are stored in the `column-major` convention, the same one used
+//! by `libcint` for the integral buffers, i.e. `a[i + nrow*j]` is the element (i,j).
+#![allow(clippy::needless_range_loop)]
+
+/// In-place Cholesky factorization `A = L L^T` of a symmetric positive-definite matrix.
+///
+/// Only the lower triangle of `a` is referenced; on exit it holds `L` and the strict
+/// upper triangle is zeroed. On failure, the index of the first non-positive pivot is returned.
+pub(crate) fn cholesky_lower(a: &mut [f64], n: usize) -> Result<(), usize> {
+    for j in 0..n {
+        let mut d = a[j + n*j];
+        for k in 0..j {d -= a[j + n*k]*a[j + n*k]};
+        if d <= 0.0 || !d.is_finite() {return Err(j)};
+        let d = d.sqrt();
+        a[j + n*j] = d;
+        for i in j+1..n {
+            let mut s = a[i + n*j];
+            for k in 0..j {s -= a[i + n*k]*a[j + n*k]};
+            a[i + n*j] = s/d;
+        }
+        for i in 0..j {a[i + n*j] = 0.0};
+    }
+    Ok(())
+}
+
+/// `C[m,n] = A[m,k] * B[k,n]`
+pub(crate) fn matmul(a: &[f64], b: &[f64], m: usize, k: usize, n: usize) -> Vec<f64> {
+    let mut c = vec![0.0; m*n];
+    for j in 0..n {
+        let c_j = &mut c[m*j..m*(j+1)];
+        for p in 0..k {
+            let b_pj = b[p + k*j];
+            if b_pj == 0.0 {continue};
+            a[m*p..m*(p+1)].iter().zip(c_j.iter_mut()).for_each(|(a_ip,c_ij)| {*c_ij += a_ip*b_pj});
+        }
+    }
+    c
+}
+
+/// `C[m,n] = A[k,m]^T * B[k,n]`
+pub(crate) fn matmul_tn(a: &[f64], b: &[f64], m: usize, k: usize, n: usize) -> Vec<f64> {
+    let mut c = vec![0.0; m*n];
+    for j in 0..n {
+        let b_j = &b[k*j..k*(j+1)];
+        for i in 0..m {
+            c[i + m*j] = a[k*i..k*(i+1)].iter().zip(b_j).map(|(x,y)| x*y).sum();
+        }
+    }
+    c
+}
+
+/// Eigenvalues (ascending) and eigenvectors (stored by columns) of a real symmetric matrix.
+///
+/// Householder tridiagonalization followed by the implicit QL iterations (`tred2`/`tql2` of EISPACK).
+pub(crate) fn symmetric_eigh(a: &[f64], n: usize) -> (Vec<f64>, Vec<f64>) {
+    if a.iter().any(|x| !x.is_finite()) {
+        panic!("Error:: the matrix of symmetric_eigh contains NaN or infinite elements")
+    }
+    let mut v = a.to_vec();
+    let mut d = vec![0.0; n];
+    let mut e = vec![0.0; n];
+    if n == 0 {return (d, v)};
+    tred2(&mut v, &mut d, &mut e, n);
+    tql2(&mut v, &mut d, &mut e, n);
+    (d, v)
+}
+
+fn tred2(v: &mut [f64], d: &mut [f64], e: &mut [f64], n: usize) {
+    // v[i + n*j] plays the role of V[i][j]
+    let id = |i: usize, j: usize| i + n*j;
+    for j in 0..n {d[j] = v[id(n-1,j)]};
+    for i in (1..n).rev() {
+        let mut scale = 0.0;
+        let mut h = 0.0;
+        for k in 0..i {scale += d[k].abs()};
+        if scale == 0.0 {
+            e[i] = d[i-1];
+            for j in 0..i {
+                d[j] = v[id(i-1,j)];
+                v[id(i,j)] = 0.0;
+                v[id(j,i)] = 0.0;
+            }
+        } else {
+            for k in 0..i {
+                d[k] /= scale;
+                h += d[k]*d[k];
+            }
+            let mut f = d[i-1];
+            let mut g = h.sqrt();
+            if f > 0.0 {g = -g};
+            e[i] = scale*g;
+            h -= f*g;
+            d[i-1] = f - g;
+            for j in 0..i {e[j] = 0.0};
+            for j in 0..i {
+                f = d[j];
+                v[id(j,i)] = f;
+                g = e[j] + v[id(j,j)]*f;
+                for k in j+1..i {
+                    g += v[id(k,j)]*d[k];
+                    e[k] += v[id(k,j)]*f;
+                }
+                e[j] = g;
+            }
+            f = 0.0;
+            for j in 0..i {
+                e[j] /= h;
+                f += e[j]*d[j];
+            }
+            let hh = f/(h + h);
+            for j in 0..i {e[j] -= hh*d[j]};
+            for j in 0..i {
+                f = d[j];
+                g = e[j];
+                for k in j..i {v[id(k,j)] -= f*e[k] + g*d[k]};
+                d[j] = v[id(i-1,j)];
+                v[id(i,j)] = 0.0;
+            }
+        }
+        d[i] = h;
+    }
+    // accumulate the transformations
+    for i in 0..n-1 {
+        v[id(n-1,i)] = v[id(i,i)];
+        v[id(i,i)] = 1.0;
+        let h = d[i+1];
+        if h != 0.0 {
+            for k in 0..=i {d[k] = v[id(k,i+1)]/h};
+            for j in 0..=i {
+                let mut g = 0.0;
+                for k in 0..=i {g += v[id(k,i+1)]*v[id(k,j)]};
+                for k in 0..=i {v[id(k,j)] -= g*d[k]};
+            }
+        }
+        for k in 0..=i {v[id(k,i+1)] = 0.0};
+    }
+    for j in 0..n {
+        d[j] = v[id(n-1,j)];
+        v[id(n-1,j)] = 0.0;
+    }
+    v[id(n-1,n-1)] = 1.0;
+    e[0] = 0.0;
+}
+
+fn tql2(v: &mut [f64], d: &mut [f64], e: &mut [f64], n: usize) {
+    let id = |i: usize, j: usize| i + n*j;
+    for i in 1..n {e[i-1] = e[i]};
+    e[n-1] = 0.0;
+    let mut f = 0.0;
+    let mut tst1: f64 = 0.0;
+    let eps = f64::EPSILON;
+    // the QL sweeps converge in a few per eigenvalue; the cap guards against a spin on bad input
+    let max_sweeps = 30*n;
+    let mut sweeps = 0;
+    for l in 0..n {
+        tst1 = tst1.max(d[l].abs() + e[l].abs());
+        let mut m = l;
+        while m < n {
+            if e[m].abs() <= eps*tst1 {break};
+            m += 1;
+        }
+        if m > l {
+            loop {
+                sweeps += 1;
+                if sweeps > max_sweeps {
+                    panic!("Error:: the QL iterations of symmetric_eigh do not converge in {} sweeps", max_sweeps)
+                }
+                let mut g = d[l];
+                let mut p = (d[l+1] - g)/(2.0*e[l]);
+                let mut r = p.hypot(1.0);
+                if p < 0.0 {r = -r};
+                d[l] = e[l]/(p + r);
+                d[l+1] = e[l]*(p + r);
+                let dl1 = d[l+1];
+                let mut h = g - d[l];
+                for i in l+2..n {d[i] -= h};
+                f += h;
+
+                p = d[m];
+                let mut c = 1.0;
+                let mut c2 = c;
+                let mut c3 = c;
+                let el1 = e[l+1];
+                let mut s = 0.0;
+                let mut s2 = 0.0;
+                for i in (l..m).rev() {
+                    c3 = c2;
+                    c2 = c;
+                    s2 = s;
+                    g = c*e[i];
+                    h = c*p;
+                    r = p.hypot(e[i]);
+                    e[i+1] = s*r;
+                    s = e[i]/r;
+                    c = p/r;
+                    p = c*d[i] - s*g;
+                    d[i+1] = h + s*(c*g + s*d[i]);
+                    for k in 0..n {
+                        h = v[id(k,i+1)];
+                        v[id(k,i+1)] = s*v[id(k,i)] + c*h;
+                        v[id(k,i)] = c*v[id(k,i)] - s*h;
+                    }
+                }
+                p = -s*s2*c3*el1*e[l]/dl1;
+                e[l] = s*p;
+                d[l] = c*p;
+                if e[l].abs() <= eps*tst1 {break};
+            }
+        }
+        d[l] += f;
+        e[l] = 0.0;
+    }
+    // sort the eigenvalues and the corresponding vectors in ascending order
+    for i in 0..n-1 {
+        let mut k = i;
+        let mut p = d[i];
+        for j in i+1..n {
+            if d[j] < p {
+                k = j;
+                p = d[j];
+            }
+        }
+        if k != i {
+            d[k] = d[i];
+            d[i] = p;
+            for j in 0..n {v.swap(id(j,i), id(j,k))};
+        }
+    }
+}
+
+/// Eigenvalues (ascending) of the real symmetric tridiagonal matrix with the diagonal `d` and the
+/// off-diagonal `e` (`e[i]` couples `i` and `i+1`; `e[n-1]` is scratch), into `d`; `e` is destroyed.
+///
+/// The implicit QL iterations of `tql2` without the eigenvectors (`tql1` of EISPACK), free of allocation.
+pub(crate) fn tridiagonal_eigenvalues(d: &mut [f64], e: &mut [f64]) {
+    let n = d.len();
+    if e.len() != n {
+        panic!("Error:: the off-diagonal of {} elements does not match the tridiagonal matrix of {}", e.len(), n)
+    }
+    if n == 0 {return};
+    if d.iter().chain(&e[..n-1]).any(|x| !x.is_finite()) {
+        panic!("Error:: the tridiagonal matrix contains NaN or infinite elements")
+    }
+    e[n-1] = 0.0;
+    tql1(d, e, n);
+}
+
+fn tql1(d: &mut [f64], e: &mut [f64], n: usize) {
+    let mut f = 0.0;
+    let mut tst1: f64 = 0.0;
+    let eps = f64::EPSILON;
+    let max_sweeps = 30*n;
+    let mut sweeps = 0;
+    for l in 0..n {
+        tst1 = tst1.max(d[l].abs() + e[l].abs());
+        let mut m = l;
+        while m < n {
+            if e[m].abs() <= eps*tst1 {break};
+            m += 1;
+        }
+        if m > l {
+            loop {
+                sweeps += 1;
+                if sweeps > max_sweeps {
+                    panic!("Error:: the QL iterations of tridiagonal_eigenvalues do not converge in {} sweeps", max_sweeps)
+                }
+                let mut g = d[l];
+                let mut p = (d[l+1] - g)/(2.0*e[l]);
+                let mut r = p.hypot(1.0);
+                if p < 0.0 {r = -r};
+                d[l] = e[l]/(p + r);
+                d[l+1] = e[l]*(p + r);
+                let dl1 = d[l+1];
+                let h = g - d[l];
+                for i in l+2..n {d[i] -= h};
+                f += h;
+
+                p = d[m];
+                let mut c = 1.0;
+                let mut c2 = c;
+                let mut c3 = c;
+                let el1 = e[l+1];
+                let mut s = 0.0;
+                let mut s2 = 0.0;
+                for i in (l..m).rev() {
+                    c3 = c2;
+                    c2 = c;
+                    s2 = s;
+                    g = c*e[i];
+                    let h = c*p;
+                    r = p.hypot(e[i]);
+                    e[i+1] = s*r;
+                    s = e[i]/r;
+                    c = p/r;
+                    p = c*d[i] - s*g;
+                    d[i+1] = h + s*(c*g + s*d[i]);
+                }
+                p = -s*s2*c3*el1*e[l]/dl1;
+                e[l] = s*p;
+                d[l] = c*p;
+                if e[l].abs() <= eps*tst1 {break};
+            }
+        }
+        d[l] += f;
+        e[l] = 0.0;
+    }
+    // insertion sort in ascending order
+    for i in 1..n {
+        let p = d[i];
+        let mut j = i;
+        while j > 0 && d[j-1] > p {
+            d[j] = d[j-1];
+            j -= 1;
+        }
+        d[j] = p;
+    }
+}
+
+#[test]
+pub fn test_linalg() {
+    // a symmetric positive-definite matrix
+    let n = 4;
+    let a = vec![
+        4.0, 1.0, 0.5, 0.2,
+        1.0, 3.0, 0.4, 0.1,
+        0.5, 0.4, 2.0, 0.3,
+        0.2, 0.1, 0.3, 1.0];
+    let (w, u) = symmetric_eigh(&a, n);
+    // A U = U diag(w)
+    let au = matmul(&a, &u, n, n, n);
+    for j in 0..n {
+        for i in 0..n {
+            assert!((au[i+n*j] - u[i+n*j]*w[j]).abs() < 1.0e-12);
+        }
+    }
+    assert!(w.windows(2).all(|x| x[0] <= x[1]));
+    // U^T U = 1
+    let utu = matmul_tn(&u, &u, n, n, n);
+    for j in 0..n {
+        for i in 0..n {
+            let one = if i==j {1.0} else {0.0};
+            assert!((utu[i+n*j] - one).abs() < 1.0e-12);
+        }
+    }
+    let nan = std::panic::catch_unwind(|| symmetric_eigh(&[1.0, f64::NAN, f64::NAN, 2.0], 2));
+    assert!(nan.is_err());
+    // the eigenvalues of a tridiagonal matrix, against the dense solver
+    let (diag, off) = ([2.0, -1.0, 0.5, 3.0, 1.0], [0.7, 1.2, 0.0, -0.4]);
+    let m = diag.len();
+    let mut dense = vec![0.0; m*m];
+    for i in 0..m {
+        dense[i + m*i] = diag[i];
+        if i+1 < m {
+            dense[i+1 + m*i] = off[i];
+            dense[i + m*(i+1)] = off[i];
+        }
+    }
+    let (w, _) = symmetric_eigh(&dense, m);
+    let (mut d, mut e) = (diag.to_vec(), vec![0.0; m]);
+    e[..m-1].copy_from_slice(&off);
+    tridiagonal_eigenvalues(&mut d, &mut e);
+    d.iter().zip(&w).for_each(|(x,y)| assert!((x-y).abs() < 1.0e-12));
+    // L L^T = A
+    let mut l = a.clone();
+    cholesky_lower(&mut l, n).unwrap();
+    let mut lt = vec![0.0; n*n];
+    for j in 0..n {for i in 0..n {lt[j+n*i] = l[i+n*j]}};
+    let llt = matmul(&l, &lt, n, n, n);
+    llt.iter().zip(a.iter()).for_each(|(x,y)| assert!((x-y).abs() < 1.0e-12));
+}
@@ -0,0 +1,139 @@
+//! Integrals between two different basis sets.
+//!
+//! `libcint` evaluates integrals only for shells living in the same `atm`/`bas`/`env` arrays.
+//! Following `conc_env` of PySCF, two data sets are concatenated with the atom indices and the
+//! `env` pointers of the second one shifted, such that the shells of the first basis are
+//! `0..nbas1` and those of the second basis are `nbas1..nbas1+nbas2` in the combined data.
+use crate::CINTR2CDATA;
+use crate::density_fitting::{MetricDecomposition, RIFactors};
+
+// slots of atm
+const CHARGE_OF: usize = 0;
+const PTR_COORD: usize = 1;
+const PTR_ZETA: usize = 3;
+const PTR_FRAC_CHARGE: usize = 4;
+// slots of bas
+const ATOM_OF: usize = 0;
+const PTR_EXP: usize = 5;
+const PTR_COEFF: usize = 6;
+
+/// Concatenate the `atm`, `bas` and `env` data of two basis sets (`conc_env` of PySCF).
+pub fn conc_env(atm1: &[Vec<i32>], bas1: &[Vec<i32>], env1: &[f64],
+                atm2: &[Vec<i32>], bas2: &[Vec<i32>], env2: &[f64]) -> (Vec<Vec<i32>>, Vec<Vec<i32>>, Vec<f64>) {
+    let off = env1.len() as i32;
+    let natm_off = atm1.len() as i32;
+    let mut atm = atm1.to_vec();
+    atm.extend(atm2.iter().map(|iatm| {
+        let mut iatm = iatm.clone();
+        iatm[PTR_COORD] += off;
+        iatm[PTR_ZETA] += off;
+        iatm
+    }));
+    let mut bas = bas1.to_vec();
+    bas.extend(bas2.iter().map(|ibas| {
+        let mut ibas = ibas.clone();
+        ibas[ATOM_OF] += natm_off;
+        ibas[PTR_EXP] += off;
+        ibas[PTR_COEFF] += off;
+        ibas
+    }));
+    let mut env = env1.to_vec();
+    env.extend_from_slice(env2);
+    (atm, bas, env)
+}
+
+impl CINTR2CDATA {
+    /// A new `CINTR2CDATA` holding the shells of `self` followed by those of `other`.
+    ///
+    /// The `CintType` of `self` is used. As for any `CINTR2CDATA`, the memory should be
+    /// released by `final_c2r` once the combined data are no longer needed.
+    pub fn conc_cint_data(&self, other: &CINTR2CDATA) -> CINTR2CDATA {
+        let (atm, bas, env) = conc_env(&self.get_atm(), &self.get_bas(), &self.get_env(),
+                                       &other.get_atm(), &other.get_bas(), &other.get_env());
+        let mut cint_data = CINTR2CDATA::new();
+        cint_data.set_cint_type(&self.cint_type);
+        cint_data.initial_r2c(&atm, atm.len() as i32, &bas, bas.len() as i32, &env);
+        cint_data
+    }
+
+    /// The 1e integrals ("ovlp", "kinetic" or "nuclear") between the basis functions of `self`
+    /// and those of `other`, as a `column-major` matrix of `[nao1, nao2]`.
+    ///
+    /// For "nuclear", only the nuclei of `self` are included, so that two basis sets of the same
+    /// molecule do not count each nucleus twice.
+    pub fn int1e_cross(&self, other: &CINTR2CDATA, op_name: &str) -> Vec<f64> {
+        let nbas1 = self.c_nbas;
+        let natm1 = self.c_natm as usize;
+        let (mut atm, bas, env) = conc_env(&self.get_atm(), &self.get_bas(), &self.get_env(),
+                                           &other.get_atm(), &other.get_bas(), &other.get_env());
+        // the atoms of `other` only carry its shells
+        atm[natm1..].iter_mut().for_each(|iatm| {
+            iatm[CHARGE_OF] = 0;
+            iatm[PTR_FRAC_CHARGE] = 0;
+        });
+        let mut cint_data = CINTR2CDATA::new();
+        cint_data.set_cint_type(&self.cint_type);
+        cint_data.initial_r2c(&atm, atm.len() as i32, &bas, bas.len() as i32, &env);
+        let nbas = cint_data.c_nbas;
+        let mat = cint_data.int1e_matrix(op_name, 0..nbas1, nbas1..nbas);
+        cint_data.final_c2r();
+        mat
+    }
+
+    /// The density-fitting factors of the orbital basis `self` with the auxiliary basis `aux`
+    /// defined in a separate `CINTR2CDATA` (see `ri3fn_factors`).
+    pub fn ri3fn_factors_with_aux(&self, aux: &CINTR2CDATA, decomposition: MetricDecomposition) -> RIFactors {
+        let nbas1 = self.c_nbas;
+        let mut cint_data = self.conc_cint_data(aux);
+        let nbas = cint_data.c_nbas;
+        let ri = cint_data.ri3fn_factors(0..nbas1, nbas1..nbas, decomposition);
+        cint_data.final_c2r();
+        ri
+    }
+}
+
+#[test]
+pub fn test_conc_cint_data() {
+    use crate::CintType;
+    let atm: Vec<Vec<i32>> = vec![vec![1,20,0,0,0,0], vec![1,23,0,0,0,0]];
+    let mut env = vec![0.0;20];
+    env.extend(vec![0.0,0.0,-0.7, 0.0,0.0,0.7]);
+    env.extend(vec![1.2, 0.8*CINTR2CDATA::gto_norm(0,1.2)]);
+    env.extend(vec![0.9, 1.0*CINTR2CDATA::gto_norm(1,0.9)]);
+    let bas1: Vec<Vec<i32>> = vec![vec![0,0,1,1,0,26,27,0], vec![1,0,1,1,0,26,27,0]];
+    let bas2: Vec<Vec<i32>> = vec![vec![0,1,1,1,0,28,29,0], vec![1,1,1,1,0,28,29,0]];
+
+    let mut basis1 = CINTR2CDATA::new();
+    basis1.set_cint_type(&CintType::Spheric);
+    basis1.initial_r2c(&atm,2,&bas1,2,&env);
+    let mut basis2 = CINTR2CDATA::new();
+    basis2.set_cint_type(&CintType::Spheric);
+    basis2.initial_r2c(&atm,2,&bas2,2,&env);
+
+    let mut combined = basis1.conc_cint_data(&basis2);
+    assert_eq!(combined.get_natm(), 4);
+    assert_eq!(combined.get_nbas(), 4);
+    let off = env.len() as i32;
+    assert_eq!(combined.get_atm()[3][PTR_COORD], 23 + off);
+    assert_eq!(combined.get_bas()[2], vec![2,1,1,1,0,28+off,29+off,0]);
+
+    // the cross overlap agrees with the corresponding block of the combined basis
+    let op = String::from("ovlp");
+    let cross = basis1.int1e_cross(&basis2, &op);
+    let block = combined.int1e_matrix(&op, 0..2, 2..4);
+    assert_eq!(cross.len(), 2*6);
+    cross.iter().zip(block.iter()).for_each(|(x,y)| assert!((x-y).abs() < 1.0e-12));
+    // the cross nuclear attraction counts the two nuclei once, as in a single data set of both bases
+    let mut both = CINTR2CDATA::new();
+    both.set_cint_type(&CintType::Spheric);
+    both.initial_r2c(&atm,2,&[bas1.clone(),bas2.clone()].concat(),4,&env);
+    let cross = basis1.int1e_cross(&basis2, "nuclear");
+    let block = both.int1e_matrix("nuclear", 0..2, 2..4);
+    assert!(block.iter().any(|x| x.abs() > 1.0e-3));
+    cross.iter().zip(block.iter()).for_each(|(x,y)| assert!((x-y).abs() < 1.0e-12));
+
+    both.final_c2r();
+    combined.final_c2r();
+    basis1.final_c2r();
+    basis2.final_c2r();
+}
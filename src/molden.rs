@@ -0,0 +1,412 @@
+//! Molden files: the geometry (`[Atoms]`), the basis set (`[GTO]`) and the molecular orbitals (`[MO]`).
+//!
+//! The contracted GTOs are written as normalized contractions of normalized primitives, the
+//! form assumed by the programs reading Molden files, and the MO coefficients are converted
+//! to the ordering and normalization of Molden by `ao_convention::AoConvention::Molden`.
+//! Spheric GTOs are flagged by `[5D7F]` and `[9G]`; Molden supports shells up to g. The `[MO]`
+//! coefficients follow the AOs of the `[GTO]` section, where the shells of `bas` are grouped by atom.
+use std::fmt::Write as FmtWrite;
+use std::io;
+
+use crate::{CINTR2CDATA, CintType};
+use crate::ao_convention::AoConvention;
+
+// slots of atm and bas
+const CHARGE_OF: usize = 0;
+const PTR_COORD: usize = 1;
+const ATOM_OF: usize = 0;
+const ANG_OF: usize = 1;
+const NPRIM_OF: usize = 2;
+const NCTR_OF: usize = 3;
+const PTR_EXP: usize = 5;
+const PTR_COEFF: usize = 6;
+
+const BOHR_IN_ANGSTROM: f64 = 0.52917721092;
+const ANG_LABELS: [&str; 5] = ["s", "p", "d", "f", "g"];
+
+/// The molecular orbitals of one spin, with the coefficients `mo_coeff` of `[nao, nmo]` in the AO order of `libcint`.
+#[derive(Clone,Debug,PartialEq)]
+pub struct MolecularOrbitals {
+    pub nao: usize,
+    pub mo_coeff: Vec<f64>,
+    pub mo_energy: Vec<f64>,
+    pub mo_occ: Vec<f64>,
+}
+
+impl MolecularOrbitals {
+    pub fn nmo(&self) -> usize {
+        self.mo_energy.len()
+    }
+}
+
+/// The content of a Molden file in the data layout of `libcint`.
+#[derive(Clone,Debug)]
+pub struct MoldenData {
+    pub atm: Vec<Vec<i32>>,
+    pub bas: Vec<Vec<i32>>,
+    pub env: Vec<f64>,
+    pub cint_type: CintType,
+    /// one set for restricted orbitals, the alpha and beta sets for unrestricted ones
+    pub orbitals: Vec<MolecularOrbitals>,
+}
+
+impl MoldenData {
+    /// A new `CINTR2CDATA` of the basis set, which should be released by `final_c2r`.
+    pub fn cint_data(&self) -> CINTR2CDATA {
+        let mut cint_data = CINTR2CDATA::new();
+        cint_data.set_cint_type(&self.cint_type);
+        cint_data.initial_r2c(&self.atm, self.atm.len() as i32, &self.bas, self.bas.len() as i32, &self.env);
+        cint_data
+    }
+}
+
+pub(crate) fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// A contracted shell on normalized primitives.
+#[derive(Clone,Debug,PartialEq)]
+pub(crate) struct ContractedShell {
+    pub l: usize,
+    pub atm_id: usize,
+    pub exps: Vec<f64>,
+    pub coeffs: Vec<f64>,
+}
+
+/// the overlap of two normalized primitives with the same angular part
+fn primitive_overlap(l: usize, a: f64, b: f64) -> f64 {
+    (2.0*(a*b).sqrt()/(a+b)).powf(l as f64 + 1.5)
+}
+
+/// The norm of the contraction `coeffs` of normalized primitives
+pub(crate) fn contraction_norm(l: usize, exps: &[f64], coeffs: &[f64]) -> f64 {
+    let mut norm2 = 0.0;
+    for (a, ca) in exps.iter().zip(coeffs.iter()) {
+        for (b, cb) in exps.iter().zip(coeffs.iter()) {norm2 += ca*cb*primitive_overlap(l, *a, *b)};
+    }
+    norm2.sqrt()
+}
+
+/// The `atm`, `bas` and `env` of the atoms `(Z, coordinates in bohr)` and the shells, whose contractions are normalized.
+pub(crate) fn cint_layout(atoms: &[(i32, [f64;3])], shells: &[ContractedShell]) -> (Vec<Vec<i32>>, Vec<Vec<i32>>, Vec<f64>) {
+    let mut atm = vec![];
+    let mut bas = vec![];
+    let mut env = vec![0.0; 20];
+    for (z, coord) in atoms.iter() {
+        atm.push(vec![*z, env.len() as i32, 0, 0, 0, 0]);
+        env.extend(coord.iter());
+    }
+    for shell in shells.iter() {
+        let norm = contraction_norm(shell.l, &shell.exps, &shell.coeffs);
+        let nprim = shell.exps.len() as i32;
+        let ptr_exp = env.len() as i32;
+        env.extend(shell.exps.iter());
+        env.extend(shell.exps.iter().zip(shell.coeffs.iter()).map(|(e, c)| c/norm*CINTR2CDATA::gto_norm(shell.l as i32, *e)));
+        bas.push(vec![shell.atm_id as i32, shell.l as i32, nprim, 1, 0, ptr_exp, ptr_exp + nprim, 0]);
+    }
+    (atm, bas, env)
+}
+
+impl CINTR2CDATA {
+    /// The contractions of all shells on normalized primitives, each normalized, together with the norm of each contracted AO (in the order of `libcint`) that
+    /// the MO coefficients should be multiplied with.
+    pub(crate) fn normalized_contractions(&self) -> (Vec<ContractedShell>, Vec<f64>) {
+        let env = self.get_env();
+        let ao_loc = self.ao_loc();
+        let mut shells = vec![];
+        let mut ao_norm = vec![];
+        for (ish, ibas) in self.get_bas().iter().enumerate() {
+            let l = ibas[ANG_OF] as usize;
+            let nprim = ibas[NPRIM_OF] as usize;
+            let nctr = ibas[NCTR_OF] as usize;
+            let exps = env[ibas[PTR_EXP] as usize..ibas[PTR_EXP] as usize + nprim].to_vec();
+            let ncomp = (ao_loc[ish+1] - ao_loc[ish])/nctr;
+            for ictr in 0..nctr {
+                let p0 = ibas[PTR_COEFF] as usize + nprim*ictr;
+                let coeffs: Vec<f64> = (0..nprim).map(|k| env[p0+k]/CINTR2CDATA::gto_norm(l as i32, exps[k])).collect();
+                let norm = contraction_norm(l, &exps, &coeffs);
+                shells.push(ContractedShell {l, atm_id: ibas[ATOM_OF] as usize, exps: exps.clone(), coeffs: coeffs.iter().map(|c| c/norm).collect()});
+                ao_norm.extend(vec![norm; ncomp]);
+            }
+        }
+        (shells, ao_norm)
+    }
+
+    /// The MO coefficients of `[nao, nmo]` on the normalized AOs in the ordering of `conv`.
+    pub(crate) fn mo_coeff_to_convention(&self, mo_coeff: &[f64], nmo: usize, conv: AoConvention) -> Vec<f64> {
+        let (_, ao_norm) = self.normalized_contractions();
+        let nao = ao_norm.len();
+        let scaled: Vec<f64> = mo_coeff.iter().enumerate().map(|(i, c)| c*ao_norm[i%nao]).collect();
+        self.ao_reorder(conv).transform_mo_coeff(&scaled, nmo)
+    }
+
+    /// The Molden file of the geometry, the basis set and the orbitals (one set for restricted orbitals,
+    /// the alpha and beta sets for unrestricted ones).
+    pub fn to_molden_string(&self, orbitals: &[MolecularOrbitals]) -> io::Result<String> {
+        let env = self.get_env();
+        let (shells, _) = self.normalized_contractions();
+        if let Some(l) = shells.iter().map(|s| s.l).find(|l| *l > 4) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Error:: the shells with l = {} are not supported by Molden", l)))
+        }
+        let ao_loc = self.ao_loc();
+        let nao = ao_loc[self.c_nbas as usize];
+        for mo in orbitals.iter() {
+            let nmo = mo.nmo();
+            if mo.nao != nao || mo.mo_coeff.len() != nao*nmo || mo.mo_occ.len() != nmo {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+                    "Error:: the orbitals of {} AOs with {} coefficients, {} energies and {} occupations do not match the basis of {} AOs",
+                    mo.nao, mo.mo_coeff.len(), nmo, mo.mo_occ.len(), nao)))
+            }
+        }
+        // the AOs in the order of the [GTO] section, i.e. the shells of bas grouped by atom
+        let bas = self.get_bas();
+        let mut gto_order = vec![];
+        for atm_id in 0..self.c_natm as usize {
+            for (ish, ibas) in bas.iter().enumerate() {
+                if ibas[ATOM_OF] as usize == atm_id {gto_order.extend(ao_loc[ish]..ao_loc[ish+1])};
+            }
+        }
+        let mut out = String::new();
+        writeln!(out, "[Molden Format]").unwrap();
+        writeln!(out, "[Atoms] (AU)").unwrap();
+        for (atm_id, iatm) in self.get_atm().iter().enumerate() {
+            let p = iatm[PTR_COORD] as usize;
+            writeln!(out, "{:<4}{:>5}{:>5}{:>20.10}{:>20.10}{:>20.10}", self.atom_symbol(atm_id), atm_id+1, iatm[CHARGE_OF],
+                     env[p], env[p+1], env[p+2]).unwrap();
+        }
+        writeln!(out, "[GTO]").unwrap();
+        for atm_id in 0..self.c_natm as usize {
+            writeln!(out, "{:>4} 0", atm_id+1).unwrap();
+            for shell in shells.iter().filter(|s| s.atm_id == atm_id) {
+                writeln!(out, " {}{:>5} 1.00", ANG_LABELS[shell.l], shell.exps.len()).unwrap();
+                shell.exps.iter().zip(shell.coeffs.iter()).for_each(|(e, c)| writeln!(out, "{:>20.10e}{:>20.10e}", e, c).unwrap());
+            }
+            writeln!(out).unwrap();
+        }
+        if let CintType::Spheric = self.cint_type {
+            writeln!(out, "[5D7F]").unwrap();
+            writeln!(out, "[9G]").unwrap();
+        }
+        writeln!(out, "[MO]").unwrap();
+        for (ispin, mo) in orbitals.iter().enumerate() {
+            let spin = if ispin == 0 {"Alpha"} else {"Beta"};
+            let nmo = mo.nmo();
+            let coeff = self.mo_coeff_to_convention(&mo.mo_coeff, nmo, AoConvention::Molden);
+            for m in 0..nmo {
+                writeln!(out, " Sym= A").unwrap();
+                writeln!(out, " Ene= {:.10}", mo.mo_energy[m]).unwrap();
+                writeln!(out, " Spin= {}", spin).unwrap();
+                writeln!(out, " Occup= {:.10}", mo.mo_occ[m]).unwrap();
+                gto_order.iter().enumerate()
+                    .for_each(|(i, k)| writeln!(out, "{:>5} {:>20.12}", i+1, coeff[k + nao*m]).unwrap());
+            }
+        }
+        Ok(out)
+    }
+
+    pub fn write_molden(&self, path: &str, orbitals: &[MolecularOrbitals]) -> io::Result<()> {
+        std::fs::write(path, self.to_molden_string(orbitals)?)
+    }
+}
+
+pub(crate) fn parse_f64(s: &str) -> io::Result<f64> {
+    s.replace(['D','d'], "e").parse::<f64>().map_err(|_| invalid_data(format!("Error:: cannot parse '{}' as a number", s)))
+}
+
+fn parse_usize(s: &str) -> io::Result<usize> {
+    s.parse::<usize>().map_err(|_| invalid_data(format!("Error:: cannot parse '{}' as an integer", s)))
+}
+
+/// an orbital of the [MO] section with the coefficients by the 1-based AO index
+struct MoldenOrbital {
+    beta: bool,
+    energy: f64,
+    occ: f64,
+    coeffs: Vec<(usize, f64)>,
+}
+
+/// Read the geometry, the basis set and the orbitals of a Molden file.
+pub fn read_molden(path: &str) -> io::Result<MoldenData> {
+    parse_molden(&std::fs::read_to_string(path)?)
+}
+
+/// Parse the content of a Molden file.
+pub fn parse_molden(content: &str) -> io::Result<MoldenData> {
+    let mut section = String::new();
+    let mut angstrom = false;
+    // (Z, coordinates in bohr)
+    let mut atoms: Vec<(i32, [f64;3])> = vec![];
+    let mut shells: Vec<ContractedShell> = vec![];
+    let mut gto_atom = 0;
+    let mut pending: Option<(Vec<usize>, usize)> = None;
+    let (mut pure_d, mut pure_f, mut pure_g) = (false, false, false);
+    let mut mos: Vec<MoldenOrbital> = vec![];
+    let mut header_open = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {continue};
+        if trimmed.starts_with('[') {
+            let lower = trimmed.to_lowercase();
+            section = lower[1..lower.find(']').unwrap_or(lower.len())].to_string();
+            match section.as_str() {
+                "atoms" => angstrom = lower.contains("ang"),
+                "5d" | "5d7f" => {pure_d = true; pure_f = true},
+                "5d10f" => pure_d = true,
+                "7f" => pure_f = true,
+                "9g" => pure_g = true,
+                _ => {},
+            }
+            continue
+        }
+        let words: Vec<&str> = trimmed.split_whitespace().collect();
+        match section.as_str() {
+            "atoms" => {
+                if words.len() < 6 {return Err(invalid_data(format!("Error:: invalid line in [Atoms]: {}", trimmed)))};
+                let factor = if angstrom {1.0/BOHR_IN_ANGSTROM} else {1.0};
+                let coord = [parse_f64(words[3])?*factor, parse_f64(words[4])?*factor, parse_f64(words[5])?*factor];
+                atoms.push((parse_usize(words[2])? as i32, coord));
+            },
+            "gto" => {
+                let label = words[0].to_lowercase();
+                if let Some((ls, nprim)) = pending.as_mut() {
+                    let exp = parse_f64(words[0])?;
+                    for i in 0..ls.len() {
+                        let n = shells.len() - ls.len() + i;
+                        shells[n].exps.push(exp);
+                        shells[n].coeffs.push(parse_f64(words[1+i])?);
+                    }
+                    *nprim -= 1;
+                    if *nprim == 0 {pending = None};
+                } else if let Some(l) = ANG_LABELS.iter().position(|x| *x == label) {
+                    shells.push(ContractedShell {l, atm_id: gto_atom, exps: vec![], coeffs: vec![]});
+                    pending = Some((vec![l], parse_usize(words[1])?));
+                } else if label == "sp" {
+                    for l in 0..2 {shells.push(ContractedShell {l, atm_id: gto_atom, exps: vec![], coeffs: vec![]})};
+                    pending = Some((vec![0,1], parse_usize(words[1])?));
+                } else if words.len() == 2 && words[0].chars().all(|c| c.is_ascii_digit()) {
+                    gto_atom = parse_usize(words[0])? - 1;
+                } else {
+                    return Err(invalid_data(format!("Error:: unsupported shell in [GTO]: {}", trimmed)))
+                }
+            },
+            "mo" => {
+                if let Some((key, value)) = trimmed.split_once('=') {
+                    if !header_open {
+                        mos.push(MoldenOrbital {beta: false, energy: 0.0, occ: 0.0, coeffs: vec![]});
+                        header_open = true;
+                    }
+                    let mo = mos.last_mut().unwrap();
+                    match key.trim().to_lowercase().as_str() {
+                        "ene" => mo.energy = parse_f64(value.trim())?,
+                        "spin" => mo.beta = value.trim().to_lowercase() == "beta",
+                        "occup" => mo.occ = parse_f64(value.trim())?,
+                        _ => {},
+                    }
+                } else {
+                    header_open = false;
+                    let mo = mos.last_mut().ok_or(invalid_data("Error:: MO coefficients without a header".to_string()))?;
+                    mo.coeffs.push((parse_usize(words[0])?, parse_f64(words[1])?));
+                }
+            },
+            _ => {},
+        }
+    }
+
+    // all shells with l >= 2 should be of the same kind
+    let pure: Vec<bool> = shells.iter().filter_map(|s| match s.l {2 => Some(pure_d), 3 => Some(pure_f), 4 => Some(pure_g), _ => None}).collect();
+    let cint_type = if pure.iter().all(|p| *p) {
+        CintType::Spheric
+    } else if pure.iter().all(|p| !*p) {
+        CintType::Cartesian
+    } else {
+        return Err(invalid_data("Error:: mixed spheric and Cartesian shells are not supported".to_string()))
+    };
+
+    let (atm, bas, env) = cint_layout(&atoms, &shells);
+
+    let mut data = MoldenData {atm, bas, env, cint_type, orbitals: vec![]};
+    let mut cint_data = data.cint_data();
+    let nao = cint_data.ao_loc()[cint_data.c_nbas as usize];
+    let back = cint_data.ao_reorder(AoConvention::Molden).inverse();
+    cint_data.final_c2r();
+    for beta in [false, true] {
+        let selected: Vec<&MoldenOrbital> = mos.iter().filter(|mo| mo.beta == beta).collect();
+        if selected.is_empty() {continue};
+        let nmo = selected.len();
+        let mut coeff = vec![0.0; nao*nmo];
+        for (m, mo) in selected.iter().enumerate() {
+            for (i, c) in mo.coeffs.iter() {
+                if *i == 0 || *i > nao {return Err(invalid_data(format!("Error:: the AO index {} is out of the basis of {} AOs", i, nao)))};
+                coeff[i-1 + nao*m] = *c;
+            }
+        }
+        data.orbitals.push(MolecularOrbitals {
+            nao,
+            mo_coeff: back.transform_mo_coeff(&coeff, nmo),
+            mo_energy: selected.iter().map(|mo| mo.energy).collect(),
+            mo_occ: selected.iter().map(|mo| mo.occ).collect(),
+        });
+    }
+    Ok(data)
+}
+
+#[test]
+pub fn test_molden() {
+    let mut cint_data = crate::test_h2_data();
+    let nao = 8;
+    let mo = MolecularOrbitals {
+        nao,
+        mo_coeff: (0..nao*3).map(|i| 0.1*(i as f64).cos()).collect(),
+        mo_energy: vec![-0.6, 0.1, 0.4],
+        mo_occ: vec![2.0, 0.0, 0.0],
+    };
+    let content = cint_data.to_molden_string(std::slice::from_ref(&mo)).unwrap();
+    assert!(content.contains("[5D7F]"));
+    let data = parse_molden(&content).unwrap();
+    assert_eq!(data.atm.len(), 2);
+    assert_eq!(data.bas.len(), 4);
+    assert_eq!(data.orbitals.len(), 1);
+    // the contraction of the test basis is not normalized
+    let (_, ao_norm) = cint_data.normalized_contractions();
+    let read = &data.orbitals[0];
+    assert_eq!(read.mo_energy, mo.mo_energy);
+    for (i, (a, b)) in read.mo_coeff.iter().zip(mo.mo_coeff.iter()).enumerate() {
+        assert!((a - b*ao_norm[i%nao]).abs() < 1.0e-10);
+    }
+    // the same basis functions up to the normalization
+    let env = cint_data.get_env();
+    let bas = cint_data.get_bas();
+    for (ibas, jbas) in bas.iter().zip(data.bas.iter()) {
+        let (pi, pj) = (ibas[PTR_COEFF] as usize, jbas[PTR_COEFF] as usize);
+        let ratio = env[pi]/data.env[pj];
+        for k in 0..ibas[NPRIM_OF] as usize {
+            assert!((env[pi+k] - ratio*data.env[pj+k]).abs() < 1.0e-10);
+        }
+    }
+    // orbitals that do not match the basis are refused
+    let short = MolecularOrbitals {nao: 7, mo_coeff: vec![0.0; 7], mo_energy: vec![0.0], mo_occ: vec![0.0]};
+    assert_eq!(cint_data.to_molden_string(&[short]).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+    // shells not grouped by atom (s of atom 0 and 1, then p of atom 0 and 1): each coefficient
+    // is written against the AO of the same label
+    let atm = cint_data.get_atm();
+    let bas = cint_data.get_bas();
+    let interleaved = vec![bas[0].clone(), bas[2].clone(), bas[1].clone(), bas[3].clone()];
+    let mut mixed = CINTR2CDATA::new();
+    mixed.set_cint_type(&CintType::Spheric);
+    mixed.initial_r2c(&atm, atm.len() as i32, &interleaved, interleaved.len() as i32, &env);
+    let data = parse_molden(&mixed.to_molden_string(std::slice::from_ref(&mo)).unwrap()).unwrap();
+    let mut read_data = data.cint_data();
+    let (_, ao_norm) = mixed.normalized_contractions();
+    let labels = mixed.ao_labels();
+    for (i, label) in read_data.ao_labels().iter().enumerate() {
+        let k = labels.iter().position(|x| x == label).unwrap();
+        for m in 0..3 {
+            assert!((data.orbitals[0].mo_coeff[i + nao*m] - mo.mo_coeff[k + nao*m]*ao_norm[k]).abs() < 1.0e-10, "{}", label);
+        }
+    }
+    read_data.final_c2r();
+    mixed.final_c2r();
+    cint_data.final_c2r();
+}
@@ -0,0 +1,85 @@
+//! `nalgebra` matrices of the AO integrals (feature `nalgebra`).
+//!
+//! The `column-major` buffers of the crate are the storage of `DMatrix<f64>` as they are, so
+//! `dmatrix` and the full-matrix drivers below move the buffers into the matrices without copying,
+//! and `dmatrix_into_vec` gives the buffer back, e.g. for the density matrices of `get_jk`. Only
+//! the components of the `ip` integrals are copied out into separate matrices.
+use std::ops::Range;
+
+use nalgebra::DMatrix;
+
+use crate::CINTR2CDATA;
+use crate::engine::{self, IntegralEngine};
+
+/// The `column-major` buffer `buf` of `nrow x ncol` as a matrix, without copying.
+pub fn dmatrix(buf: Vec<f64>, nrow: usize, ncol: usize) -> DMatrix<f64> {
+    if buf.len() != nrow*ncol {
+        panic!("Error:: the buffer of {} elements does not match the {}x{} matrix", buf.len(), nrow, ncol)
+    }
+    DMatrix::from_vec(nrow, ncol, buf)
+}
+
+/// The `column-major` buffer of `mat`, without copying.
+pub fn dmatrix_into_vec(mat: DMatrix<f64>) -> Vec<f64> {
+    mat.data.into()
+}
+
+/// The one-electron integrals `intor` between `i_shls` and `j_shls` of any engine, one `[ni, nj]`
+/// matrix per component.
+pub fn int1e_dmatrices<E: IntegralEngine>(engine: &mut E, intor: &str, i_shls: Range<usize>, j_shls: Range<usize>) -> Vec<DMatrix<f64>> {
+    let ao_loc = engine.ao_loc();
+    let ni = ao_loc[i_shls.end] - ao_loc[i_shls.start];
+    let nj = ao_loc[j_shls.end] - ao_loc[j_shls.start];
+    let ncomp = engine.ncomp(intor);
+    let buf = engine::int1e_matrix(engine, intor, i_shls, j_shls);
+    if ncomp == 1 {return vec![dmatrix(buf, ni, nj)]}
+    // an empty range gives no chunks to split
+    if ni*nj == 0 {return vec![DMatrix::zeros(ni, nj); ncomp]}
+    buf.chunks_exact(ni*nj).map(|comp| DMatrix::from_column_slice(ni, nj, comp)).collect()
+}
+
+impl CINTR2CDATA {
+    /// `int1e_matrix` as a `[ni, nj]` matrix
+    pub fn int1e_matrix_dmatrix(&mut self, op_name: &str, i_shls: Range<i32>, j_shls: Range<i32>) -> DMatrix<f64> {
+        let ao_loc = self.ao_loc();
+        let ni = ao_loc[i_shls.end as usize] - ao_loc[i_shls.start as usize];
+        let nj = ao_loc[j_shls.end as usize] - ao_loc[j_shls.start as usize];
+        dmatrix(self.int1e_matrix(op_name, i_shls, j_shls), ni, nj)
+    }
+    /// `int2c2e_metric` as a `[naux, naux]` matrix
+    pub fn int2c2e_metric_dmatrix(&mut self, aux_shls: Range<i32>) -> DMatrix<f64> {
+        let ao_loc = self.ao_loc();
+        let naux = ao_loc[aux_shls.end as usize] - ao_loc[aux_shls.start as usize];
+        dmatrix(self.int2c2e_metric(aux_shls), naux, naux)
+    }
+}
+
+#[test]
+pub fn test_nalgebra() {
+    let mut cint_data = crate::test_h2_data();
+    let buf = cint_data.int1e_matrix("ovlp", 0..4, 1..3);
+    let ptr = buf.as_ptr();
+    let ovlp = dmatrix(buf.clone(), 8, 4);
+    assert_eq!(ovlp[(5,3)], buf[5 + 8*3]);
+    // the buffer is moved in and out without copying
+    let moved = dmatrix(buf, 8, 4);
+    assert_eq!(moved.as_slice().as_ptr(), ptr);
+    let back = dmatrix_into_vec(moved);
+    assert_eq!(back.as_ptr(), ptr);
+    assert_eq!(cint_data.int1e_matrix_dmatrix("ovlp", 0..4, 1..3), ovlp);
+
+    // the components of the ip integrals, against the buffer of the generic driver
+    let nao = cint_data.ao_loc()[4];
+    let ipovlp = int1e_dmatrices(&mut cint_data, "int1e_ipovlp", 0..4, 0..4);
+    assert_eq!(ipovlp.len(), 3);
+    assert_eq!(ipovlp[2].shape(), (nao, nao));
+    let buf = engine::int1e_matrix(&mut cint_data, "int1e_ipovlp", 0..4, 0..4);
+    assert_eq!(ipovlp[1][(6,2)], buf[6 + nao*2 + nao*nao]);
+    let metric = cint_data.int2c2e_metric_dmatrix(0..4);
+    assert_eq!(metric.shape(), (nao, nao));
+    assert!((metric.transpose() - &metric).amax() < 1.0e-12);
+    // an empty range gives empty matrices, one per component
+    let empty = int1e_dmatrices(&mut cint_data, "int1e_ipovlp", 2..2, 0..4);
+    assert_eq!(empty.len(), 3);
+    assert_eq!(empty[0].shape(), (0, nao));
+}
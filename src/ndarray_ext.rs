@@ -0,0 +1,130 @@
+//! `ndarray` views of the integrals (feature `ndarray`).
+//!
+//! The `column-major` buffers of the crate are wrapped without copying as arrays in the Fortran
+//! order, i.e. `a[[p, q]] = buf[p + ni*q]`, with the components of the `ip` integrals as the last
+//! axis, e.g. `[di, dj, 3]` for `cint_ip_ij_array` and `[di, dj, dk, 3]` for `cint_ip_3c2e_array`.
+use std::ops::Range;
+
+use ndarray::{Array, Array2, Array3, Array4, ArrayD, IxDyn, ShapeBuilder};
+
+use crate::CINTR2CDATA;
+use crate::engine::{self, IntegralEngine};
+
+/// the `column-major` buffer `buf` as an array of `shape` in the Fortran order
+fn fortran<Sh: ShapeBuilder>(buf: Vec<f64>, shape: Sh) -> Array<f64, Sh::Dim> {
+    Array::from_shape_vec(shape.f(), buf)
+        .unwrap_or_else(|err| panic!("Error:: the buffer does not match the shape of the array: {}", err))
+}
+
+/// The `column-major` buffer `buf` as an array of `shape` in the Fortran order.
+pub fn fortran_array(buf: Vec<f64>, shape: &[usize]) -> ArrayD<f64> {
+    fortran(buf, IxDyn(shape))
+}
+
+/// The block of `intor` for `shls` of any engine, `[di, dj, ...]` with the component axis last if `ncomp > 1`.
+pub fn block_array<E: IntegralEngine>(engine: &E, intor: &str, shls: &[usize]) -> ArrayD<f64> {
+    let mut shape: Vec<usize> = shls.iter().map(|i| engine.shell_dim(*i)).collect();
+    let ncomp = engine.ncomp(intor);
+    if ncomp > 1 {shape.push(ncomp)}
+    fortran_array(engine.eval(intor, shls), &shape)
+}
+
+/// The one-electron integrals `intor` between `i_shls` and `j_shls` of any engine, `[ni, nj, ncomp]`.
+pub fn int1e_array<E: IntegralEngine>(engine: &mut E, intor: &str, i_shls: Range<usize>, j_shls: Range<usize>) -> Array3<f64> {
+    let ao_loc = engine.ao_loc();
+    let ni = ao_loc[i_shls.end] - ao_loc[i_shls.start];
+    let nj = ao_loc[j_shls.end] - ao_loc[j_shls.start];
+    let ncomp = engine.ncomp(intor);
+    fortran(engine::int1e_matrix(engine, intor, i_shls, j_shls), (ni, nj, ncomp))
+}
+
+impl CINTR2CDATA {
+    fn shell_dims(&self, shls: &[i32]) -> Vec<usize> {
+        shls.iter().map(|i| self.cint_cgto_rust(*i) as usize).collect()
+    }
+    /// `cint_ij` as `[di, dj]`
+    pub fn cint_ij_array(&mut self, i:i32,j:i32,op_name: &str) -> Array2<f64> {
+        let d = self.shell_dims(&[i,j]);
+        fortran(self.cint_ij(i,j,op_name), (d[0], d[1]))
+    }
+    /// `cint_ip_ij` as `[di, dj, 3]`
+    pub fn cint_ip_ij_array(&mut self, i:i32,j:i32,op_name: &str) -> Array3<f64> {
+        let d = self.shell_dims(&[i,j]);
+        fortran(self.cint_ip_ij(i,j,op_name), (d[0], d[1], 3))
+    }
+    /// `cint_2c2e` as `[di, dj]`
+    pub fn cint_2c2e_array(&mut self, i:i32,j:i32) -> Array2<f64> {
+        let d = self.shell_dims(&[i,j]);
+        fortran(self.cint_2c2e(i,j), (d[0], d[1]))
+    }
+    /// `cint_ip_2c2e` as `[di, dj, 3]`
+    pub fn cint_ip_2c2e_array(&mut self, i:i32,j:i32) -> Array3<f64> {
+        let d = self.shell_dims(&[i,j]);
+        fortran(self.cint_ip_2c2e(i,j), (d[0], d[1], 3))
+    }
+    /// `cint_3c2e` as `[di, dj, dk]`
+    pub fn cint_3c2e_array(&mut self, i:i32,j:i32,k:i32) -> Array3<f64> {
+        let d = self.shell_dims(&[i,j,k]);
+        fortran(self.cint_3c2e(i,j,k), (d[0], d[1], d[2]))
+    }
+    /// `cint_ip_3c2e` as `[di, dj, dk, 3]`
+    pub fn cint_ip_3c2e_array(&mut self, i:i32,j:i32,k:i32,op_name: &str) -> Array4<f64> {
+        let d = self.shell_dims(&[i,j,k]);
+        fortran(self.cint_ip_3c2e(i,j,k,op_name), (d[0], d[1], d[2], 3))
+    }
+    /// `cint_ijkl_by_shell` as `[di, dj, dk, dl]`
+    pub fn cint_ijkl_array(&mut self, i:i32,j:i32,k:i32,l:i32) -> Array4<f64> {
+        let d = self.shell_dims(&[i,j,k,l]);
+        fortran(self.cint_ijkl_by_shell(i,j,k,l), (d[0], d[1], d[2], d[3]))
+    }
+    /// `int1e_matrix` as `[ni, nj]`
+    pub fn int1e_matrix_array(&mut self, op_name: &str, i_shls: Range<i32>, j_shls: Range<i32>) -> Array2<f64> {
+        let ao_loc = self.ao_loc();
+        let ni = ao_loc[i_shls.end as usize] - ao_loc[i_shls.start as usize];
+        let nj = ao_loc[j_shls.end as usize] - ao_loc[j_shls.start as usize];
+        fortran(self.int1e_matrix(op_name, i_shls, j_shls), (ni, nj))
+    }
+    /// `int2c2e_metric` as `[naux, naux]`
+    pub fn int2c2e_metric_array(&mut self, aux_shls: Range<i32>) -> Array2<f64> {
+        let ao_loc = self.ao_loc();
+        let naux = ao_loc[aux_shls.end as usize] - ao_loc[aux_shls.start as usize];
+        fortran(self.int2c2e_metric(aux_shls), (naux, naux))
+    }
+}
+
+#[test]
+pub fn test_ndarray() {
+    let mut cint_data = crate::test_h2_data();
+    // a p shell and the contracted s shell
+    cint_data.cint_ij_optimizer_rust("ovlp");
+    let buf = cint_data.cint_ij(1,2,"ovlp");
+    let ovlp = cint_data.cint_ij_array(1,2,"ovlp");
+    assert_eq!(ovlp.shape(), &[3,1]);
+    assert_eq!(ovlp.strides(), &[1,3]);
+    assert_eq!(ovlp[[2,0]], buf[2]);
+    cint_data.int1e_ipovlp_optimizer_rust();
+    let buf = cint_data.cint_ip_ij(1,3,"ipovlp");
+    let ip = cint_data.cint_ip_ij_array(1,3,"ipovlp");
+    assert_eq!(ip.shape(), &[3,3,3]);
+    assert_eq!(ip[[2,1,0]], buf[2 + 3]);
+    assert_eq!(ip[[0,2,1]], buf[3*2 + 9]);
+    cint_data.cint3c2e_ip1_optimizer_rust();
+    let buf = cint_data.cint_ip_3c2e(3,1,0,"ip1");
+    let ip = cint_data.cint_ip_3c2e_array(3,1,0,"ip1");
+    assert_eq!(ip.shape(), &[3,3,1,3]);
+    assert_eq!(ip[[1,2,0,2]], buf[1 + 3*2 + 9*2]);
+    cint_data.cint2e_optimizer_rust();
+    let buf = cint_data.cint_ijkl_by_shell(1,3,0,1);
+    let eri = cint_data.cint_ijkl_array(1,3,0,1);
+    assert_eq!(eri[[2,0,0,1]], buf[2 + 9]);
+
+    // the full matrices, against the generic driver with the component axis
+    let ovlp = cint_data.int1e_matrix_array("ovlp", 0..4, 1..3);
+    assert_eq!(ovlp.shape(), &[8,4]);
+    let ovlp_generic = int1e_array(&mut cint_data, "int1e_ovlp", 0..4, 1..3);
+    assert_eq!(ovlp_generic.shape(), &[8,4,1]);
+    assert_eq!(ovlp[[5,3]], ovlp_generic[[5,3,0]]);
+    let block = block_array(&cint_data, "int1e_ipovlp", &[1,3]);
+    assert_eq!(block.shape(), &[3,3,3]);
+    cint_data.cint_del_optimizer_rust();
+}
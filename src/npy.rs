@@ -0,0 +1,344 @@
+//! NumPy `.npy` and `.npz` files of the integral arrays, for the comparison with Python (PySCF) reference data.
+//!
+//! The buffers of the crate are `column-major`, so they are written with `fortran_order: True` and the
+//! dimensions unchanged, e.g. `cint_ip_ij` of `[di, dj, 3]` loads in `numpy` as an array of shape `(di, dj, 3)`.
+//! Arrays saved by `numpy` in C order are read as they are; `NpyArray::to_column_major` gives their
+//! `column-major` buffer for the comparison with the crate.
+//!
+//! Only little-endian (and big-endian on reading) `f8`/`c16` are supported, and `.npz` archives are
+//! written and read uncompressed (`np.savez`, not `np.savez_compressed`).
+use std::io;
+use std::path::Path;
+
+use crate::molden::invalid_data;
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+/// The elements of an `.npy` array, complex numbers as `[re, im]`.
+#[derive(Clone,Debug,PartialEq)]
+pub enum NpyData {
+    Float(Vec<f64>),
+    Complex(Vec<[f64;2]>),
+}
+
+impl NpyData {
+    pub fn len(&self) -> usize {
+        match self {
+            NpyData::Float(data) => data.len(),
+            NpyData::Complex(data) => data.len(),
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// An array of an `.npy` file.
+#[derive(Clone,Debug,PartialEq)]
+pub struct NpyArray {
+    pub shape: Vec<usize>,
+    /// `true` for the `column-major` (Fortran) element order, `false` for the row-major (C) order
+    pub fortran_order: bool,
+    pub data: NpyData,
+}
+
+/// the position in the column-major order of the `idx`-th element in the row-major order of `shape`
+fn c_to_f_index(mut idx: usize, shape: &[usize]) -> usize {
+    let mut f_idx = 0;
+    for (axis, n) in shape.iter().enumerate().rev() {
+        f_idx += (idx % n)*shape[..axis].iter().product::<usize>();
+        idx /= n;
+    }
+    f_idx
+}
+
+impl NpyArray {
+    /// the `column-major` array `data` of the dimensions `shape`
+    pub fn from_f64(data: &[f64], shape: &[usize]) -> NpyArray {
+        if shape.iter().product::<usize>() != data.len() {
+            panic!("Error:: {} elements do not match the shape {:?}", data.len(), shape)
+        }
+        NpyArray {shape: shape.to_vec(), fortran_order: true, data: NpyData::Float(data.to_vec())}
+    }
+
+    /// the `column-major` complex array `data` of the dimensions `shape`
+    pub fn from_complex(data: &[[f64;2]], shape: &[usize]) -> NpyArray {
+        if shape.iter().product::<usize>() != data.len() {
+            panic!("Error:: {} elements do not match the shape {:?}", data.len(), shape)
+        }
+        NpyArray {shape: shape.to_vec(), fortran_order: true, data: NpyData::Complex(data.to_vec())}
+    }
+
+    /// the array in the `column-major` order, reordering the elements of a C-order array
+    pub fn to_column_major(&self) -> NpyArray {
+        if self.fortran_order || self.shape.len() < 2 {
+            return NpyArray {fortran_order: true, ..self.clone()}
+        }
+        let data = match &self.data {
+            NpyData::Float(data) => {
+                let mut out = vec![0.0; data.len()];
+                data.iter().enumerate().for_each(|(i, x)| out[c_to_f_index(i, &self.shape)] = *x);
+                NpyData::Float(out)
+            },
+            NpyData::Complex(data) => {
+                let mut out = vec![[0.0;2]; data.len()];
+                data.iter().enumerate().for_each(|(i, x)| out[c_to_f_index(i, &self.shape)] = *x);
+                NpyData::Complex(out)
+            },
+        };
+        NpyArray {shape: self.shape.clone(), fortran_order: true, data}
+    }
+
+    /// the `column-major` real buffer, an error for a complex array
+    pub fn into_f64(self) -> io::Result<(Vec<f64>, Vec<usize>)> {
+        let array = self.to_column_major();
+        match array.data {
+            NpyData::Float(data) => Ok((data, array.shape)),
+            NpyData::Complex(_) => Err(invalid_data("Error:: a complex array is read as a real one".to_string())),
+        }
+    }
+
+    /// the `column-major` complex buffer; a real array is promoted
+    pub fn into_complex(self) -> (Vec<[f64;2]>, Vec<usize>) {
+        let array = self.to_column_major();
+        match array.data {
+            NpyData::Float(data) => (data.iter().map(|x| [*x, 0.0]).collect(), array.shape),
+            NpyData::Complex(data) => (data, array.shape),
+        }
+    }
+
+    /// the content of the `.npy` file (format version 1.0)
+    pub fn to_npy_bytes(&self) -> Vec<u8> {
+        let descr = match self.data {NpyData::Float(_) => "<f8", NpyData::Complex(_) => "<c16"};
+        let shape = match self.shape.len() {
+            1 => format!("({},)", self.shape[0]),
+            _ => format!("({})", self.shape.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(", ")),
+        };
+        let order = if self.fortran_order {"True"} else {"False"};
+        let mut header = format!("{{'descr': '{}', 'fortran_order': {}, 'shape': {}, }}", descr, order, shape);
+        // the magic, version and length (10 bytes) plus the header padded to a multiple of 64 bytes
+        let total = (10 + header.len() + 1).div_ceil(64)*64;
+        header.push_str(&" ".repeat(total - 10 - header.len() - 1));
+        header.push('\n');
+        let mut bytes = Vec::with_capacity(total + self.data.len()*16);
+        bytes.extend_from_slice(NPY_MAGIC);
+        bytes.extend_from_slice(&[1, 0]);
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        match &self.data {
+            NpyData::Float(data) => data.iter().for_each(|x| bytes.extend_from_slice(&x.to_le_bytes())),
+            NpyData::Complex(data) => data.iter().flatten().for_each(|x| bytes.extend_from_slice(&x.to_le_bytes())),
+        }
+        bytes
+    }
+}
+
+/// the value of `key` in the header dictionary, up to the next top-level comma or the closing brace
+fn header_value<'a>(header: &'a str, key: &str) -> io::Result<&'a str> {
+    let missing = || invalid_data(format!("Error:: '{}' is missing in the npy header {}", key, header));
+    let start = header.find(&format!("'{}'", key)).ok_or_else(missing)? + key.len() + 2;
+    let rest = header[start..].trim_start().strip_prefix(':').ok_or_else(missing)?.trim_start();
+    let end = if rest.starts_with('(') {
+        rest.find(')').map(|i| i + 1)
+    } else {
+        rest.find([',', '}'])
+    }.ok_or_else(missing)?;
+    Ok(rest[..end].trim())
+}
+
+/// Parse the content of an `.npy` file.
+pub fn parse_npy(bytes: &[u8]) -> io::Result<NpyArray> {
+    if bytes.len() < 10 || &bytes[..6] != NPY_MAGIC {
+        return Err(invalid_data("Error:: not an npy file".to_string()))
+    }
+    let (header_len, offset) = match bytes[6] {
+        1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+        2 | 3 if bytes.len() >= 12 => (u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize, 12),
+        v => return Err(invalid_data(format!("Error:: unsupported npy format version {}", v))),
+    };
+    let header = bytes.get(offset..offset + header_len)
+        .and_then(|h| std::str::from_utf8(h).ok())
+        .ok_or_else(|| invalid_data("Error:: truncated npy header".to_string()))?;
+    let descr = header_value(header, "descr")?.trim_matches(|c| c == '\'' || c == '"');
+    let fortran_order = match header_value(header, "fortran_order")? {
+        "True" => true,
+        "False" => false,
+        other => return Err(invalid_data(format!("Error:: invalid fortran_order {}", other))),
+    };
+    let shape = header_value(header, "shape")?.trim_start_matches('(').trim_end_matches(')')
+        .split(',').map(|s| s.trim()).filter(|s| !s.is_empty())
+        .map(|s| s.trim_end_matches('L').parse::<usize>().map_err(|_| invalid_data(format!("Error:: invalid npy shape in {}", header))))
+        .collect::<io::Result<Vec<usize>>>()?;
+    let size: usize = shape.iter().product();
+    let body = &bytes[offset + header_len..];
+    let (big_endian, complex) = match descr {
+        "<f8" => (false, false),
+        ">f8" => (true, false),
+        "<c16" => (false, true),
+        ">c16" => (true, true),
+        other => return Err(invalid_data(format!("Error:: unsupported npy dtype {}, only f8 and c16 are supported", other))),
+    };
+    let nval = if complex {2*size} else {size};
+    if body.len() < 8*nval {
+        return Err(invalid_data(format!("Error:: truncated npy data, {} bytes for {} values", body.len(), nval)))
+    }
+    let values: Vec<f64> = body[..8*nval].chunks_exact(8).map(|b| {
+        let b: [u8;8] = b.try_into().unwrap();
+        if big_endian {f64::from_be_bytes(b)} else {f64::from_le_bytes(b)}
+    }).collect();
+    let data = if complex {
+        NpyData::Complex(values.chunks_exact(2).map(|c| [c[0], c[1]]).collect())
+    } else {
+        NpyData::Float(values)
+    };
+    Ok(NpyArray {shape, fortran_order, data})
+}
+
+pub fn read_npy<P: AsRef<Path>>(path: P) -> io::Result<NpyArray> {
+    parse_npy(&std::fs::read(path)?)
+}
+
+pub fn write_npy<P: AsRef<Path>>(path: P, array: &NpyArray) -> io::Result<()> {
+    std::fs::write(path, array.to_npy_bytes())
+}
+
+/// Write the `column-major` array `data` of the dimensions `shape`, e.g. the buffer of `cint_ij`.
+pub fn write_npy_f64<P: AsRef<Path>>(path: P, data: &[f64], shape: &[usize]) -> io::Result<()> {
+    write_npy(path, &NpyArray::from_f64(data, shape))
+}
+
+/// Read an `.npy` array of `f8` as a `column-major` buffer and its dimensions.
+pub fn read_npy_f64<P: AsRef<Path>>(path: P) -> io::Result<(Vec<f64>, Vec<usize>)> {
+    read_npy(path)?.into_f64()
+}
+
+/// the CRC-32 (IEEE) checksum of the zip entries
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for b in bytes {
+        crc ^= *b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {(crc >> 1) ^ 0xedb8_8320} else {crc >> 1};
+        }
+    }
+    !crc
+}
+
+/// the content of an uncompressed `.npz` archive of the arrays `(name, array)`, stored as `name.npy`
+pub fn to_npz_bytes(arrays: &[(&str, &NpyArray)]) -> io::Result<Vec<u8>> {
+    let mut bytes: Vec<u8> = vec![];
+    let mut central: Vec<u8> = vec![];
+    for (name, array) in arrays {
+        let file_name = format!("{}.npy", name);
+        let data = array.to_npy_bytes();
+        let (offset, size) = (bytes.len(), data.len());
+        if offset > u32::MAX as usize || size > u32::MAX as usize {
+            return Err(invalid_data(format!("Error:: {} exceeds the 4 GiB limit of the npz writer", file_name)))
+        }
+        let crc = crc32(&data);
+        // version 2.0, no flags, stored, no time stamp
+        let common = [
+            &20u16.to_le_bytes()[..], &0u16.to_le_bytes(), &0u16.to_le_bytes(), &0u16.to_le_bytes(), &0x21u16.to_le_bytes(),
+            &crc.to_le_bytes(), &(size as u32).to_le_bytes(), &(size as u32).to_le_bytes(),
+            &(file_name.len() as u16).to_le_bytes(), &0u16.to_le_bytes(),
+        ].concat();
+        bytes.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        bytes.extend_from_slice(&common);
+        bytes.extend_from_slice(file_name.as_bytes());
+        bytes.extend_from_slice(&data);
+        central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes());
+        central.extend_from_slice(&common);
+        // comment length, disk number, internal and external attributes
+        central.extend_from_slice(&[0u8; 10]);
+        central.extend_from_slice(&(offset as u32).to_le_bytes());
+        central.extend_from_slice(file_name.as_bytes());
+    }
+    let central_offset = bytes.len();
+    if central_offset > u32::MAX as usize {
+        return Err(invalid_data("Error:: the npz archive exceeds the 4 GiB limit of the npz writer".to_string()))
+    }
+    bytes.extend_from_slice(&central);
+    bytes.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    bytes.extend_from_slice(&[0u8; 4]);
+    bytes.extend_from_slice(&(arrays.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(&(arrays.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&(central_offset as u32).to_le_bytes());
+    bytes.extend_from_slice(&0u16.to_le_bytes());
+    Ok(bytes)
+}
+
+fn read_u16(bytes: &[u8], pos: usize) -> io::Result<usize> {
+    bytes.get(pos..pos+2).map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+        .ok_or_else(|| invalid_data("Error:: truncated npz archive".to_string()))
+}
+
+fn read_u32(bytes: &[u8], pos: usize) -> io::Result<usize> {
+    bytes.get(pos..pos+4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+        .ok_or_else(|| invalid_data("Error:: truncated npz archive".to_string()))
+}
+
+/// Parse an uncompressed `.npz` archive into the arrays `(name, array)` in the order of the archive.
+pub fn parse_npz(bytes: &[u8]) -> io::Result<Vec<(String, NpyArray)>> {
+    // the end of central directory record, followed by a comment of at most 65535 bytes
+    let eocd = (0..bytes.len().saturating_sub(21)).rev().take(65536 + 22)
+        .find(|i| bytes[*i..*i+4] == 0x0605_4b50u32.to_le_bytes())
+        .ok_or_else(|| invalid_data("Error:: not an npz (zip) archive".to_string()))?;
+    let nentry = read_u16(bytes, eocd + 10)?;
+    let mut pos = read_u32(bytes, eocd + 16)?;
+    let mut arrays = vec![];
+    for _ in 0..nentry {
+        if read_u32(bytes, pos)? != 0x0201_4b50 {
+            return Err(invalid_data("Error:: corrupted central directory of the npz archive".to_string()))
+        }
+        let method = read_u16(bytes, pos + 10)?;
+        let size = read_u32(bytes, pos + 20)?;
+        let name_len = read_u16(bytes, pos + 28)?;
+        let extra_len = read_u16(bytes, pos + 30)?;
+        let comment_len = read_u16(bytes, pos + 32)?;
+        let local = read_u32(bytes, pos + 42)?;
+        let name = bytes.get(pos + 46..pos + 46 + name_len).map(|n| String::from_utf8_lossy(n).to_string())
+            .ok_or_else(|| invalid_data("Error:: truncated npz archive".to_string()))?;
+        pos += 46 + name_len + extra_len + comment_len;
+        if method != 0 {
+            return Err(invalid_data(format!("Error:: {} is compressed, only uncompressed npz archives (np.savez) are supported", name)))
+        }
+        if size == u32::MAX as usize {
+            return Err(invalid_data(format!("Error:: {} is a ZIP64 entry, which is not supported", name)))
+        }
+        let data_start = local + 30 + read_u16(bytes, local + 26)? + read_u16(bytes, local + 28)?;
+        let data = bytes.get(data_start..data_start + size)
+            .ok_or_else(|| invalid_data(format!("Error:: truncated entry {} of the npz archive", name)))?;
+        let key = name.strip_suffix(".npy").unwrap_or(&name).to_string();
+        arrays.push((key, parse_npy(data)?));
+    }
+    Ok(arrays)
+}
+
+pub fn write_npz<P: AsRef<Path>>(path: P, arrays: &[(&str, &NpyArray)]) -> io::Result<()> {
+    std::fs::write(path, to_npz_bytes(arrays)?)
+}
+
+pub fn read_npz<P: AsRef<Path>>(path: P) -> io::Result<Vec<(String, NpyArray)>> {
+    parse_npz(&std::fs::read(path)?)
+}
+
+#[test]
+pub fn test_npy() {
+    let real = NpyArray::from_f64(&(0..24).map(|i| i as f64 - 0.25).collect::<Vec<f64>>(), &[2, 3, 4]);
+    let cplx = NpyArray::from_complex(&[[1.0, -1.0], [0.5, 2.0], [-3.0, 0.0]], &[3]);
+    let bytes = real.to_npy_bytes();
+    assert_eq!(bytes.len(), 128 + 24*8);
+    assert!(std::str::from_utf8(&bytes[10..128]).unwrap().starts_with("{'descr': '<f8', 'fortran_order': True, 'shape': (2, 3, 4), }"));
+    assert_eq!(parse_npy(&bytes).unwrap(), real);
+    assert_eq!(parse_npy(&cplx.to_npy_bytes()).unwrap(), cplx);
+    // the C-order [[1,2,3],[4,5,6]] of numpy
+    let c_order = NpyArray {shape: vec![2, 3], fortran_order: false, data: NpyData::Float(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0])};
+    let (mat, shape) = parse_npy(&c_order.to_npy_bytes()).unwrap().into_f64().unwrap();
+    assert_eq!((mat, shape), (vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0], vec![2, 3]));
+    let npz = to_npz_bytes(&[("int1e_ovlp", &real), ("mo_coeff", &cplx)]).unwrap();
+    let arrays = parse_npz(&npz).unwrap();
+    assert_eq!(arrays, vec![("int1e_ovlp".to_string(), real), ("mo_coeff".to_string(), cplx)]);
+    assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+}
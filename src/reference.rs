@@ -0,0 +1,760 @@
+//! A pure-Rust reference backend of the basic Gaussian integrals.
+//!
+//! `ReferenceEngine` evaluates the overlap, kinetic, nuclear attraction and `rinv` integrals, the
+//! 2c2e, 3c2e and 4c2e Coulomb integrals and their `ip` derivatives for spheric and Cartesian shells
+//! of any angular momentum. It takes the same `atm`/`bas`/`env` as libcint and reproduces its
+//! normalization (`CINTcommon_fac_sp` for the s and p shells, `cart2sph_coeff` for the spheric ones)
+//! and its `column-major` blocks of `[di, dj, ..., ncomp]`. It is slow but exact to the precision of
+//! the Boys function, and serves to cross-validate libcint and as the backend of the crate built
+//! without the `libcint` feature.
+//!
+//! The primitive integrals follow McMurchie and Davidson: the products of Cartesian Gaussians are
+//! expanded in Hermite Gaussians (`E^{ij}_t`) and the Coulomb operators are evaluated by the Hermite
+//! integrals `R_{tuv}`. The `ip` integrals differentiate the Cartesian components,
+//! `d/dx x^l e^{-a x^2} = l x^{l-1} e^{-a x^2} - 2a x^{l+1} e^{-a x^2}`. The nuclei are point charges,
+//! and the range-separated Coulomb operator (`PTR_RANGE_OMEGA` of `env`) is not supported.
+use std::f64::consts::PI;
+use std::os::raw::c_int;
+
+use crate::{CINTR2CDATA, CintType};
+use crate::cart2sph::{cart2sph_coeff, cart_index, ncart};
+use crate::engine::IntegralEngine;
+use crate::rys::boys_into;
+
+// slots of atm
+const CHARGE_OF: usize = 0;
+const PTR_COORD: usize = 1;
+const PTR_FRAC_CHARGE: usize = 4;
+const ATM_SLOTS: usize = 6;
+// slots of bas
+const ATOM_OF: usize = 0;
+const ANG_OF: usize = 1;
+const NPRIM_OF: usize = 2;
+const NCTR_OF: usize = 3;
+const PTR_EXP: usize = 5;
+const PTR_COEFF: usize = 6;
+const BAS_SLOTS: usize = 8;
+// the origin of the rinv operator in env
+const PTR_RINV_ORIG: usize = 4;
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+enum Operator {
+    Overlap,
+    Kinetic,
+    Nuclear,
+    Rinv,
+    Coulomb,
+}
+
+#[derive(Clone,Copy,Debug)]
+struct Intor {
+    name: &'static str,
+    op: Operator,
+    ncenter: usize,
+    /// the shell differentiated by `ip`, if any
+    nabla: Option<usize>,
+}
+
+const fn intor(name: &'static str, op: Operator, ncenter: usize, nabla: Option<usize>) -> Intor {
+    Intor {name, op, ncenter, nabla}
+}
+
+/// the integrals of the reference backend, named as in the libcint catalog
+const INTEGRALS: &[Intor] = &[
+    intor("int1e_ovlp", Operator::Overlap, 2, None),
+    intor("int1e_kin", Operator::Kinetic, 2, None),
+    intor("int1e_nuc", Operator::Nuclear, 2, None),
+    intor("int1e_rinv", Operator::Rinv, 2, None),
+    intor("int1e_ipovlp", Operator::Overlap, 2, Some(0)),
+    intor("int1e_ipkin", Operator::Kinetic, 2, Some(0)),
+    intor("int1e_ipnuc", Operator::Nuclear, 2, Some(0)),
+    intor("int1e_iprinv", Operator::Rinv, 2, Some(0)),
+    intor("int2c2e", Operator::Coulomb, 2, None),
+    intor("int2c2e_ip1", Operator::Coulomb, 2, Some(0)),
+    intor("int2c2e_ip2", Operator::Coulomb, 2, Some(1)),
+    intor("int3c2e", Operator::Coulomb, 3, None),
+    intor("int3c2e_ip1", Operator::Coulomb, 3, Some(0)),
+    intor("int3c2e_ip2", Operator::Coulomb, 3, Some(2)),
+    intor("int2e", Operator::Coulomb, 4, None),
+    intor("int2e_ip1", Operator::Coulomb, 4, Some(0)),
+    intor("int2e_ip2", Operator::Coulomb, 4, Some(2)),
+];
+
+fn find_intor(name: &str) -> &'static Intor {
+    INTEGRALS.iter().find(|intor| intor.name == name)
+        .unwrap_or_else(|| panic!("Error:: {} is not provided by the reference integral engine", name))
+}
+
+/// the normalization of the radial part `r^l e^{-a r^2}`, as `CINTgto_norm`
+pub(crate) fn gto_norm(l: usize, a: f64) -> f64 {
+    // \int r^{2l+2} e^{-2a r^2} dr = (2l+1)!! sqrt(pi) / (2^{l+2} (2a)^{l+3/2})
+    let double_factorial: f64 = (1..=2*l+1).step_by(2).map(|i| i as f64).product();
+    let integral = double_factorial*PI.sqrt()/(2.0_f64.powi(l as i32 + 2)*(2.0*a).powf(l as f64 + 1.5));
+    1.0/integral.sqrt()
+}
+
+/// the powers `[lx, ly, lz]` of the Cartesian components of `l` in the order of `cart_index`
+fn cart_powers(l: usize) -> Vec<[usize;3]> {
+    (0..=l).rev().flat_map(|lx| (0..=l-lx).rev().map(move |ly| [lx, ly, l-lx-ly])).collect()
+}
+
+/// The Hermite expansion coefficients `E^{ij}_t` of `x_A^i x_B^j e^{-a x_A^2 - b x_B^2}` in one dimension.
+struct HermiteE {
+    la: usize,
+    nt: usize,
+    e: Vec<f64>,
+}
+
+impl HermiteE {
+    fn new(la: usize, lb: usize, a: f64, b: f64, xa: f64, xb: f64) -> HermiteE {
+        let p = a + b;
+        let xp = (a*xa + b*xb)/p;
+        let nt = la + lb + 1;
+        let mut h = HermiteE {la, nt, e: vec![0.0; nt*(la+1)*(lb+1)]};
+        h.e[0] = (-a*b/p*(xa - xb).powi(2)).exp();
+        for j in 0..=lb {
+            for i in 0..=la {
+                if i == 0 && j == 0 {continue}
+                // raise i from (i-1, j), or j from (0, j-1)
+                let (prev, x) = if i > 0 {(h.index(i-1, j), xp - xa)} else {(h.index(0, j-1), xp - xb)};
+                let cur = h.index(i, j);
+                for t in 0..=i+j {
+                    let mut v = x*h.e[prev+t];
+                    if t > 0 {v += h.e[prev+t-1]/(2.0*p)}
+                    if t+1 < i+j {v += (t+1) as f64*h.e[prev+t+1]}
+                    h.e[cur+t] = v;
+                }
+            }
+        }
+        h
+    }
+    fn index(&self, i: usize, j: usize) -> usize {
+        self.nt*(i + (self.la+1)*j)
+    }
+    fn get(&self, i: usize, j: usize, t: usize) -> f64 {
+        if t > i+j {0.0} else {self.e[self.index(i, j) + t]}
+    }
+}
+
+/// The Hermite Coulomb integrals `R_{tuv}(p, pc)` for `t+u+v <= lmax`, indexed `t + n*(u + n*v)` with `n = lmax+1`.
+fn hermite_r(lmax: usize, p: f64, pc: [f64;3]) -> Vec<f64> {
+    let n = lmax + 1;
+    let idx = |t: usize, u: usize, v: usize| t + n*(u + n*v);
+    let mut f = vec![0.0; n];
+    boys_into(p*(pc[0]*pc[0] + pc[1]*pc[1] + pc[2]*pc[2]), &mut f);
+    // R^{m+1} in `r`, R^m in `next`
+    let mut r = vec![0.0; n*n*n];
+    let mut next = vec![0.0; n*n*n];
+    for m in (0..=lmax).rev() {
+        let l = lmax - m;
+        for v in 0..=l {
+            for u in 0..=l-v {
+                for t in 0..=l-u-v {
+                    next[idx(t,u,v)] = if t > 0 {
+                        pc[0]*r[idx(t-1,u,v)] + if t > 1 {(t-1) as f64*r[idx(t-2,u,v)]} else {0.0}
+                    } else if u > 0 {
+                        pc[1]*r[idx(t,u-1,v)] + if u > 1 {(u-1) as f64*r[idx(t,u-2,v)]} else {0.0}
+                    } else if v > 0 {
+                        pc[2]*r[idx(t,u,v-1)] + if v > 1 {(v-1) as f64*r[idx(t,u,v-2)]} else {0.0}
+                    } else {
+                        (-2.0*p).powi(m as i32)*f[m]
+                    };
+                }
+            }
+        }
+        std::mem::swap(&mut r, &mut next);
+    }
+    r
+}
+
+#[derive(Clone,Copy,Debug)]
+struct Primitive {
+    l: usize,
+    center: [f64;3],
+    exp: f64,
+}
+
+/// the product center and the sum of the exponents of two primitives
+fn gaussian_product(a: &Primitive, b: &Primitive) -> ([f64;3], f64) {
+    let p = a.exp + b.exp;
+    let center = [0,1,2].map(|d| (a.exp*a.center[d] + b.exp*b.center[d])/p);
+    (center, p)
+}
+
+/// The one-electron integrals over the Cartesian components of `a` and `b`, `[ncart(la), ncart(lb)]`,
+/// with the Coulomb potential of the point charges `potential` for `Nuclear` and `Rinv`.
+fn one_electron(op: Operator, a: &Primitive, b: &Primitive, potential: &[(f64,[f64;3])]) -> Vec<f64> {
+    let lb = if op == Operator::Kinetic {b.l + 2} else {b.l};
+    let e: Vec<HermiteE> = (0..3).map(|d| HermiteE::new(a.l, lb, a.exp, b.exp, a.center[d], b.center[d])).collect();
+    let (center, p) = gaussian_product(a, b);
+    let s1d = |d: usize, i: usize, j: usize| e[d].get(i, j, 0)*(PI/p).sqrt();
+    let n = a.l + b.l + 1;
+    let rs: Vec<(f64, Vec<f64>)> = potential.iter()
+        .map(|(charge, c)| (*charge, hermite_r(a.l + b.l, p, [0,1,2].map(|d| center[d] - c[d])))).collect();
+    let (pa, pb) = (cart_powers(a.l), cart_powers(b.l));
+    let mut out = vec![0.0; pa.len()*pb.len()];
+    for (jb, j) in pb.iter().enumerate() {
+        for (ia, i) in pa.iter().enumerate() {
+            out[ia + pa.len()*jb] = match op {
+                Operator::Overlap => (0..3).map(|d| s1d(d, i[d], j[d])).product(),
+                Operator::Kinetic => {
+                    // -1/2 d^2/dx^2 acting on x_B^j e^{-b x_B^2}
+                    let t1d = |d: usize| {
+                        let (id, jd) = (i[d], j[d]);
+                        let mut t = -2.0*b.exp*b.exp*s1d(d, id, jd+2) + b.exp*(2*jd+1) as f64*s1d(d, id, jd);
+                        if jd >= 2 {t -= 0.5*(jd*(jd-1)) as f64*s1d(d, id, jd-2)}
+                        t
+                    };
+                    let s = [0,1,2].map(|d| s1d(d, i[d], j[d]));
+                    t1d(0)*s[1]*s[2] + s[0]*t1d(1)*s[2] + s[0]*s[1]*t1d(2)
+                },
+                Operator::Nuclear | Operator::Rinv => 2.0*PI/p*rs.iter().map(|(charge, r)| {
+                    let mut sum = 0.0;
+                    for v in 0..=i[2]+j[2] {
+                        for u in 0..=i[1]+j[1] {
+                            for t in 0..=i[0]+j[0] {
+                                sum += e[0].get(i[0],j[0],t)*e[1].get(i[1],j[1],u)*e[2].get(i[2],j[2],v)*r[t + n*(u + n*v)];
+                            }
+                        }
+                    }
+                    charge*sum
+                }).sum::<f64>(),
+                Operator::Coulomb => unreachable!(),
+            };
+        }
+    }
+    out
+}
+
+/// The Hermite expansion of the products of the Cartesian components of a pair of primitives,
+/// with the non-vanishing `([t, u, v], E_tuv)` of each component pair `i + ncart(la)*j`.
+struct PairExpansion {
+    p: f64,
+    center: [f64;3],
+    l: usize,
+    terms: Vec<Vec<([usize;3], f64)>>,
+}
+
+impl PairExpansion {
+    fn new(a: &Primitive, b: &Primitive) -> PairExpansion {
+        let e: Vec<HermiteE> = (0..3).map(|d| HermiteE::new(a.l, b.l, a.exp, b.exp, a.center[d], b.center[d])).collect();
+        let (center, p) = gaussian_product(a, b);
+        let mut terms = vec![];
+        for j in cart_powers(b.l) {
+            for i in cart_powers(a.l) {
+                let mut pair = vec![];
+                for v in 0..=i[2]+j[2] {
+                    for u in 0..=i[1]+j[1] {
+                        for t in 0..=i[0]+j[0] {
+                            let coeff = e[0].get(i[0],j[0],t)*e[1].get(i[1],j[1],u)*e[2].get(i[2],j[2],v);
+                            if coeff != 0.0 {pair.push(([t,u,v], coeff))}
+                        }
+                    }
+                }
+                terms.push(pair);
+            }
+        }
+        PairExpansion {p, center, l: a.l + b.l, terms}
+    }
+}
+
+/// The Coulomb integrals `(ab|cd)` over the Cartesian components of 2, 3 or 4 primitives, with the
+/// missing functions of `(a|c)` and `(ab|c)` being the unit Gaussians, `[ncart(la), ..., ncart(ld)]`.
+fn coulomb(prims: &[Primitive]) -> Vec<f64> {
+    let unit = |p: &Primitive| Primitive {l: 0, center: p.center, exp: 0.0};
+    let (a, b, c, d) = match prims {
+        [a, c] => (*a, unit(a), *c, unit(c)),
+        [a, b, c] => (*a, *b, *c, unit(c)),
+        [a, b, c, d] => (*a, *b, *c, *d),
+        _ => panic!("Error:: the Coulomb integrals take 2, 3 or 4 shells, but {} are given", prims.len()),
+    };
+    let bra = PairExpansion::new(&a, &b);
+    let ket = PairExpansion::new(&c, &d);
+    let (p, q) = (bra.p, ket.p);
+    let l = bra.l + ket.l;
+    let n = l + 1;
+    let r = hermite_r(l, p*q/(p + q), [0,1,2].map(|x| bra.center[x] - ket.center[x]));
+    let prefactor = 2.0*PI.powf(2.5)/(p*q*(p + q).sqrt());
+    let nb = bra.l + 1;
+    let nab = bra.terms.len();
+    let mut out = vec![0.0; nab*ket.terms.len()];
+    let mut w = vec![0.0; nb*nb*nb];
+    for (cd, ket_terms) in ket.terms.iter().enumerate() {
+        // W_tuv = \sum_{t'u'v'} (-1)^{t'+u'+v'} E^{cd}_{t'u'v'} R_{t+t',u+u',v+v'}
+        for v in 0..nb {
+            for u in 0..nb-v {
+                for t in 0..nb-u-v {
+                    w[t + nb*(u + nb*v)] = ket_terms.iter().map(|([tk, uk, vk], coeff)| {
+                        let sign = if (tk + uk + vk) % 2 == 0 {1.0} else {-1.0};
+                        sign*coeff*r[t+tk + n*(u+uk + n*(v+vk))]
+                    }).sum();
+                }
+            }
+        }
+        for (ab, bra_terms) in bra.terms.iter().enumerate() {
+            out[ab + nab*cd] = prefactor*bra_terms.iter().map(|([t, u, v], coeff)| coeff*w[t + nb*(u + nb*v)]).sum::<f64>();
+        }
+    }
+    out
+}
+
+fn prim_cart(op: Operator, prims: &[Primitive], potential: &[(f64,[f64;3])]) -> Vec<f64> {
+    match op {
+        Operator::Coulomb => coulomb(prims),
+        _ => one_electron(op, &prims[0], &prims[1], potential),
+    }
+}
+
+/// The primitive integrals over the Cartesian components of `prims`, `[ncart(l0), ncart(l1), ..., ncomp]`,
+/// with the `ip` derivative of the shell `nabla` as the components (x, y, z).
+fn prim_block(op: Operator, nabla: Option<usize>, prims: &[Primitive], potential: &[(f64,[f64;3])]) -> Vec<f64> {
+    let Some(n) = nabla else {return prim_cart(op, prims, potential)};
+    let l = prims[n].l;
+    let mut shifted = prims.to_vec();
+    shifted[n].l = l + 1;
+    let up = prim_cart(op, &shifted, potential);
+    let down = if l > 0 {
+        shifted[n].l = l - 1;
+        prim_cart(op, &shifted, potential)
+    } else {vec![]};
+    let before: usize = prims[..n].iter().map(|p| ncart(p.l)).product();
+    let after: usize = prims[n+1..].iter().map(|p| ncart(p.l)).product();
+    let (nc, nc_up) = (ncart(l), ncart(l+1));
+    let nc_down = if l > 0 {ncart(l-1)} else {0};
+    let size = before*nc*after;
+    let mut out = vec![0.0; 3*size];
+    for (c, pow) in cart_powers(l).iter().enumerate() {
+        for d in 0..3 {
+            let mut pow_up = *pow;
+            pow_up[d] += 1;
+            let c_up = cart_index(pow_up[0], pow_up[1], pow_up[2]);
+            let c_down = (pow[d] > 0).then(|| {
+                let mut pow_down = *pow;
+                pow_down[d] -= 1;
+                cart_index(pow_down[0], pow_down[1], pow_down[2])
+            });
+            for k in 0..after {
+                for i in 0..before {
+                    let mut v = -2.0*prims[n].exp*up[i + before*(c_up + nc_up*k)];
+                    if let Some(c_down) = c_down {v += pow[d] as f64*down[i + before*(c_down + nc_down*k)]}
+                    out[i + before*(c + nc*k) + size*d] = v;
+                }
+            }
+        }
+    }
+    out
+}
+
+/// A contracted shell, with the angular normalization of the s and p shells absorbed in `coeffs`.
+#[derive(Clone,Debug)]
+struct Shell {
+    l: usize,
+    center: [f64;3],
+    exps: Vec<f64>,
+    /// `[nprim, nctr]`
+    coeffs: Vec<f64>,
+    nctr: usize,
+}
+
+impl Shell {
+    /// the shell `ish` of the flattened `atm`, `bas` and `env` of libcint
+    fn from_cint(ish: usize, atm: &[i32], bas: &[i32], env: &[f64]) -> Shell {
+        let b = &bas[BAS_SLOTS*ish..BAS_SLOTS*(ish+1)];
+        let (l, nprim, nctr) = (b[ANG_OF] as usize, b[NPRIM_OF] as usize, b[NCTR_OF] as usize);
+        let ptr_coord = atm[ATM_SLOTS*b[ATOM_OF] as usize + PTR_COORD] as usize;
+        let (ptr_exp, ptr_coeff) = (b[PTR_EXP] as usize, b[PTR_COEFF] as usize);
+        // CINTcommon_fac_sp
+        let fac = match l {0 => 0.282_094_791_773_878_14, 1 => 0.488_602_511_902_919_9, _ => 1.0};
+        Shell {
+            l,
+            center: [env[ptr_coord], env[ptr_coord+1], env[ptr_coord+2]],
+            exps: env[ptr_exp..ptr_exp+nprim].to_vec(),
+            coeffs: env[ptr_coeff..ptr_coeff+nprim*nctr].iter().map(|c| fac*c).collect(),
+            nctr,
+        }
+    }
+    fn dim(&self, cint_type: CintType) -> usize {
+        match cint_type {
+            CintType::Spheric => (2*self.l+1)*self.nctr,
+            CintType::Cartesian => ncart(self.l)*self.nctr,
+        }
+    }
+}
+
+/// the point charges `(Z, R)` of the nuclei, with the fractional charges of `env` if set
+fn nuclei(atm: &[i32], env: &[f64]) -> Vec<(f64,[f64;3])> {
+    atm.chunks(ATM_SLOTS).map(|a| {
+        let ptr = a[PTR_COORD] as usize;
+        let charge = if a[PTR_FRAC_CHARGE] != 0 {env[a[PTR_FRAC_CHARGE] as usize]} else {a[CHARGE_OF] as f64};
+        (charge, [env[ptr], env[ptr+1], env[ptr+2]])
+    }).collect()
+}
+
+/// the point charges of the operator of `intor`: `-Z` of the nuclei for `Nuclear`, a unit charge for `Rinv`
+fn potential(intor: &Intor, nuclei: &[(f64,[f64;3])], rinv_origin: [f64;3]) -> Vec<(f64,[f64;3])> {
+    match intor.op {
+        Operator::Nuclear => nuclei.iter().map(|(z, r)| (-z, *r)).collect(),
+        Operator::Rinv => vec![(1.0, rinv_origin)],
+        _ => vec![],
+    }
+}
+
+/// Transform the axis `n` of the `column-major` tensor `data` of `dims`, holding the contracted
+/// Cartesian functions of a shell of `l`, to the spheric functions.
+fn cart2sph_axis(data: &[f64], dims: &mut [usize], n: usize, l: usize) -> Vec<f64> {
+    let (nc, ns) = (ncart(l), 2*l+1);
+    let c2s = cart2sph_coeff(l);
+    let before: usize = dims[..n].iter().product();
+    let after: usize = dims[n+1..].iter().product();
+    let nctr = dims[n]/nc;
+    let mut out = vec![0.0; before*ns*nctr*after];
+    for k in 0..after*nctr {
+        for s in 0..ns {
+            let dst = before*(s + ns*k);
+            for c in 0..nc {
+                let coeff = c2s[c + nc*s];
+                if coeff == 0.0 {continue}
+                let src = before*(c + nc*k);
+                (0..before).for_each(|i| out[dst+i] += coeff*data[src+i]);
+            }
+        }
+    }
+    dims[n] = ns*nctr;
+    out
+}
+
+/// The block of `intor` over the contracted `shells`, `[d0, d1, ..., ncomp]`.
+fn eval_block(intor: &Intor, shells: &[&Shell], potential: &[(f64,[f64;3])], cint_type: CintType) -> Vec<f64> {
+    if shells.len() != intor.ncenter {
+        panic!("Error:: {} takes {} shells, but {} are given", intor.name, intor.ncenter, shells.len())
+    }
+    let ncomp = if intor.nabla.is_some() {3} else {1};
+    let ncarts: Vec<usize> = shells.iter().map(|s| ncart(s.l)).collect();
+    let mut dims: Vec<usize> = shells.iter().map(|s| ncart(s.l)*s.nctr).collect();
+    let size: usize = dims.iter().product();
+    let prim_size: usize = ncarts.iter().product();
+    // the position in the contracted block of each element of a primitive block (for the first contractions)
+    let base: Vec<usize> = (0..prim_size*ncomp).map(|f| {
+        let (mut rest, mut stride, mut pos) = (f % prim_size, 1, size*(f/prim_size));
+        ncarts.iter().zip(&dims).for_each(|(nc, d)| {
+            pos += stride*(rest % nc);
+            rest /= nc;
+            stride *= d;
+        });
+        pos
+    }).collect();
+    let multi_index = |mut flat: usize, extents: &[usize]| -> Vec<usize> {
+        extents.iter().map(|n| {let i = flat % n; flat /= n; i}).collect()
+    };
+    // the contractions of all shells and their offsets
+    let nctrs: Vec<usize> = shells.iter().map(|s| s.nctr).collect();
+    let ctrs: Vec<(Vec<usize>, usize)> = (0..nctrs.iter().product()).map(|flat| {
+        let ics = multi_index(flat, &nctrs);
+        let mut stride = 1;
+        let offset = ics.iter().zip(ncarts.iter().zip(&dims)).map(|(ic, (nc, d))| {
+            let offset = stride*nc*ic;
+            stride *= d;
+            offset
+        }).sum();
+        (ics, offset)
+    }).collect();
+    let nprims: Vec<usize> = shells.iter().map(|s| s.exps.len()).collect();
+    let mut out = vec![0.0; size*ncomp];
+    for flat in 0..nprims.iter().product() {
+        let ips = multi_index(flat, &nprims);
+        let prims: Vec<Primitive> = shells.iter().zip(&ips)
+            .map(|(s, ip)| Primitive {l: s.l, center: s.center, exp: s.exps[*ip]}).collect();
+        let block = prim_block(intor.op, intor.nabla, &prims, potential);
+        for (ics, offset) in ctrs.iter() {
+            let coeff: f64 = shells.iter().zip(ics.iter().zip(&ips)).map(|(s, (ic, ip))| s.coeffs[ip + s.exps.len()*ic]).product();
+            if coeff == 0.0 {continue}
+            block.iter().zip(&base).for_each(|(v, pos)| out[pos + offset] += coeff*v);
+        }
+    }
+    if cint_type == CintType::Spheric {
+        dims.push(ncomp);
+        for (n, s) in shells.iter().enumerate() {
+            if s.l > 1 {out = cart2sph_axis(&out, &mut dims, n, s.l)}
+        }
+    }
+    out
+}
+
+/// The pure-Rust reference backend of the basic integrals, with the input and the conventions of libcint.
+///
+/// It implements `IntegralEngine` for "int1e_ovlp", "int1e_kin", "int1e_nuc", "int1e_rinv", "int2c2e",
+/// "int3c2e" and "int2e", and the `ip` integrals "int1e_ipovlp", "int1e_ipkin", "int1e_ipnuc",
+/// "int1e_iprinv", "int2c2e_ip1", "int2c2e_ip2", "int3c2e_ip1", "int3c2e_ip2", "int2e_ip1" and
+/// "int2e_ip2"; the other integrals panic.
+#[derive(Clone,Debug)]
+pub struct ReferenceEngine {
+    shells: Vec<Shell>,
+    /// the point charges `(Z, R)` of the nuclei
+    nuclei: Vec<(f64,[f64;3])>,
+    rinv_origin: [f64;3],
+    cint_type: CintType,
+}
+
+impl ReferenceEngine {
+    /// the engine of the basis given by `atm`, `bas` and `env` in the layout of `CINTR2CDATA::initial_r2c`
+    pub fn new(atm: &[Vec<i32>], bas: &[Vec<i32>], env: &[f64], cint_type: CintType) -> ReferenceEngine {
+        let (atm, bas) = (atm.concat(), bas.concat());
+        ReferenceEngine {
+            shells: (0..bas.len()/BAS_SLOTS).map(|i| Shell::from_cint(i, &atm, &bas, env)).collect(),
+            nuclei: nuclei(&atm, env),
+            rinv_origin: [env[PTR_RINV_ORIG], env[PTR_RINV_ORIG+1], env[PTR_RINV_ORIG+2]],
+            cint_type,
+        }
+    }
+    /// the engine of the basis, the `CintType` and the rinv origin of `cint_data`
+    pub fn from_cint_data(cint_data: &CINTR2CDATA) -> ReferenceEngine {
+        ReferenceEngine::new(&cint_data.get_atm(), &cint_data.get_bas(), &cint_data.get_env(), cint_data.get_cint_type())
+    }
+    pub fn set_cint_type(&mut self, cint_type: &CintType) {
+        self.cint_type = *cint_type;
+    }
+    /// set the origin of the `1/|r-R|` operator of the `rinv` integrals
+    pub fn set_rinv_origin(&mut self, origin: &[f64;3]) {
+        self.rinv_origin = *origin;
+    }
+    /// whether the integral `intor` is provided by the reference backend
+    pub fn supports(intor: &str) -> bool {
+        INTEGRALS.iter().any(|i| i.name == intor)
+    }
+}
+
+impl IntegralEngine for ReferenceEngine {
+    type Workspace = ();
+
+    fn nbas(&self) -> usize {
+        self.shells.len()
+    }
+    fn cint_type(&self) -> CintType {
+        self.cint_type
+    }
+    fn shell_dim(&self, ish: usize) -> usize {
+        self.shells[ish].dim(self.cint_type)
+    }
+    fn ncomp(&self, intor: &str) -> usize {
+        if find_intor(intor).nabla.is_some() {3} else {1}
+    }
+    fn ncenter(&self, intor: &str) -> usize {
+        find_intor(intor).ncenter
+    }
+    fn prepare(&mut self, _intor: &str) {}
+    fn workspace(&self, _intor: &str) {}
+    fn eval_into(&self, intor: &str, shls: &[usize], out: &mut [f64], _workspace: &mut ()) -> usize {
+        let intor = find_intor(intor);
+        let shells: Vec<&Shell> = shls.iter().map(|i| &self.shells[*i]).collect();
+        let block = eval_block(intor, &shells, &potential(intor, &self.nuclei, self.rinv_origin), self.cint_type);
+        if out.len() < block.len() {
+            panic!("Error:: the output of {} elements is too short for the block of {} elements of {}", out.len(), block.len(), intor.name)
+        }
+        out[..block.len()].copy_from_slice(&block);
+        block.len()
+    }
+}
+
+/// Evaluate `intor` with the arguments of the libcint C API, for the stand-ins of the integral
+/// functions of `cint` without the `libcint` feature. No cache is needed, so `out = NULL` returns 0.
+#[allow(clippy::too_many_arguments)]
+pub(crate) unsafe fn cint_eval_raw(intor: &str, cint_type: CintType, out: *mut f64, dims: *const c_int, shls: *const c_int,
+                                   atm: *const c_int, natm: c_int, bas: *const c_int, nbas: c_int, env: *const f64) -> c_int {
+    if out.is_null() {return 0}
+    let intor = find_intor(intor);
+    let atm = std::slice::from_raw_parts(atm, natm as usize*ATM_SLOTS);
+    let bas = std::slice::from_raw_parts(bas, nbas as usize*BAS_SLOTS);
+    // the part of env referred to by atm and bas
+    let env_len = atm.chunks(ATM_SLOTS).map(|a| (a[PTR_COORD] as usize + 3).max(a[PTR_FRAC_CHARGE] as usize + 1))
+        .chain(bas.chunks(BAS_SLOTS).map(|b| {
+            let nprim = b[NPRIM_OF] as usize;
+            (b[PTR_EXP] as usize + nprim).max(b[PTR_COEFF] as usize + nprim*b[NCTR_OF] as usize)
+        }))
+        .fold(PTR_RINV_ORIG + 3, usize::max);
+    let env = std::slice::from_raw_parts(env, env_len);
+    let shls = std::slice::from_raw_parts(shls, intor.ncenter);
+    let shells: Vec<Shell> = shls.iter().map(|i| Shell::from_cint(*i as usize, atm, bas, env)).collect();
+    let rinv_origin = [env[PTR_RINV_ORIG], env[PTR_RINV_ORIG+1], env[PTR_RINV_ORIG+2]];
+    let block = eval_block(intor, &shells.iter().collect::<Vec<_>>(), &potential(intor, &nuclei(atm, env), rinv_origin), cint_type);
+    let sizes: Vec<usize> = shells.iter().map(|s| s.dim(cint_type)).collect();
+    let dims: Vec<usize> = if dims.is_null() {sizes.clone()} else {
+        std::slice::from_raw_parts(dims, intor.ncenter).iter().map(|d| *d as usize).collect()
+    };
+    for (f, v) in block.iter().enumerate() {
+        let (mut rest, mut stride, mut pos) = (f, 1, 0);
+        sizes.iter().zip(&dims).for_each(|(s, d)| {
+            pos += stride*(rest % s);
+            rest /= s;
+            stride *= d;
+        });
+        *out.add(pos + stride*rest) = *v;
+    }
+    block.iter().any(|v| *v != 0.0) as c_int
+}
+
+#[test]
+pub fn test_reference_engine() {
+    // H2 in STO-3G at 1.4 bohr, against Szabo and Ostlund (table 3.5 and eq. 3.235)
+    let mut atm = vec![];
+    let mut bas = vec![];
+    let mut env = vec![0.0; 20];
+    for (iatm, z) in [0.0, 1.4].into_iter().enumerate() {
+        atm.push(vec![1, env.len() as i32, 0, 0, 0, 0]);
+        env.extend([0.0, 0.0, z]);
+        let ptr = env.len() as i32;
+        let exps = [3.42525091, 0.62391373, 0.16885540];
+        env.extend(exps);
+        env.extend(exps.iter().zip([0.15432897, 0.53532814, 0.44463454]).map(|(e, c)| c*gto_norm(0, *e)));
+        bas.push(vec![iatm as i32, 0, 3, 1, 0, ptr, ptr+3, 0]);
+    }
+    let mut engine = ReferenceEngine::new(&atm, &bas, &env, CintType::Spheric);
+    let close = |x: f64, y: f64| (x - y).abs() < 1.0e-4;
+    assert!(close(engine.eval("int1e_ovlp", &[0,0])[0], 1.0));
+    assert!(close(engine.eval("int1e_ovlp", &[0,1])[0], 0.6593));
+    assert!(close(engine.eval("int1e_kin", &[0,0])[0], 0.7600));
+    assert!(close(engine.eval("int1e_kin", &[0,1])[0], 0.2365));
+    assert!(close(engine.eval("int1e_nuc", &[0,0])[0], -1.8804));
+    assert!(close(engine.eval("int1e_nuc", &[0,1])[0], -1.1948));
+    assert!(close(engine.eval("int2e", &[0,0,0,0])[0], 0.7746));
+    assert!(close(engine.eval("int2e", &[0,0,1,1])[0], 0.5697));
+    assert!(close(engine.eval("int2e", &[1,0,1,0])[0], 0.2970));
+    assert!(close(engine.eval("int2e", &[1,0,0,0])[0], 0.4441));
+    engine.set_rinv_origin(&[0.0, 0.0, 1.4]);
+    assert!(close(engine.eval("int1e_rinv", &[0,0])[0], 0.6538));
+
+    // single normalized primitives of l = 0..4 on two centers
+    let (alpha, beta) = (1.3, 0.7);
+    let centers = [[0.0, 0.0, 0.0], [0.3, -0.4, 0.9]];
+    let mut atm = vec![];
+    let mut bas = vec![];
+    let mut env = vec![0.0; 20];
+    for (iatm, center) in centers.iter().enumerate() {
+        atm.push(vec![1, env.len() as i32, 0, 0, 0, 0]);
+        env.extend(center);
+    }
+    for (iatm, exp) in [(0, alpha), (1, beta)] {
+        for l in 0..=4 {
+            let ptr = env.len() as i32;
+            env.extend([exp, gto_norm(l, exp)]);
+            bas.push(vec![iatm, l as i32, 1, 1, 0, ptr, ptr+1, 0]);
+        }
+    }
+    let mut engine = ReferenceEngine::new(&atm, &bas, &env, CintType::Spheric);
+    assert_eq!(engine.ao_loc()[10], 2*(1+3+5+7+9));
+    for l in 0..=4 {
+        let n = 2*l+1;
+        let ovlp = engine.eval("int1e_ovlp", &[l,l]);
+        let kin = engine.eval("int1e_kin", &[l,l]);
+        for j in 0..n {
+            for i in 0..n {
+                let delta = if i == j {1.0} else {0.0};
+                assert!((ovlp[i + n*j] - delta).abs() < 1.0e-12);
+                assert!((kin[i + n*j] - delta*(2*l+3) as f64*alpha/2.0).abs() < 1.0e-12);
+            }
+        }
+    }
+    // the s function at the nucleus at the origin, and its self-repulsion
+    let nuc = engine.eval("int1e_nuc", &[0,0])[0];
+    let nuc_far = engine.eval("int1e_rinv", &[0,0])[0];
+    let r = (0.3_f64*0.3 + 0.4*0.4 + 0.9*0.9).sqrt();
+    assert!((nuc_far - 2.0*(2.0*alpha/PI).sqrt()).abs() < 1.0e-12);
+    assert!((nuc + nuc_far + erf((2.0*alpha).sqrt()*r)/r).abs() < 1.0e-12);
+    assert!((engine.eval("int2e", &[0,0,0,0])[0] - 2.0*(alpha/PI).sqrt()).abs() < 1.0e-12);
+    // (a|b) for the unnormalized 2c2e of normalized s functions
+    let ab = engine.eval("int2c2e", &[0,5])[0];
+    let mu = alpha*beta/(alpha + beta);
+    let norm = |a: f64| (2.0*a/PI).powf(0.75)*(PI/a).powf(1.5);
+    assert!((ab - norm(alpha)*norm(beta)*erf(mu.sqrt()*r)/r).abs() < 1.0e-12);
+
+    // the permutational symmetry of the ERIs of d, f and g shells, and the 3c2e of the unit Gaussian
+    let (i, j, k, l) = (2, 8, 4, 7);
+    let dims = [5, 7, 9, 5];
+    let ijkl = engine.eval("int2e", &[i,j,k,l]);
+    let jilk = engine.eval("int2e", &[j,i,l,k]);
+    let klij = engine.eval("int2e", &[k,l,i,j]);
+    for q in 0..dims[3] {
+        for p in 0..dims[2] {
+            for b in 0..dims[1] {
+                for a in 0..dims[0] {
+                    let v = ijkl[a + 5*(b + 7*(p + 9*q))];
+                    assert!((v - jilk[b + 7*(a + 5*(q + 5*p))]).abs() < 1.0e-12);
+                    assert!((v - klij[p + 9*(q + 5*(a + 5*b))]).abs() < 1.0e-12);
+                }
+            }
+        }
+    }
+
+    // the ip integrals against the finite differences of the bra center (d/dr = -d/dA)
+    for (intor, ip_intor, shls) in [("int1e_ovlp", "int1e_ipovlp", vec![3,7]), ("int1e_kin", "int1e_ipkin", vec![2,9]),
+                                    ("int1e_nuc", "int1e_ipnuc", vec![7,1]), ("int3c2e", "int3c2e_ip1", vec![3,6,2]),
+                                    ("int2e", "int2e_ip1", vec![2,5,1,8])] {
+        let ip = engine.eval(ip_intor, &shls);
+        let size = ip.len()/3;
+        let h = 1.0e-4;
+        for d in 0..3 {
+            let mut shifted = engine.clone();
+            shifted.shells[shls[0]].center[d] += h;
+            let plus = shifted.eval(intor, &shls);
+            shifted.shells[shls[0]].center[d] -= 2.0*h;
+            let minus = shifted.eval(intor, &shls);
+            (0..size).for_each(|x| assert!((ip[x + size*d] + (plus[x] - minus[x])/(2.0*h)).abs() < 1.0e-7));
+        }
+    }
+
+    // the Cartesian d functions x^2 and xy, normalized in the radial part only
+    engine.set_cint_type(&CintType::Cartesian);
+    let ovlp = engine.eval("int1e_ovlp", &[2,2]);
+    assert!((ovlp[0] - 4.0*PI/5.0).abs() < 1.0e-12);
+    assert!((ovlp[1 + 6] - 4.0*PI/15.0).abs() < 1.0e-12);
+    assert_eq!(engine.eval("int3c2e_ip2", &[0,1,2]).len(), 3*6*3);
+
+    #[cfg(feature = "libcint")]
+    {
+        // against libcint, on the joint basis of the H2 tests with the contracted shells (without the
+        // `libcint` feature, CINTR2CDATA runs on this engine and there is nothing to compare)
+        let mut cint_data = crate::test_h2_data();
+        let aux_data = crate::test_h2_aux_data();
+        let mut bas = cint_data.get_bas();
+        let mut env = cint_data.get_env();
+        let offset = env.len() as i32;
+        env.extend(&aux_data.get_env()[20..]);
+        bas.extend(aux_data.get_bas().iter().map(|b| vec![b[0], b[1], b[2], b[3], b[4], b[5]+offset-20, b[6]+offset-20, b[7]]));
+        let atm = cint_data.get_atm();
+        cint_data.initial_r2c(&atm, atm.len() as i32, &bas, bas.len() as i32, &env);
+        cint_data.set_rinv_origin(&[0.1, -0.2, 0.3]);
+        for cint_type in [CintType::Spheric, CintType::Cartesian] {
+            cint_data.set_cint_type(&cint_type);
+            let engine = ReferenceEngine::from_cint_data(&cint_data);
+            let nbas = engine.nbas();
+            for intor in INTEGRALS.iter() {
+                cint_data.prepare(intor.name);
+                let shls_list: Vec<Vec<usize>> = match intor.ncenter {
+                    2 => vec![vec![0,3], vec![1,6], vec![4,7]],
+                    3 => vec![vec![0,1,6], vec![3,2,7], vec![1,1,nbas-1]],
+                    _ => vec![vec![0,1,2,3], vec![1,3,1,2], vec![3,3,3,3]],
+                };
+                for shls in shls_list {
+                    let reference = engine.eval(intor.name, &shls);
+                    let cint = IntegralEngine::eval(&cint_data, intor.name, &shls);
+                    assert_eq!(reference.len(), cint.len());
+                    reference.iter().zip(&cint).for_each(|(x, y)| assert!((x - y).abs() < 1.0e-10, "{} {:?}", intor.name, shls));
+                }
+            }
+        }
+        cint_data.cint_del_optimizer_rust();
+    }
+    // without libcint, the integrals not provided here panic in CINTR2CDATA, which can be caught
+    #[cfg(not(feature = "libcint"))]
+    {
+        let mut cint_data = crate::test_h2_data();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| cint_data.cint_intor("int1e_r", &[0,1])));
+        assert!(result.is_err());
+        cint_data.final_c2r();
+    }
+}
+
+/// erf by the Boys function, `erf(x) = 2x/sqrt(pi) F_0(x^2)`
+#[cfg(test)]
+fn erf(x: f64) -> f64 {
+    2.0*x/PI.sqrt()*crate::rys::boys(0, x*x)
+}
@@ -0,0 +1,250 @@
+//! Density-fitted (RI) Coulomb and exchange matrices.
+//!
+//! With the three-center integrals `(ij|P)` and the Coulomb metric `V_{PQ} = (P|Q)`,
+//!
+//! J_{ij} = \sum_{PQ} (ij|P) [V^{-1}]_{PQ} \sum_{kl} (Q|kl) D_{lk}
+//!
+//! K_{ij} = \sum_{PQ} \sum_{kl} (ik|P) [V^{-1}]_{PQ} (Q|lj) D_{kl}
+//!
+//! The three-center integrals are evaluated in batches of auxiliary shells with at most
+//! `aux_batch_size` fitting functions each, so that `(ij|P)` is never stored for all `P` at once.
+//! For the exchange matrices, the occupied-transformed intermediates `(i m|P) = \sum_k (ik|P) C_{km}`
+//! are built batch by batch and contracted at once with the rows of `V^{-1/2}` of the batch into the
+//! fitted factors `b^Q_{im}`, which is the natural path when the orbital coefficients are known; the
+//! factors of `nao*nocc*naux` are the only intermediates kept over all `P`.
+use std::ops::Range;
+
+use crate::{CINTR2CDATA, linalg};
+use crate::density_fitting::{MetricDecomposition, int2c2e_metric, int3c2e_packed, metric_inverse_sqrt, pair_index};
+use crate::engine::{self, IntegralEngine};
+
+impl CINTR2CDATA {
+    /// RI-J: the Coulomb matrices of the density matrices `dms` (`column-major` `[nao, nao]` over `orb_shls`)
+    /// fitted by the auxiliary shells `aux_shls`.
+    pub fn get_j_ri(&mut self, dms: &[Vec<f64>], orb_shls: Range<i32>, aux_shls: Range<i32>,
+                    decomposition: MetricDecomposition, aux_batch_size: usize) -> Vec<Vec<f64>> {
+        get_j_ri(self, dms, engine::shell_range(orb_shls), engine::shell_range(aux_shls), decomposition, aux_batch_size)
+    }
+
+    /// RI-K from the orbital coefficients: the exchange matrices of the densities
+    /// `D = C diag(n) C^T` with `mo_coeffs[s]` the `column-major` `C` of `[nao, nmo]`
+    /// and `mo_occ[s]` the occupation numbers `n` of the `nmo` orbitals.
+    ///
+    /// The peak memory is the fitted factors of `nao*nocc*naux` per density, which every fitting
+    /// function contributes to, plus one batch of `(ij|P)` of `nao*(nao+1)/2*aux_batch_size`.
+    pub fn get_k_ri_mo(&mut self, mo_coeffs: &[Vec<f64>], mo_occ: &[Vec<f64>], orb_shls: Range<i32>, aux_shls: Range<i32>,
+                       decomposition: MetricDecomposition, aux_batch_size: usize) -> Vec<Vec<f64>> {
+        get_k_ri_mo(self, mo_coeffs, mo_occ, engine::shell_range(orb_shls), engine::shell_range(aux_shls), decomposition, aux_batch_size)
+    }
+
+    /// RI-K from the (symmetric) density matrices `dms`, which are factorized by their
+    /// eigen-decomposition to follow the orbital path of `get_k_ri_mo`.
+    pub fn get_k_ri(&mut self, dms: &[Vec<f64>], orb_shls: Range<i32>, aux_shls: Range<i32>,
+                    decomposition: MetricDecomposition, aux_batch_size: usize) -> Vec<Vec<f64>> {
+        get_k_ri(self, dms, engine::shell_range(orb_shls), engine::shell_range(aux_shls), decomposition, aux_batch_size)
+    }
+}
+
+/// The auxiliary shells of `aux_shls` grouped in batches of at most `aux_batch_size` basis functions
+/// (a shell larger than the batch size forms a batch by itself).
+fn aux_batches<E: IntegralEngine>(engine: &E, aux_shls: Range<usize>, aux_batch_size: usize) -> Vec<Range<usize>> {
+    let ao_loc = engine.ao_loc();
+    let mut batches = vec![];
+    let mut start = aux_shls.start;
+    for k in aux_shls.clone() {
+        if k > start && ao_loc[k+1] - ao_loc[start] > aux_batch_size {
+            batches.push(start..k);
+            start = k;
+        }
+    }
+    if start < aux_shls.end {batches.push(start..aux_shls.end)};
+    batches
+}
+
+/// `CINTR2CDATA::get_j_ri` for any engine: the RI-J Coulomb matrices of the density matrices `dms`.
+///
+/// "int2c2e" and "int3c2e" are prepared by this driver.
+pub fn get_j_ri<E: IntegralEngine>(engine: &mut E, dms: &[Vec<f64>], orb_shls: Range<usize>, aux_shls: Range<usize>,
+                                   decomposition: MetricDecomposition, aux_batch_size: usize) -> Vec<Vec<f64>> {
+    let ao_loc = engine.ao_loc();
+    let nao = ao_loc[orb_shls.end] - ao_loc[orb_shls.start];
+    let npair = nao*(nao+1)/2;
+    let p_off = ao_loc[aux_shls.start];
+    let naux = ao_loc[aux_shls.end] - p_off;
+    let ndm = dms.len();
+    // D_ij + D_ji in packed-ij order, with the diagonal counted once
+    let dms_packed: Vec<Vec<f64>> = dms.iter().map(|dm| {
+        let mut packed = vec![0.0; npair];
+        for j in 0..nao {
+            for i in j..nao {
+                packed[pair_index(i,j)] = if i == j {dm[i + nao*i]} else {dm[i + nao*j] + dm[j + nao*i]};
+            }
+        }
+        packed
+    }).collect();
+
+    let metric = int2c2e_metric(engine, aux_shls.clone());
+    let (m, nkept) = metric_inverse_sqrt(metric, naux, decomposition);
+    let batches = aux_batches(engine, aux_shls, aux_batch_size);
+
+    // gamma_P = \sum_kl (P|kl) D_lk
+    let mut gamma = vec![0.0; naux*ndm];
+    for batch in batches.iter() {
+        let b0 = ao_loc[batch.start] - p_off;
+        let nb = ao_loc[batch.end] - ao_loc[batch.start];
+        let tensor = int3c2e_packed(engine, orb_shls.clone(), batch.clone());
+        let gamma_b = linalg::matmul_tn(&tensor, &dms_packed.concat(), nb, npair, ndm);
+        for idm in 0..ndm {
+            gamma[naux*idm + b0..naux*idm + b0 + nb].copy_from_slice(&gamma_b[nb*idm..nb*(idm+1)]);
+        }
+    }
+    // c = V^{-1} gamma = M M^T gamma
+    let coeff = linalg::matmul(&m, &linalg::matmul_tn(&m, &gamma, nkept, naux, ndm), naux, nkept, ndm);
+
+    let mut vj_packed = vec![0.0; npair*ndm];
+    for batch in batches.iter() {
+        let b0 = ao_loc[batch.start] - p_off;
+        let nb = ao_loc[batch.end] - ao_loc[batch.start];
+        let tensor = int3c2e_packed(engine, orb_shls.clone(), batch.clone());
+        let coeff_b: Vec<f64> = (0..ndm).flat_map(|idm| coeff[naux*idm + b0..naux*idm + b0 + nb].to_vec()).collect();
+        linalg::matmul(&tensor, &coeff_b, npair, nb, ndm).iter().zip(vj_packed.iter_mut()).for_each(|(x,y)| {*y += x});
+    }
+    vj_packed.chunks(npair).map(|packed| unpack_symmetric(packed, nao)).collect()
+}
+
+/// `CINTR2CDATA::get_k_ri_mo` for any engine: the RI-K exchange matrices of the densities `C diag(n) C^T`.
+///
+/// "int2c2e" and "int3c2e" are prepared by this driver.
+pub fn get_k_ri_mo<E: IntegralEngine>(engine: &mut E, mo_coeffs: &[Vec<f64>], mo_occ: &[Vec<f64>], orb_shls: Range<usize>, aux_shls: Range<usize>,
+                                      decomposition: MetricDecomposition, aux_batch_size: usize) -> Vec<Vec<f64>> {
+    let ao_loc = engine.ao_loc();
+    let nao = ao_loc[orb_shls.end] - ao_loc[orb_shls.start];
+    let npair = nao*(nao+1)/2;
+    let p_off = ao_loc[aux_shls.start];
+    let naux = ao_loc[aux_shls.end] - p_off;
+    // only the occupied orbitals contribute
+    let occupied: Vec<(Vec<f64>, Vec<f64>)> = mo_coeffs.iter().zip(mo_occ.iter()).map(|(c, occ)| {
+        let occ_idx: Vec<usize> = (0..occ.len()).filter(|i| occ[*i] != 0.0).collect();
+        let c_occ: Vec<f64> = occ_idx.iter().flat_map(|i| c[nao*i..nao*(i+1)].to_vec()).collect();
+        (c_occ, occ_idx.iter().map(|i| occ[*i]).collect())
+    }).collect();
+
+    let metric = int2c2e_metric(engine, aux_shls.clone());
+    let (m, nkept) = metric_inverse_sqrt(metric, naux, decomposition);
+
+    // the fitted occupied factors b^q_{k m} = \sum_P (k m|P) M_{Pq} of [nao*nocc, nkept], with each
+    // occupied-transformed (k m|P) contracted into them as soon as it is produced
+    let mut factors: Vec<Vec<f64>> = occupied.iter().map(|(_, occ)| vec![0.0; nao*occ.len()*nkept]).collect();
+    for batch in aux_batches(engine, aux_shls, aux_batch_size) {
+        let b0 = ao_loc[batch.start] - p_off;
+        let nb = ao_loc[batch.end] - ao_loc[batch.start];
+        let tensor = int3c2e_packed(engine, orb_shls.clone(), batch);
+        for p in 0..nb {
+            let square = unpack_symmetric(&tensor[npair*p..npair*(p+1)], nao);
+            occupied.iter().zip(factors.iter_mut()).for_each(|((c_occ, occ), b)| {
+                let nrow = nao*occ.len();
+                let x_p = linalg::matmul(&square, c_occ, nao, nao, occ.len());
+                for q in 0..nkept {
+                    let m_pq = m[b0 + p + naux*q];
+                    if m_pq == 0.0 {continue};
+                    b[nrow*q..nrow*(q+1)].iter_mut().zip(x_p.iter()).for_each(|(b,x)| {*b += m_pq*x});
+                }
+            });
+        }
+    }
+
+    occupied.iter().zip(factors.iter()).map(|((_, occ), b)| {
+        let nrow = nao*occ.len();
+        let mut vk = vec![0.0; nao*nao];
+        for p in 0..nkept {
+            let b_p = &b[nrow*p..nrow*(p+1)];
+            for (i, n_i) in occ.iter().enumerate() {
+                let b_pi = &b_p[nao*i..nao*(i+1)];
+                for nu in 0..nao {
+                    let factor = n_i*b_pi[nu];
+                    vk[nao*nu..nao*(nu+1)].iter_mut().zip(b_pi.iter()).for_each(|(k,b)| {*k += factor*b});
+                }
+            }
+        }
+        vk
+    }).collect()
+}
+
+/// `CINTR2CDATA::get_k_ri` for any engine: the RI-K exchange matrices of the (symmetric) density matrices `dms`.
+///
+/// "int2c2e" and "int3c2e" are prepared by this driver.
+pub fn get_k_ri<E: IntegralEngine>(engine: &mut E, dms: &[Vec<f64>], orb_shls: Range<usize>, aux_shls: Range<usize>,
+                                   decomposition: MetricDecomposition, aux_batch_size: usize) -> Vec<Vec<f64>> {
+    let ao_loc = engine.ao_loc();
+    let nao = ao_loc[orb_shls.end] - ao_loc[orb_shls.start];
+    let (mo_occ, mo_coeffs): (Vec<Vec<f64>>, Vec<Vec<f64>>) = dms.iter().map(|dm| {
+        let mut dm_sym = dm.clone();
+        for j in 0..nao {
+            for i in 0..nao {dm_sym[i + nao*j] = 0.5*(dm[i + nao*j] + dm[j + nao*i])};
+        }
+        let (w, u) = linalg::symmetric_eigh(&dm_sym, nao);
+        let w = w.iter().map(|x| if x.abs() < 1.0e-14 {0.0} else {*x}).collect();
+        (w, u)
+    }).unzip();
+    get_k_ri_mo(engine, &mo_coeffs, &mo_occ, orb_shls, aux_shls, decomposition, aux_batch_size)
+}
+
+/// the symmetric `[nao, nao]` matrix from its packed lower triangle
+fn unpack_symmetric(packed: &[f64], nao: usize) -> Vec<f64> {
+    let mut square = vec![0.0; nao*nao];
+    for j in 0..nao {
+        for i in j..nao {
+            let v = packed[pair_index(i,j)];
+            square[i + nao*j] = v;
+            square[j + nao*i] = v;
+        }
+    }
+    square
+}
+
+#[test]
+pub fn test_ri_jk() {
+    let mut orbital = crate::test_h2_data();
+    let mut aux = crate::test_h2_aux_data();
+    let mut cint_data = orbital.conc_cint_data(&aux);
+    let nbas = cint_data.get_nbas();
+    let nao = 8;
+    let ri = cint_data.ri3fn_factors(0..4, 4..nbas, MetricDecomposition::Cholesky);
+
+    // D = C diag(n) C^T with two orbitals
+    let mo_coeff: Vec<f64> = (0..nao*2).map(|i| 0.3*((i*i) as f64).sin()).collect();
+    let mo_occ = vec![2.0, 1.0];
+    let mut dm = vec![0.0; nao*nao];
+    for j in 0..nao {for i in 0..nao {for m in 0..2 {
+        dm[i + nao*j] += mo_occ[m]*mo_coeff[i + nao*m]*mo_coeff[j + nao*m];
+    }}}
+    let dms = vec![dm.clone()];
+
+    let vj = cint_data.get_j_ri(&dms, 0..4, 4..nbas, MetricDecomposition::Cholesky, 5);
+    let vk = cint_data.get_k_ri(&dms, 0..4, 4..nbas, MetricDecomposition::Cholesky, 5);
+    let vk_mo = cint_data.get_k_ri_mo(&[mo_coeff], &[mo_occ], 0..4, 4..nbas, MetricDecomposition::Eigen(1.0e-14), 7);
+    // the reference from the fitted ERIs
+    let eri = |i,j,k,l| (0..ri.naux).map(|p| ri.get(i,j,p)*ri.get(k,l,p)).sum::<f64>();
+    for j in 0..nao {
+        for i in 0..nao {
+            let mut j_ref = 0.0;
+            let mut k_ref = 0.0;
+            for l in 0..nao {for k in 0..nao {
+                j_ref += eri(i,j,k,l)*dm[l + nao*k];
+                k_ref += eri(i,k,j,l)*dm[l + nao*k];
+            }}
+            assert!((vj[0][i + nao*j] - j_ref).abs() < 1.0e-10);
+            assert!((vk[0][i + nao*j] - k_ref).abs() < 1.0e-10);
+            assert!((vk_mo[0][i + nao*j] - k_ref).abs() < 1.0e-8);
+        }
+    }
+    // the generic drivers on the reference engine
+    let mut reference = crate::reference::ReferenceEngine::from_cint_data(&cint_data);
+    let vj_ref = get_j_ri(&mut reference, &dms, 0..4, 4..nbas as usize, MetricDecomposition::Cholesky, 5);
+    let vk_ref = get_k_ri(&mut reference, &dms, 0..4, 4..nbas as usize, MetricDecomposition::Cholesky, 5);
+    vj_ref[0].iter().zip(vj[0].iter()).for_each(|(x,y)| assert!((x-y).abs() < 1.0e-10));
+    vk_ref[0].iter().zip(vk[0].iter()).for_each(|(x,y)| assert!((x-y).abs() < 1.0e-10));
+    cint_data.final_c2r();
+    orbital.final_c2r();
+    aux.final_c2r();
+}
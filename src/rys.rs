@@ -0,0 +1,265 @@
+//! The Boys function and the Rys quadrature.
+//!
+//! The Boys function is `F_m(T) = \int_0^1 t^{2m} e^{-T t^2} dt` for `T >= 0`. The Rys quadrature of
+//! `n` roots integrates `\int_0^1 P(t^2) e^{-x t^2} dt = \sum_i w_i P(t_i^2)` exactly for the
+//! polynomials `P` of degree below `2n`, i.e. `\sum_i w_i t_i^{2m} = F_m(x)` for `m < 2n`. The roots
+//! are returned as `t_i^2` in ascending order; those of libcint are `u_i = t_i^2/(1 - t_i^2)`.
+//!
+//! `F_m(T)` is evaluated by its series and the downward recursion for `T < 40 + m`, and by the
+//! upward recursion from `F_0` otherwise, to a relative accuracy of about 1e-14. The Rys roots and
+//! weights are the Gauss quadrature (Golub–Welsch) of the recurrence coefficients of the orthogonal
+//! polynomials, obtained by the Stieltjes procedure on a Gauss–Legendre discretization of the weight
+//! function: the roots are the eigenvalues of the tridiagonal Jacobi matrix by the QL iterations,
+//! refined by Newton iterations, and the weights are from the Christoffel function. No heap memory
+//! is allocated per call.
+use std::f64::consts::PI;
+use std::sync::OnceLock;
+
+use crate::linalg;
+
+/// the largest number of Rys roots
+pub const RYS_MAX_ROOTS: usize = 14;
+
+/// `t` below which `F_m(t)` is evaluated by the series
+fn series_limit(m: usize) -> f64 {
+    40.0 + m as f64
+}
+
+/// `e^{t} F_m(t)` by the series `\sum_k (2t)^k / ((2m+1)(2m+3)...(2m+2k+1))`
+fn boys_series(m: usize, t: f64) -> f64 {
+    let m = m as f64;
+    let mut term = 1.0/(2.0*m + 1.0);
+    let mut sum = term;
+    let mut k = 1.0;
+    while term > 1.0e-17*sum {
+        term *= 2.0*t/(2.0*m + 2.0*k + 1.0);
+        sum += term;
+        k += 1.0;
+    }
+    sum
+}
+
+/// The Boys function `F_m(t)`.
+pub fn boys(m: usize, t: f64) -> f64 {
+    let exp_t = (-t).exp();
+    if t < series_limit(m) {
+        return exp_t*boys_series(m, t)
+    }
+    // erf(sqrt(t)) = 1 to double precision, and the upward recursion is stable for t > m
+    let mut f = 0.5*(PI/t).sqrt();
+    for i in 0..m {
+        f = ((2*i+1) as f64*f - exp_t)/(2.0*t);
+    }
+    f
+}
+
+/// The Boys functions `F_0(t), ..., F_{n-1}(t)` into `out` of length `n`.
+pub fn boys_into(t: f64, out: &mut [f64]) {
+    let Some(m_max) = out.len().checked_sub(1) else {return};
+    let exp_t = (-t).exp();
+    if t < series_limit(m_max) {
+        out[m_max] = exp_t*boys_series(m_max, t);
+        for i in (0..m_max).rev() {
+            out[i] = (2.0*t*out[i+1] + exp_t)/(2*i+1) as f64;
+        }
+    } else {
+        out[0] = 0.5*(PI/t).sqrt();
+        for i in 0..m_max {
+            out[i+1] = ((2*i+1) as f64*out[i] - exp_t)/(2.0*t);
+        }
+    }
+}
+
+/// the number of arguments of `boys_batch` whose series are summed in lockstep
+const BOYS_CHUNK: usize = 8;
+
+/// The Boys function `F_m` of all `ts` into `out`, equal to `boys(m, t)` element by element.
+///
+/// The series of a chunk of arguments are summed in lockstep, with the converged (or large) ones
+/// frozen, so that the inner loop runs over fixed-size arrays.
+pub fn boys_batch(m: usize, ts: &[f64], out: &mut [f64]) {
+    if ts.len() != out.len() {
+        panic!("Error:: the output of {} elements does not match the {} arguments of the Boys function", out.len(), ts.len())
+    }
+    let limit = series_limit(m);
+    let m_f = m as f64;
+    for (ts, out) in ts.chunks(BOYS_CHUNK).zip(out.chunks_mut(BOYS_CHUNK)) {
+        let mut two_t = [0.0; BOYS_CHUNK];
+        ts.iter().zip(two_t.iter_mut()).for_each(|(t, x)| if *t < limit {*x = 2.0*t});
+        let mut term = [1.0/(2.0*m_f + 1.0); BOYS_CHUNK];
+        let mut sum = term;
+        let mut k = 1.0;
+        loop {
+            let denominator = 2.0*m_f + 2.0*k + 1.0;
+            let mut converged = true;
+            for i in 0..BOYS_CHUNK {
+                term[i] *= two_t[i]/denominator;
+                sum[i] += term[i];
+                if term[i] <= 1.0e-17*sum[i] {two_t[i] = 0.0} else {converged = false};
+            }
+            if converged {break}
+            k += 1.0;
+        }
+        for (i, (f, t)) in out.iter_mut().zip(ts).enumerate() {
+            *f = if *t < limit {(-t).exp()*sum[i]} else {boys(m, *t)};
+        }
+    }
+}
+
+/// the number of positive nodes of the Gauss–Legendre rule discretizing the Rys weight function
+const NLEGENDRE: usize = 128;
+
+/// the squares `u^2` of the positive nodes and the weights of the Gauss–Legendre rule of `2*NLEGENDRE`
+/// points on [-1, 1]
+fn gauss_legendre() -> &'static [(f64, f64)] {
+    static NODES: OnceLock<Vec<(f64, f64)>> = OnceLock::new();
+    NODES.get_or_init(|| {
+        let n = 2*NLEGENDRE;
+        (0..NLEGENDRE).map(|i| {
+            let mut x = (PI*(i as f64 + 0.75)/(n as f64 + 0.5)).cos();
+            let mut dp = 1.0;
+            for _ in 0..100 {
+                let (mut p0, mut p1) = (1.0, x);
+                for k in 2..=n {
+                    let p2 = ((2*k-1) as f64*x*p1 - (k-1) as f64*p0)/k as f64;
+                    p0 = p1;
+                    p1 = p2;
+                }
+                dp = n as f64*(x*p1 - p0)/(x*x - 1.0);
+                let dx = p1/dp;
+                x -= dx;
+                if dx.abs() < 1.0e-16 {break}
+            }
+            (x*x, 2.0/((1.0 - x*x)*dp*dp))
+        }).collect()
+    })
+}
+
+/// The Rys roots `t_i^2` (ascending) and weights `w_i` of `x` into `roots` and `weights`, whose
+/// common length is the number of roots, at most `RYS_MAX_ROOTS`.
+pub fn rys_roots_into(x: f64, roots: &mut [f64], weights: &mut [f64]) {
+    let n = roots.len();
+    if n == 0 || n > RYS_MAX_ROOTS || weights.len() != n {
+        panic!("Error:: the Rys quadrature is available for 1 to {} roots, but {} roots and {} weights are requested",
+               RYS_MAX_ROOTS, n, weights.len())
+    }
+    // the weight function e^{-x t^2} on [0, b], beyond which it is below e^{-144}, in s = (t/b)^2;
+    // all scratch is on the stack
+    let b = (12.0/x.sqrt()).min(1.0);
+    let nodes = gauss_legendre();
+    let mut w = [0.0; NLEGENDRE];
+    w.iter_mut().zip(nodes).for_each(|(w, (s, ws))| *w = ws*b*(-x*b*b*s).exp());
+    // the Stieltjes procedure with the orthonormal polynomials q_k at the nodes:
+    // sqrt(beta_{k+1}) q_{k+1} = (s - alpha_k) q_k - sqrt(beta_k) q_{k-1}
+    let mu0: f64 = w.iter().sum();
+    let mut alpha = [0.0_f64; RYS_MAX_ROOTS];
+    let mut beta = [0.0_f64; RYS_MAX_ROOTS];
+    let mut q_prev = [0.0; NLEGENDRE];
+    let mut q = [1.0/mu0.sqrt(); NLEGENDRE];
+    for k in 0..n {
+        alpha[k] = (0..NLEGENDRE).map(|j| w[j]*nodes[j].0*q[j]*q[j]).sum();
+        if k+1 == n {break}
+        // q_prev becomes the unnormalized q_{k+1}
+        let sqrt_beta = beta[k].sqrt();
+        (0..NLEGENDRE).for_each(|j| q_prev[j] = (nodes[j].0 - alpha[k])*q[j] - sqrt_beta*q_prev[j]);
+        beta[k+1] = (0..NLEGENDRE).map(|j| w[j]*q_prev[j]*q_prev[j]).sum();
+        std::mem::swap(&mut q, &mut q_prev);
+        let norm = beta[k+1].sqrt();
+        q.iter_mut().for_each(|q| *q /= norm);
+    }
+    // Golub–Welsch: the roots are the eigenvalues of the tridiagonal Jacobi matrix
+    let mut eigenvalues = [0.0; RYS_MAX_ROOTS];
+    let mut off_diagonal = [0.0; RYS_MAX_ROOTS];
+    eigenvalues[..n].copy_from_slice(&alpha[..n]);
+    (0..n-1).for_each(|k| off_diagonal[k] = beta[k+1].sqrt());
+    linalg::tridiagonal_eigenvalues(&mut eigenvalues[..n], &mut off_diagonal[..n]);
+    for (i, root) in eigenvalues[..n].iter().copied().enumerate() {
+        // Newton iterations on the monic polynomial p_n
+        let mut root = root;
+        for _ in 0..3 {
+            let (mut p0, mut p1, mut dp0, mut dp1) = (0.0, 1.0, 0.0, 0.0);
+            for k in 0..n {
+                let (p2, dp2) = ((root - alpha[k])*p1 - beta[k]*p0, p1 + (root - alpha[k])*dp1 - beta[k]*dp0);
+                (p0, p1, dp0, dp1) = (p1, p2, dp1, dp2);
+            }
+            if dp1 == 0.0 {break}
+            root -= p1/dp1;
+        }
+        // the Christoffel function 1/\sum_k q_k(root)^2
+        let (mut q0, mut q1) = (0.0, 1.0/mu0.sqrt());
+        let mut sum = q1*q1;
+        for k in 0..n-1 {
+            let q2 = ((root - alpha[k])*q1 - beta[k].sqrt()*q0)/beta[k+1].sqrt();
+            (q0, q1) = (q1, q2);
+            sum += q1*q1;
+        }
+        roots[i] = b*b*root;
+        weights[i] = 1.0/sum;
+    }
+}
+
+/// The Rys roots `t_i^2` (ascending) and weights `w_i` of `x` for `nroots <= RYS_MAX_ROOTS`.
+pub fn rys_roots(nroots: usize, x: f64) -> (Vec<f64>, Vec<f64>) {
+    let mut roots = vec![0.0; nroots];
+    let mut weights = vec![0.0; nroots];
+    rys_roots_into(x, &mut roots, &mut weights);
+    (roots, weights)
+}
+
+#[test]
+#[allow(clippy::excessive_precision)]
+pub fn test_rys() {
+    // reference values by mpmath at 120 digits, F_m(T) = 1F1(m+1/2; m+3/2; -T)/(2m+1)
+    let boys_ref = [
+        (0, 0.0, 1.0),
+        (0, 1.0e-3, 0.99966676664286177172),
+        (3, 0.7, 0.083547093602981055107),
+        (8, 12.5, 3.0145110503970596681e-6),
+        (0, 35.0, 0.1497996913402740547),
+        (16, 35.0, 8.6484060937247593146e-14),
+        (12, 60.0, 4.05791663779760347e-15),
+        (5, 250.0, 1.6949493438387336387e-12),
+        (40, 30.0, 3.742677839294421076e-15),
+    ];
+    for (m, t, f) in boys_ref {
+        assert!((boys(m, t) - f).abs() < 1.0e-14*f, "F_{}({})", m, t);
+        let mut all = vec![0.0; m+3];
+        boys_into(t, &mut all);
+        assert!((all[m] - f).abs() < 1.0e-13*f, "F_{}({})", m, t);
+    }
+    let ts = [0.0, 0.5, 45.0, 1.0e4];
+    let mut out = [0.0; 4];
+    boys_batch(2, &ts, &mut out);
+    (0..4).for_each(|i| assert_eq!(out[i], boys(2, ts[i])));
+    // more arguments than a chunk, mixing the series and the upward recursion
+    let ts: Vec<f64> = (0..19).map(|i| 0.37*(i*i) as f64).collect();
+    let mut out = vec![0.0; ts.len()];
+    for m in [0, 5, 16] {
+        boys_batch(m, &ts, &mut out);
+        ts.iter().zip(&out).for_each(|(t, f)| assert_eq!(*f, boys(m, *t)));
+    }
+
+    // the first and the last roots and weights, by the Chebyshev algorithm and the Jacobi
+    // eigenproblem of mpmath at 120 digits
+    let rys_ref = [
+        (1, 0.3, [0.3074562790834371828, 0.90839287703275053866], [0.3074562790834371828, 0.90839287703275053866]),
+        (3, 0.0, [0.056939115967007353237, 0.46791393457269104739], [0.86949939491826234133, 0.17132449237917034504]),
+        (5, 2.5, [0.017769362353217962827, 0.25413435140899047215], [0.93472441403174725052, 0.0080906258734618881073]),
+        (8, 17.0, [0.0039447019873121119039, 0.11752157968360334885], [0.92110450636039883231, 1.4694308302627930794e-8]),
+        (14, 0.5, [0.0029813522649435979028, 0.10893609387415352705], [0.99277230989462662888, 0.0056518493331293449669]),
+        (14, 45.0, [0.00094803060789033864724, 0.059022601311241270911], [0.90013979090788490318, 2.3834848321804420649e-19]),
+        (14, 300.0, [0.00014430678579924516168, 0.023013454113735656195], [0.14483087435950680958, 6.5825975942587181476e-21]),
+        (10, 5000.0, [0.000012038412629917583094, 0.0065371126667029073673], [0.0058049900680472451518, 3.152838729382793223e-15]),
+    ];
+    let close = |x: f64, y: f64| (x - y).abs() < 1.0e-12*y.abs();
+    for (n, x, first, last) in rys_ref {
+        let (roots, weights) = rys_roots(n, x);
+        assert!(close(roots[0], first[0]) && close(weights[0], first[1]), "{} roots of {}", n, x);
+        assert!(close(roots[n-1], last[0]) && close(weights[n-1], last[1]), "{} roots of {}", n, x);
+        // the moments F_m(x) for m < 2n
+        for m in 0..2*n {
+            let moment: f64 = roots.iter().zip(&weights).map(|(r, w)| w*r.powi(m as i32)).sum();
+            assert!((moment - boys(m, x)).abs() < 1.0e-12*boys(m, x), "F_{}({}) by {} roots", m, x, n);
+        }
+    }
+}
@@ -0,0 +1,260 @@
+//! Integral screening for the two-electron repulsive integrals.
+//!
+//! Two kinds of screening are available:
+//! 1) the Schwarz inequality `|(ij|kl)| <= Q_ij Q_kl` with the shell-pair bounds `Q_ij = sqrt(max|(ij|ij)|)`;
+//! 2) the shell-pair overlap estimate `exp(-a b/(a+b) R_ij^2)` with the most diffuse exponents
+//!    `a` and `b` of the two shells, which removes the distant pairs before any integral is computed.
+//!
+//! For Fock builds, the bounds can further be weighted by the largest density-matrix element of
+//! the shell blocks coupled to the quartet (see `Screening::with_density`).
+//!
+//! The quartet drivers visit the unique quartets under the 8-fold permutational symmetry
+//! (`i>=j`, `k>=l`, `ij>=kl`) and report how many of them have been skipped.
+use crate::CINTR2CDATA;
+use crate::engine::IntegralEngine;
+
+// slots of atm and bas
+const PTR_COORD: usize = 1;
+const ATOM_OF: usize = 0;
+const NPRIM_OF: usize = 2;
+const PTR_EXP: usize = 5;
+
+/// The precomputed screening data of a basis set.
+#[derive(Clone,Debug)]
+pub struct Screening {
+    pub nbas: usize,
+    /// Schwarz bounds `Q_ij` of all shell pairs, `[nbas, nbas]`
+    pub schwarz: Vec<f64>,
+    /// the shell pairs surviving the overlap screening, `[nbas, nbas]`
+    pub pair_mask: Vec<bool>,
+    /// the quartets with `Q_ij Q_kl` below this threshold are skipped
+    pub threshold: f64,
+    /// the offsets of the basis functions of the shells
+    pub ao_loc: Vec<usize>,
+    /// the largest `|D|` of each shell block over all density matrices, `[nbas, nbas]`
+    pub dm_max: Option<Vec<f64>>,
+}
+
+/// How many quartets were computed and skipped by a screened driver.
+#[derive(Clone,Copy,Debug,Default)]
+pub struct ScreeningStats {
+    /// the number of unique shell quartets visited
+    pub n_total: usize,
+    /// the quartets skipped by the shell-pair overlap screening
+    pub n_skipped_pair: usize,
+    /// the quartets skipped by the Schwarz screening
+    pub n_skipped_schwarz: usize,
+    /// the quartets skipped by the density-weighted Schwarz screening
+    pub n_skipped_density: usize,
+}
+
+impl ScreeningStats {
+    pub fn n_skipped(&self) -> usize {
+        self.n_skipped_pair + self.n_skipped_schwarz + self.n_skipped_density
+    }
+    pub fn n_computed(&self) -> usize {
+        self.n_total - self.n_skipped()
+    }
+}
+
+impl Screening {
+    pub fn schwarz_ij(&self, i: i32, j: i32) -> f64 {
+        self.schwarz[i as usize + self.nbas*j as usize]
+    }
+    pub fn pair_is_significant(&self, i: i32, j: i32) -> bool {
+        self.pair_mask[i as usize + self.nbas*j as usize]
+    }
+    /// whether the quartet `(ij|kl)` can be skipped
+    pub fn skip(&self, i: i32, j: i32, k: i32, l: i32) -> bool {
+        let q = self.schwarz_ij(i,j)*self.schwarz_ij(k,l);
+        !self.pair_is_significant(i,j) || !self.pair_is_significant(k,l)
+            || q < self.threshold || q*self.density_bound(i,j,k,l) < self.threshold
+    }
+    /// Enable the density-weighted bounds for the Coulomb and exchange builds with the density matrices `dms`,
+    /// each of which is a `column-major` matrix of `[nao, nao]`.
+    pub fn with_density(mut self, dms: &[Vec<f64>]) -> Screening {
+        let nbas = self.nbas;
+        let nao = self.ao_loc[nbas];
+        let mut dm_max = vec![0.0_f64; nbas*nbas];
+        for ish in 0..nbas {
+            for jsh in 0..nbas {
+                let d = &mut dm_max[ish + nbas*jsh];
+                for dm in dms {
+                    for j in self.ao_loc[jsh]..self.ao_loc[jsh+1] {
+                        *d = dm[nao*j + self.ao_loc[ish]..nao*j + self.ao_loc[ish+1]].iter().fold(*d, |acc, x| acc.max(x.abs()));
+                    }
+                }
+            }
+        }
+        self.dm_max = Some(dm_max);
+        self
+    }
+    /// the largest density-matrix element coupled to `(ij|kl)` in either the Coulomb or the
+    /// exchange contraction, or 1.0 without the density-weighted bounds
+    pub fn density_bound(&self, i: i32, j: i32, k: i32, l: i32) -> f64 {
+        match &self.dm_max {
+            None => 1.0,
+            Some(dm_max) => {
+                let d = |p: i32, q: i32| dm_max[p as usize + self.nbas*q as usize].max(dm_max[q as usize + self.nbas*p as usize]);
+                d(i,j).max(d(k,l)).max(d(i,k)).max(d(i,l)).max(d(j,k)).max(d(j,l))
+            }
+        }
+    }
+}
+
+impl CINTR2CDATA {
+    /// The Schwarz bounds `Q_ij = sqrt(max|(ij|ij)|)` of all shell pairs, `[nbas, nbas]`.
+    ///
+    /// The 2e optimizer is (re-)initialized by this driver.
+    pub fn schwarz_bounds(&mut self) -> Vec<f64> {
+        schwarz_bounds(self)
+    }
+
+    /// The overlap estimate `exp(-a b/(a+b) R_ij^2)` of all shell pairs, `[nbas, nbas]`,
+    /// using the most diffuse primitive exponent of each shell.
+    pub fn shell_pair_overlap_estimate(&self) -> Vec<f64> {
+        let atm = self.get_atm();
+        let bas = self.get_bas();
+        let env = self.get_env();
+        let nbas = bas.len();
+        let shell_info: Vec<([f64;3], f64)> = bas.iter().map(|ibas| {
+            let ptr = atm[ibas[ATOM_OF] as usize][PTR_COORD] as usize;
+            let exp_start = ibas[PTR_EXP] as usize;
+            let exp_min = env[exp_start..exp_start + ibas[NPRIM_OF] as usize].iter().fold(f64::MAX, |acc, e| acc.min(*e));
+            ([env[ptr], env[ptr+1], env[ptr+2]], exp_min)
+        }).collect();
+        let mut estimate = vec![0.0; nbas*nbas];
+        for i in 0..nbas {
+            for j in 0..nbas {
+                let (ri, ai) = shell_info[i];
+                let (rj, aj) = shell_info[j];
+                let r2: f64 = ri.iter().zip(rj.iter()).map(|(x,y)| (x-y)*(x-y)).sum();
+                estimate[i + nbas*j] = (-ai*aj/(ai+aj)*r2).exp();
+            }
+        }
+        estimate
+    }
+
+    /// Prepare the screening data, skipping the quartets with `Q_ij Q_kl < schwarz_threshold`
+    /// and the shell pairs with an overlap estimate below `overlap_threshold`.
+    pub fn screening(&mut self, schwarz_threshold: f64, overlap_threshold: f64) -> Screening {
+        let nbas = self.c_nbas as usize;
+        let schwarz = self.schwarz_bounds();
+        let pair_mask = self.shell_pair_overlap_estimate().iter().map(|s| *s >= overlap_threshold).collect();
+        Screening {nbas, schwarz, pair_mask, threshold: schwarz_threshold, ao_loc: self.ao_loc(), dm_max: None}
+    }
+
+    /// `cint_ijkl_by_shell` for the quartets surviving the screening, `None` otherwise.
+    pub fn cint_ijkl_screened(&mut self, i: i32, j: i32, k: i32, l: i32, screening: &Screening) -> Option<Vec<f64>> {
+        if screening.skip(i,j,k,l) {
+            None
+        } else {
+            Some(self.cint_ijkl_by_shell(i,j,k,l))
+        }
+    }
+
+    /// Visit all unique quartets (`i>=j`, `k>=l`, `ij>=kl`) surviving the screening and pass
+    /// the integrals of each of them to `f(i,j,k,l,&buf)`.
+    ///
+    /// The 2e optimizer is (re-)initialized by this driver.
+    pub fn for_each_screened_quartet<F>(&mut self, screening: &Screening, f: F) -> ScreeningStats
+    where F: FnMut(i32,i32,i32,i32,&[f64]) {
+        for_each_screened_quartet(self, screening, f)
+    }
+}
+
+/// The Schwarz bounds `Q_ij = sqrt(max|(ij|ij)|)` of all shell pairs of any engine, `[nbas, nbas]`.
+///
+/// "int2e" is prepared by this driver.
+pub fn schwarz_bounds<E: IntegralEngine>(engine: &mut E) -> Vec<f64> {
+    let nbas = engine.nbas();
+    let mut schwarz = vec![0.0; nbas*nbas];
+    engine.prepare("int2e");
+    let mut buf = vec![0.0; engine.max_block_size("int2e")];
+    let mut workspace = engine.workspace("int2e");
+    for i in 0..nbas {
+        for j in 0..=i {
+            let size = engine.eval_4c2e(i, j, i, j, &mut buf, &mut workspace);
+            let dij = (size as f64).sqrt() as usize;
+            let q = (0..dij).fold(0.0_f64, |acc, ij| acc.max(buf[ij + dij*ij].abs())).sqrt();
+            schwarz[i + nbas*j] = q;
+            schwarz[j + nbas*i] = q;
+        }
+    }
+    schwarz
+}
+
+/// The Schwarz screening of any engine, skipping the quartets with `Q_ij Q_kl < schwarz_threshold`
+/// (the shell-pair overlap screening of `CINTR2CDATA::screening` needs the basis data).
+pub fn schwarz_screening<E: IntegralEngine>(engine: &mut E, schwarz_threshold: f64) -> Screening {
+    let nbas = engine.nbas();
+    let schwarz = schwarz_bounds(engine);
+    Screening {nbas, schwarz, pair_mask: vec![true; nbas*nbas], threshold: schwarz_threshold, ao_loc: engine.ao_loc(), dm_max: None}
+}
+
+/// Visit all unique quartets (`i>=j`, `k>=l`, `ij>=kl`) of any engine surviving the screening and
+/// pass the integrals of each of them to `f(i,j,k,l,&buf)`.
+///
+/// "int2e" is prepared by this driver.
+pub fn for_each_screened_quartet<E, F>(engine: &mut E, screening: &Screening, mut f: F) -> ScreeningStats
+where E: IntegralEngine, F: FnMut(i32,i32,i32,i32,&[f64]) {
+    let nbas = engine.nbas() as i32;
+    let mut stats = ScreeningStats::default();
+    engine.prepare("int2e");
+    let mut buf = vec![0.0; engine.max_block_size("int2e")];
+    let mut workspace = engine.workspace("int2e");
+    for i in 0..nbas {
+        for j in 0..=i {
+            for k in 0..=i {
+                let l_max = if k == i {j} else {k};
+                for l in 0..=l_max {
+                    stats.n_total += 1;
+                    if !screening.pair_is_significant(i,j) || !screening.pair_is_significant(k,l) {
+                        stats.n_skipped_pair += 1;
+                        continue;
+                    }
+                    let q = screening.schwarz_ij(i,j)*screening.schwarz_ij(k,l);
+                    if q < screening.threshold {
+                        stats.n_skipped_schwarz += 1;
+                        continue;
+                    }
+                    if q*screening.density_bound(i,j,k,l) < screening.threshold {
+                        stats.n_skipped_density += 1;
+                        continue;
+                    }
+                    let size = engine.eval_4c2e(i as usize, j as usize, k as usize, l as usize, &mut buf, &mut workspace);
+                    f(i,j,k,l,&buf[..size]);
+                }
+            }
+        }
+    }
+    stats
+}
+
+#[test]
+pub fn test_screening() {
+    let mut cint_data = crate::test_h2_data();
+    let nbas = cint_data.get_nbas() as usize;
+    let npair = nbas*(nbas+1)/2;
+    let n_unique = npair*(npair+1)/2;
+
+    let screening = cint_data.screening(0.0, 0.0);
+    let mut n_visited = 0;
+    let stats = cint_data.for_each_screened_quartet(&screening, |_,_,_,_,_| {n_visited += 1});
+    assert_eq!(stats.n_total, n_unique);
+    assert_eq!(stats.n_skipped(), 0);
+    assert_eq!(n_visited, n_unique);
+
+    // the Schwarz bounds are never violated
+    let screening = cint_data.screening(1.0e-10, 1.0e-10);
+    cint_data.for_each_screened_quartet(&screening, |i,j,k,l,buf| {
+        let bound = screening.schwarz_ij(i,j)*screening.schwarz_ij(k,l);
+        assert!(buf.iter().all(|x| x.abs() <= bound*(1.0+1.0e-10)));
+    });
+
+    let screening = cint_data.screening(1.0e10, 0.0);
+    let stats = cint_data.for_each_screened_quartet(&screening, |_,_,_,_,_| {});
+    assert_eq!(stats.n_skipped_schwarz, n_unique);
+    assert_eq!(stats.n_computed(), 0);
+    cint_data.final_c2r();
+}